@@ -86,6 +86,11 @@ impl ECIES {
             &SecretKey::from(secret_key.to_owned()),
         );
 
+        // anything shorter than a nonce can't be a cipher made by us
+        if bytes.len() < 24 {
+            return Err(crypto_box::aead::Error);
+        }
+
         let bytes_split = bytes.split_at(bytes.len() - 24);
 
         let mut nonce: GenericArray<u8, typenum::U24> = *GenericArray::from_slice(&bytes_split.1);
@@ -142,6 +147,11 @@ impl ECIES {
             &SecretKey::from(secret_key.to_owned()),
         );
 
+        // anything shorter than a nonce can't be a cipher made by us
+        if bytes.len() < 24 {
+            return Err(crypto_box::aead::Error);
+        }
+
         let bytes_split = bytes.split_at(bytes.len() - 24);
 
         let mut nonce: GenericArray<u8, typenum::U24> = *GenericArray::from_slice(&bytes_split.1);
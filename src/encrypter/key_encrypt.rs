@@ -3,7 +3,7 @@ use chacha20poly1305::aead::{Aead, NewAead};
 use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
 use rand::prelude::*;
 use rand_hc::Hc128Rng;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
 ///
 /// And object that uses a cryptographically generated rng (Hc128Rng)
@@ -13,12 +13,27 @@ use zeroize::Zeroize;
 ///
 /// Its use in this app is for generating a key from the password
 /// in order to encrypt the private key of the vault.
+/// Optionally a keyfile can be mixed in as the argon2 secret, in which case
+/// both the password and the keyfile are needed to decrypt the key.
 ///
 pub struct KeyEncrypt<'a> {
     rng: Hc128Rng,
     config_argon: Config<'a>,
 }
 
+impl<'a> KeyEncrypt<'a> {
+    ///
+    /// Creates a new KeyEncrypt object that also needs the keyfile,
+    /// given as the hash of its content, on top of the password.
+    ///
+    pub fn new_with_keyfile(keyfile_hash: &'a [u8]) -> KeyEncrypt<'a> {
+        let mut key_encrypt = KeyEncrypt::new();
+        key_encrypt.config_argon.secret = keyfile_hash;
+
+        key_encrypt
+    }
+}
+
 impl KeyEncrypt<'_> {
     ///
     /// Creates a new KeyEncrypt object with necessary configuration needed
@@ -41,6 +56,26 @@ impl KeyEncrypt<'_> {
         KeyEncrypt { rng, config_argon }
     }
 
+    ///
+    /// Creates a new KeyEncrypt object for recovery codes.
+    /// They're random and long enough that they don't need the heavy
+    /// settings used for passwords, so unlocking with one stays quick.
+    ///
+    pub fn new_for_recovery_code() -> KeyEncrypt<'static> {
+        let mut key_encrypt = KeyEncrypt::new();
+        key_encrypt.config_argon.mem_cost = 16384;
+        key_encrypt.config_argon.time_cost = 2;
+
+        key_encrypt
+    }
+
+    ///
+    /// Whether a keyfile is mixed into the key derivation.
+    ///
+    pub fn has_keyfile(&self) -> bool {
+        !self.config_argon.secret.is_empty()
+    }
+
     ///
     /// Takes the given passwords, generates a 32 bytes key
     /// and encrypts the given key with XChaCha20Poly1305.
@@ -95,7 +130,7 @@ impl KeyEncrypt<'_> {
         &mut self,
         password: &[u8],
         encrypted_key: &[u8],
-    ) -> Result<Vec<u8>, argon2::Error> {
+    ) -> Result<Zeroizing<Vec<u8>>, argon2::Error> {
         // split the arrays
         let bytes_split1 = encrypted_key.split_at(encrypted_key.len() - 1024);
         let bytes_split2 = bytes_split1.0.split_at(bytes_split1.0.len() - 24);
@@ -106,8 +141,9 @@ impl KeyEncrypt<'_> {
 
         // hash the password with the raw method so only the hash comes out, in order to use it for
         // decryption
-        let mut hash = match argon2::hash_raw(password, &salt, &self.config_argon) {
-            Ok(hash) => hash,
+        // wiped when it goes out of scope, also when the decryption fails
+        let hash = match argon2::hash_raw(password, &salt, &self.config_argon) {
+            Ok(hash) => Zeroizing::new(hash),
             Err(error) => return Err(error),
         };
 
@@ -120,14 +156,11 @@ impl KeyEncrypt<'_> {
 
         // decrypt cipher
         let plaintext = match aead.decrypt(&nonce, cipher) {
-            Ok(text) => text,
+            Ok(text) => Zeroizing::new(text),
             Err(_) => return Err(argon2::Error::DecodingFail),
         };
 
-        // clean memory
-        hash.zeroize();
-
-        // return the decrypted bytes
+        // return the decrypted bytes, wiped once they're dropped
         Ok(plaintext)
     }
 }
@@ -1,2 +1,3 @@
 pub mod ecies;
 pub mod key_encrypt;
+pub mod stream_encrypt;
@@ -0,0 +1,218 @@
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::{Key, XChaCha20Poly1305};
+use rand::prelude::*;
+use rand_hc::Hc128Rng;
+use std::io::{Read, Write};
+use zeroize::Zeroize;
+
+/// The size of the plain chunks the content is split into.
+pub const CHUNK_SIZE: usize = 65536;
+
+/// XChaCha20Poly1305 nonces are 24 bytes, STREAM BE32 uses 5 of them for the counter and last
+/// chunk flag.
+pub const NONCE_PREFIX_SIZE: usize = 19;
+
+#[derive(Debug)]
+pub enum StreamErr {
+    ReadFailed(u16),
+    WriteFailed(u16),
+    EncryptFailed(u16),
+    Corrupted(u16),
+}
+
+///
+/// An object for encrypting big contents in chunks using the
+/// XChaCha20Poly1305 STREAM construction. Every chunk is individually
+/// authenticated and the last one is flagged, so reordering, dropping
+/// or truncating chunks is detected when decrypting.
+///
+/// The content is never held in memory as a whole, only one chunk at a time.
+///
+pub struct StreamEncrypt {
+    rng: Hc128Rng,
+}
+
+impl StreamEncrypt {
+    ///
+    /// Creates a new StreamEncrypt object using a cryptographically
+    /// generated rng (Hc128Rng)
+    ///
+    pub fn new() -> StreamEncrypt {
+        let rng = Hc128Rng::from_entropy();
+
+        StreamEncrypt { rng }
+    }
+
+    ///
+    /// Generates a new random key and nonce prefix for a stream.
+    ///
+    pub fn gen_key(&mut self) -> ([u8; 32], [u8; NONCE_PREFIX_SIZE]) {
+        let mut key: [u8; 32] = [0; 32];
+        let mut nonce_prefix: [u8; NONCE_PREFIX_SIZE] = [0; NONCE_PREFIX_SIZE];
+        self.rng.fill_bytes(&mut key);
+        self.rng.fill_bytes(&mut nonce_prefix);
+
+        (key, nonce_prefix)
+    }
+
+    ///
+    /// Generates a random id for a stream, used to find its chunks.
+    ///
+    pub fn gen_id(&mut self) -> [u8; 16] {
+        let mut id: [u8; 16] = [0; 16];
+        self.rng.fill_bytes(&mut id);
+
+        id
+    }
+
+    ///
+    /// Reads everything from the given reader in chunks, encrypts every chunk
+    /// and hands it over to the given sink (for example to store it in a db).
+    /// Returns the number of chunks and the number of plain bytes read.
+    ///
+    pub fn encrypt_reader<R: Read, F: FnMut(u32, Vec<u8>) -> Result<(), StreamErr>>(
+        &mut self,
+        reader: &mut R,
+        key: &[u8; 32],
+        nonce_prefix: &[u8; NONCE_PREFIX_SIZE],
+        mut sink: F,
+    ) -> Result<(u32, u64), StreamErr> {
+        let mut encryptor = EncryptorBE32::<XChaCha20Poly1305>::new(
+            Key::from_slice(key),
+            GenericArray::from_slice(nonce_prefix),
+        );
+
+        let mut current: Vec<u8> = vec![0; CHUNK_SIZE];
+        let mut next: Vec<u8> = vec![0; CHUNK_SIZE];
+        let mut current_len = read_chunk(reader, &mut current)?;
+        let mut index: u32 = 0;
+        let mut size: u64 = 0;
+
+        // read one chunk ahead so we know which one is the last
+        loop {
+            let next_len = read_chunk(reader, &mut next)?;
+            size += current_len as u64;
+
+            if next_len == 0 {
+                let cipher = match encryptor.encrypt_last(&current[..current_len]) {
+                    Ok(cipher) => cipher,
+                    Err(err) => {
+                        eprintln!("ERROR: Failed to encrypt the last chunk of the content!\n{err}");
+                        return Err(StreamErr::EncryptFailed(0));
+                    }
+                };
+                sink(index, cipher)?;
+                break;
+            }
+
+            let cipher = match encryptor.encrypt_next(&current[..current_len]) {
+                Ok(cipher) => cipher,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to encrypt a chunk of the content!\n{err}");
+                    return Err(StreamErr::EncryptFailed(0));
+                }
+            };
+            sink(index, cipher)?;
+
+            std::mem::swap(&mut current, &mut next);
+            current_len = next_len;
+            index += 1;
+        }
+
+        // clean memory
+        current.zeroize();
+        next.zeroize();
+
+        Ok((index + 1, size))
+    }
+
+    ///
+    /// Decrypts the given number of chunks, fetched one by one from the
+    /// given source, and writes them to the given writer as they get decrypted.
+    ///
+    pub fn decrypt_to_writer<W: Write, F: FnMut(u32) -> Result<Vec<u8>, StreamErr>>(
+        &mut self,
+        writer: &mut W,
+        key: &[u8; 32],
+        nonce_prefix: &[u8; NONCE_PREFIX_SIZE],
+        chunks: u32,
+        mut source: F,
+    ) -> Result<u64, StreamErr> {
+        // there is always at least one chunk, even for empty contents
+        if chunks == 0 {
+            eprintln!("ERROR: The content stream has no chunks, it can't be valid!");
+            return Err(StreamErr::Corrupted(0));
+        }
+
+        let mut decryptor = DecryptorBE32::<XChaCha20Poly1305>::new(
+            Key::from_slice(key),
+            GenericArray::from_slice(nonce_prefix),
+        );
+        let mut size: u64 = 0;
+
+        for index in 0..chunks - 1 {
+            let cipher = source(index)?;
+            let plain = match decryptor.decrypt_next(&cipher[..]) {
+                Ok(plain) => plain,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to decrypt a chunk of the content!\n{err}");
+                    return Err(StreamErr::Corrupted(0));
+                }
+            };
+            size += write_chunk(writer, plain)?;
+        }
+
+        // the last chunk is flagged, so a truncated stream fails here
+        let cipher = source(chunks - 1)?;
+        let plain = match decryptor.decrypt_last(&cipher[..]) {
+            Ok(plain) => plain,
+            Err(err) => {
+                eprintln!("ERROR: Failed to decrypt the last chunk of the content!\n{err}");
+                return Err(StreamErr::Corrupted(0));
+            }
+        };
+        size += write_chunk(writer, plain)?;
+
+        Ok(size)
+    }
+}
+
+///
+/// Writes a decrypted chunk and wipes it from memory afterwards,
+/// returns the number of bytes written.
+///
+fn write_chunk<W: Write>(writer: &mut W, mut plain: Vec<u8>) -> Result<u64, StreamErr> {
+    let written = plain.len() as u64;
+    let result = writer.write_all(&plain);
+    plain.zeroize();
+
+    match result {
+        Ok(_) => Ok(written),
+        Err(err) => {
+            eprintln!("ERROR: Failed to write a decrypted chunk of the content!\n{err}");
+            Err(StreamErr::WriteFailed(0))
+        }
+    }
+}
+
+///
+/// Fills the given buffer as much as possible from the reader,
+/// returns how many bytes were read, 0 meaning there is nothing left.
+///
+fn read_chunk<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, StreamErr> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a chunk of the content!\n{err}");
+                return Err(StreamErr::ReadFailed(0));
+            }
+        };
+    }
+
+    Ok(filled)
+}
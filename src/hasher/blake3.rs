@@ -1,4 +1,6 @@
 use blake3;
+use std::fs;
+use std::io;
 
 ///
 /// Hash a string with blake3.
@@ -17,3 +19,15 @@ pub fn hash_str(text: &str) -> [u8; 32] {
 pub fn hash_bytes(bytes: &[u8]) -> [u8; 32] {
     blake3::hash(bytes).as_bytes().to_owned()
 }
+
+///
+/// Hash the content of a file with blake3,
+/// the file is read in pieces, not as a whole.
+///
+pub fn hash_file(path: &str) -> Result<[u8; 32], io::Error> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher.finalize().as_bytes().to_owned())
+}
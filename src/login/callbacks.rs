@@ -1,6 +1,6 @@
 use super::core::LoginError;
 use crate::vault;
-use crate::{encrypter::key_encrypt::KeyEncrypt, utils::db};
+use crate::{utils, utils::db};
 use fltk::frame::Frame;
 use fltk::group::Flex;
 use fltk::prelude::*;
@@ -8,15 +8,17 @@ use fltk::text;
 use fltk::window::DoubleWindow;
 use fltk::{button, input};
 use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
 
 ///
 /// The callback function called when you hit the login button.
 /// Takes the username and password and checks to see if there exists a vault
-/// and if there is it tries logging in to it.
+/// and if there is it tries logging in to it, with the keyfile if one was selected.
 ///
 pub fn login_button_callback(
     input_user_arc: Arc<Mutex<input::Input>>,
     input_pass_arc: Arc<Mutex<input::SecretInput>>,
+    keyfile_path_arc: Arc<Mutex<String>>,
     text_status_arc: Arc<Mutex<text::TextDisplay>>,
     wind_clone: &mut DoubleWindow,
     is_windows: bool,
@@ -39,7 +41,7 @@ pub fn login_button_callback(
             return;
         }
     };
-    let input_pass = match input_pass_arc.lock() {
+    let mut input_pass = match input_pass_arc.lock() {
         Ok(object) => object,
         Err(err) => {
             eprintln!("ERROR: Failed to get value under input_pass ARC!\n{err}");
@@ -50,9 +52,22 @@ pub fn login_button_callback(
         }
     };
 
+    let keyfile_path = match keyfile_path_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under keyfile_path ARC!\n{err}");
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: There was a Poison Error, try to restart!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+    };
+
     // parse some stuff
     let vault_name = input_user.value();
-    let password = input_pass.value();
+    // the password is wiped from memory once it's used, and from the input too
+    let password = Zeroizing::new(input_pass.value());
+    input_pass.set_value("");
 
     // drop the arc references since they are not needed anymore
     drop(input_user);
@@ -74,65 +89,115 @@ pub fn login_button_callback(
         }
     };
 
-    // create necessary objects
-    let mut key_encrypt = KeyEncrypt::new();
-
-    // super::core::create_vault(&vault_name, &password, &mut estashdb, &mut argon, &mut ecies, &mut key_encrypt, is_windows);
-    let vault =
-        match super::core::login_vault(&vault_name, &password, &mut estashdb, &mut key_encrypt) {
-            Ok(v) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: Successfully logging in!");
-                text_status.set_buffer(text_status_buf);
-                v
-            }
-            Err(LoginError::CorruptedVault(_)) => {
+    // read the keyfile, if one was selected
+    let keyfile_hash = if keyfile_path.is_empty() {
+        None
+    } else {
+        match utils::keyfile::read_keyfile(&keyfile_path) {
+            Some(hash) => Some(hash),
+            None => {
                 let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: This vault is corrupted!");
+                text_status_buf.set_text("Status: Failed to read the keyfile!");
                 text_status.set_buffer(text_status_buf);
                 return;
             }
-            Err(LoginError::WrongCredentials(_)) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: The input credentials are wrong!");
-                text_status.set_buffer(text_status_buf);
-                return;
-            }
-            Err(LoginError::CorruptedVaultsDb(_)) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: The vaults db is corrupted!");
-                text_status.set_buffer(text_status_buf);
-                return;
-            }
-            Err(LoginError::CorruptedPubKeyDb(_)) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: The pub key db is corrupted!");
-                text_status.set_buffer(text_status_buf);
-                return;
-            }
-            Err(LoginError::FailedToAccessVaultsDb(_)) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: Failed to access the vaults db!");
-                text_status.set_buffer(text_status_buf);
-                return;
-            }
-            Err(LoginError::FailedToAccessPubKeyDb(_)) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: Failed to access the pub key db!");
-                text_status.set_buffer(text_status_buf);
-                return;
-            }
-            Err(LoginError::FailedToAccessPrivKeyDb(_)) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: Failed to access the priv key db!");
-                text_status.set_buffer(text_status_buf);
-                return;
-            }
-        };
+        }
+    };
+
+    // super::core::create_vault(&vault_name, &password, &mut estashdb, &mut argon, &mut ecies, &mut key_encrypt, is_windows);
+    let (vault, failed_logins) = match super::core::login_vault(
+        &vault_name,
+        &password,
+        &mut estashdb,
+        keyfile_hash.as_ref(),
+    ) {
+        Ok(v) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: Successfully logging in!");
+            text_status.set_buffer(text_status_buf);
+            v
+        }
+        Err(LoginError::CorruptedVault(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: This vault is corrupted!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::WrongCredentials(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: The input credentials are wrong!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::CorruptedVaultsDb(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: The vaults db is corrupted!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::CorruptedPubKeyDb(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: The pub key db is corrupted!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::FailedToAccessVaultsDb(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: Failed to access the vaults db!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::FailedToAccessPubKeyDb(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: Failed to access the pub key db!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::TeamVault(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text(
+                "Status: This is a team vault, log into your own vault and open it from Vault > Team!",
+            );
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::FailedToAccessPrivKeyDb(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: Failed to access the priv key db!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::LockedOut(_)) => {
+            let remaining = super::core::lockout_remaining(&estashdb, &vault_name);
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text(&format!(
+                "Status: Too many failed logins, try again in {remaining} seconds!"
+            ));
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::RecoveryRequired(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text(
+                "Status: Too many failed logins, only a recovery code unlocks this vault now!",
+            );
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+        Err(LoginError::Destroyed(_)) => {
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: Too many failed logins, the vault was erased!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+    };
+
+    // the vault window opens the vaults db again, sled only lets one open it at a time
+    drop(estashdb);
 
     // open vault window
     wind_clone.hide();
-    let mut vault_wind = vault::window::create(is_windows, vault, wind_clone);
+    let mut vault_wind = vault::window::create(is_windows, vault, failed_logins, wind_clone);
     vault_wind.show();
 }
 
@@ -147,6 +212,7 @@ pub fn resize_callback(
     back_button_arc: Arc<Mutex<button::Button>>,
     input_user_arc: Arc<Mutex<input::Input>>,
     input_pass_arc: Arc<Mutex<input::SecretInput>>,
+    but_keyfile_arc: Arc<Mutex<button::Button>>,
     but_login_arc: Arc<Mutex<button::Button>>,
     text_status_arc: Arc<Mutex<text::TextDisplay>>,
     flex: &mut Flex,
@@ -191,6 +257,17 @@ pub fn resize_callback(
         }
     };
 
+    match but_keyfile_arc.lock() {
+        Ok(mut o) => {
+            o.set_label_size(font_size / 2);
+        }
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error changing but_keyfile text size, arc poison error!\n{err}"
+            );
+        }
+    };
+
     match but_login_arc.lock() {
         Ok(mut o) => {
             o.set_label_size(font_size);
@@ -1,12 +1,20 @@
 use crate::hasher::blake3;
-use crate::utils::Vault;
-use crate::{encrypter::key_encrypt::KeyEncrypt, utils::db};
+use crate::utils::key_slots::KeySlotKind;
+use crate::utils::login_guard::{self, LockoutPolicy};
+use crate::utils::{self, db, key_slots, secure::SecretKey, Vault};
 use serde::{Deserialize, Serialize};
+use std::fs;
 use std::str;
 
 #[derive(Serialize, Deserialize)]
 pub struct VaultDbValue {
     id: u64,
+    // whether a keyfile is needed on top of the password
+    #[serde(default)]
+    keyfile: bool,
+    // team vaults have no password, they're opened from a member's vault
+    #[serde(default)]
+    team: bool,
 }
 
 // Extension method for Vec obj in order to be able to easily convert between Vec<u8> and [u8;
@@ -32,19 +40,26 @@ pub enum LoginError {
     FailedToAccessPrivKeyDb(u16),
     CorruptedPubKeyDb(u16),
     FailedToAccessPubKeyDb(u16),
+    TeamVault(u16),
+    LockedOut(u16),
+    RecoveryRequired(u16),
+    Destroyed(u16),
 }
 
 ///
 /// Takes the parsed input from the window and tries logging you in.
 /// Checks if a vault with that name is present
-/// if it is then it checks the password.
+/// if it is then it tries the password (and the keyfile if one was given)
+/// on every key slot of the vault, a recovery code works in place of the password.
+/// Failed logins are counted, after a few of them the vault has to wait between attempts
+/// and its lockout policy applies, on success it returns how many there were since the last one.
 ///
 pub fn login_vault(
     vault_name: &str,
     password: &str,
     estashdb: &mut db::EstashDb,
-    key_encrypt: &mut KeyEncrypt,
-) -> Result<Vault, LoginError> {
+    keyfile_hash: Option<&[u8; 32]>,
+) -> Result<(Vault, u32), LoginError> {
     let vault_name_hashed = blake3::hash_str(vault_name);
     // check if the vault is present in the database
     match estashdb.vault_db.contains_key(vault_name_hashed) {
@@ -91,28 +106,45 @@ pub fn login_vault(
 
     // extract data into separate values
     let vault_id = vault_value.id;
+    let vault_has_keyfile = vault_value.keyfile;
 
-    // extract and decrypt the private encryption key for the vault
-    let vault_priv_key_encrypted = match estashdb.vault_priv_key_db.get(vault_name_hashed) {
-        Ok(data_unchecked) => match data_unchecked {
-            Some(data_raw) => data_raw.to_vec(),
-            None => {
-                eprintln!("ERROR: There is a key with the vault's name hash, but it has no data, meaning the vault priv key db is corrupted!");
-                return Err(LoginError::CorruptedVaultsDb(0));
-            }
-        },
-        Err(error) => {
-            eprintln!("ERROR: There was an error accessing priv key db!\n{error}");
-            return Err(LoginError::FailedToAccessPrivKeyDb(0));
-        }
+    if vault_value.team {
+        eprintln!("ERROR: The vault is a team vault, it can't be unlocked with a password!");
+        return Err(LoginError::TeamVault(0));
+    }
+
+    // refuse before doing any key derivation while the vault has to wait
+    let attempts = match login_guard::get_attempts(&estashdb.vault_db, &vault_name_hashed) {
+        Ok(attempts) => attempts,
+        Err(_) => return Err(LoginError::FailedToAccessVaultsDb(0)),
     };
-    let vault_priv_key = match key_encrypt
-        .decrypt_with_password_bytes(password.as_bytes(), &vault_priv_key_encrypted)
-    {
-        Ok(key) => key,
-        Err(error) => {
-            eprintln!("ERROR: The credentials are wrong!\n{error}");
-            return Err(LoginError::WrongCredentials(0));
+    if attempts.delay_remaining() > 0 {
+        eprintln!("ERROR: Too many failed logins, the vault has to wait before the next one!");
+        return Err(LoginError::LockedOut(0));
+    }
+    let only_kind =
+        if attempts.policy == LockoutPolicy::RequireRecovery && attempts.policy_applies() {
+            Some(KeySlotKind::RecoveryCode)
+        } else {
+            None
+        };
+
+    // try every key slot of the vault, the password field also takes a recovery code
+    let vault_priv_key = match key_slots::unlock(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        vault_has_keyfile,
+        password,
+        keyfile_hash,
+        only_kind,
+    ) {
+        Ok(Some((_, key))) => key,
+        Ok(None) => {
+            eprintln!("ERROR: The credentials are wrong, no key slot could be opened!");
+            return Err(failed_login(estashdb, &vault_name_hashed, vault_id));
+        }
+        Err(_) => {
+            return Err(LoginError::FailedToAccessPrivKeyDb(0));
         }
     };
 
@@ -131,9 +163,10 @@ pub fn login_vault(
         }
     };
 
-    let vault_priv_key_bytes: [u8; 32] = match Vec::to_owned_array(vault_priv_key) {
-        Ok(key) => key,
-        Err(_) => {
+    // copied straight into its locked page, the decrypted buffer is wiped when dropped
+    let vault_priv_key = match SecretKey::from_slice(&vault_priv_key) {
+        Some(key) => key,
+        None => {
             eprintln!("ERROR: The vault private key we got is not 32 bytes, meaning the vault is probably corrupted, unrecoverable!");
             return Err(LoginError::CorruptedVault(0));
         }
@@ -147,10 +180,91 @@ pub fn login_vault(
         }
     };
 
-    Ok(Vault {
-        vault_name: vault_name.to_string(),
-        id: vault_id,
-        priv_key: vault_priv_key_bytes,
-        pub_key: vault_pub_key_bytes,
-    })
+    let failed_logins = match login_guard::record_success(&estashdb.vault_db, &vault_name_hashed) {
+        Ok(failed) => failed,
+        Err(_) => return Err(LoginError::FailedToAccessVaultsDb(0)),
+    };
+
+    Ok((
+        Vault {
+            vault_name: vault_name.to_string(),
+            id: vault_id,
+            priv_key: vault_priv_key,
+            pub_key: vault_pub_key_bytes,
+            has_keyfile: vault_has_keyfile,
+            is_team: false,
+        },
+        failed_logins,
+    ))
+}
+
+///
+/// Returns the seconds left before the vault can be tried again.
+///
+pub fn lockout_remaining(estashdb: &db::EstashDb, vault_name: &str) -> u64 {
+    let vault_name_hashed = blake3::hash_str(vault_name);
+
+    match login_guard::get_attempts(&estashdb.vault_db, &vault_name_hashed) {
+        Ok(attempts) => attempts.delay_remaining(),
+        Err(_) => 0,
+    }
+}
+
+///
+/// Counts the failed login and applies the lockout policy of the vault
+/// if there were enough of them, returns the error to show for it.
+///
+fn failed_login(
+    estashdb: &db::EstashDb,
+    vault_name_hashed: &[u8; 32],
+    vault_id: u64,
+) -> LoginError {
+    let attempts = match login_guard::record_failure(&estashdb.vault_db, vault_name_hashed) {
+        Ok(attempts) => attempts,
+        Err(_) => return LoginError::FailedToAccessVaultsDb(0),
+    };
+    if !attempts.policy_applies() {
+        return LoginError::WrongCredentials(0);
+    }
+
+    match attempts.policy {
+        LockoutPolicy::SelfDestruct => match destroy_vault(estashdb, vault_name_hashed, vault_id) {
+            Ok(_) => LoginError::Destroyed(0),
+            Err(err) => err,
+        },
+        _ => LoginError::RecoveryRequired(0),
+    }
+}
+
+///
+/// Erases the vault for good, the key slots go first so nothing can unlock it
+/// even if removing the rest fails.
+///
+fn destroy_vault(
+    estashdb: &db::EstashDb,
+    vault_name_hashed: &[u8; 32],
+    vault_id: u64,
+) -> Result<(), LoginError> {
+    eprintln!("ERROR: Too many failed logins, erasing the vault as its policy says!");
+    if key_slots::remove_all_slots(&estashdb.vault_priv_key_db, vault_name_hashed).is_err() {
+        return Err(LoginError::FailedToAccessPrivKeyDb(0));
+    }
+    if let Err(err) = estashdb.vault_pub_key_db.remove(vault_name_hashed) {
+        eprintln!("ERROR: Failed to remove the public key of the erased vault!\n{err}");
+        return Err(LoginError::FailedToAccessPubKeyDb(0));
+    }
+    if let Err(err) = estashdb.vault_db.remove(vault_name_hashed) {
+        eprintln!("ERROR: Failed to remove the erased vault from the vaults db!\n{err}");
+        return Err(LoginError::FailedToAccessVaultsDb(0));
+    }
+    let _ = login_guard::remove_attempts(&estashdb.vault_db, vault_name_hashed);
+    let _ = estashdb.vault_db.flush();
+    let _ = estashdb.vault_pub_key_db.flush();
+
+    let vault_path = utils::get_vaults_root_path(utils::is_windows()) + &vault_id.to_string();
+    if let Err(err) = fs::remove_dir_all(vault_path) {
+        eprintln!("ERROR: Failed to remove the directory of the erased vault, its contents can't be decrypted anymore though!\n{err}");
+    }
+
+    Ok(())
 }
@@ -1,6 +1,7 @@
+use crate::utils;
 use fltk::{
-    enums::Color,
     button::Button,
+    enums::Color,
     frame,
     group::{self, Flex},
     prelude::*,
@@ -48,6 +49,13 @@ pub fn create(
     input_pass.set_color(Color::from_rgb(31, 31, 31));
     input_pass.set_text_color(Color::from_rgb(140, 140, 140));
 
+    // keyfile button
+    let mut but_keyfile = Button::default().with_label(utils::keyfile::NO_KEYFILE_LABEL);
+    let but_keyfile_arc = Arc::new(Mutex::new(but_keyfile.clone()));
+    but_keyfile.set_label_color(Color::from_rgb(140, 140, 140));
+    but_keyfile.set_color(Color::from_rgb(43, 43, 43));
+    let keyfile_path_arc = Arc::new(Mutex::new(String::new()));
+
     // login button
     let mut but_login = Button::default().with_label("Login");
     but_login.set_color(but_login.color().lighter());
@@ -90,6 +98,7 @@ pub fn create(
     // clone the needed arc references
     let input_user_arc_clone = input_user_arc.clone();
     let input_pass_arc_clone = input_pass_arc.clone();
+    let but_keyfile_arc_clone = but_keyfile_arc.clone();
     let but_login_arc_clone = but_login_arc.clone();
     let text_status_arc_clone = text_status_arc.clone();
     let back_button_arc_clone = back_button_arc.clone();
@@ -102,6 +111,7 @@ pub fn create(
             back_button_arc_clone.clone(),
             input_user_arc_clone.clone(),
             input_pass_arc_clone.clone(),
+            but_keyfile_arc_clone.clone(),
             but_login_arc_clone.clone(),
             text_status_arc_clone.clone(),
             &mut flex,
@@ -111,18 +121,25 @@ pub fn create(
     // clone the needed arc references
     let input_user_arc_clone = input_user_arc.clone();
     let input_pass_arc_clone = input_pass_arc.clone();
+    let keyfile_path_arc_clone = keyfile_path_arc.clone();
     let text_status_arc_clone = text_status_arc.clone();
     // login button callback
     but_login.set_callback(move |_| {
         super::callbacks::login_button_callback(
             input_user_arc_clone.clone(),
             input_pass_arc_clone.clone(),
+            keyfile_path_arc_clone.clone(),
             text_status_arc_clone.clone(),
             &mut wind_clone,
             is_windows,
         )
     });
 
+    // keyfile button callback
+    but_keyfile.set_callback(move |b| {
+        utils::keyfile::keyfile_button_callback(b, keyfile_path_arc.clone());
+    });
+
     // set back button callback
     back_button.set_callback(move |_| {
         wind_clone2.hide();
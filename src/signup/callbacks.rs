@@ -1,20 +1,24 @@
 use super::core::SingupError;
+use crate::hasher::blake3;
 use crate::{
     encrypter::{ecies::ECIES, key_encrypt::KeyEncrypt},
-    utils::db,
+    utils::{self, db},
 };
 use fltk::{button, frame::Frame, group::Flex, input, prelude::*, text::TextDisplay};
 use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
 
 ///
 /// Callback function called when you hit the signup function
 /// Takes the username and make a vault with that name
-/// and a set of encryption keys encrypted with a key derived from the password.
+/// and a set of encryption keys encrypted with a key derived from the password
+/// (and the keyfile, if one was selected).
 ///
 pub fn singup_button_callback(
     input_user_arc: Arc<Mutex<input::Input>>,
     input_pass_arc: Arc<Mutex<input::SecretInput>>,
     input_pass_again_arc: Arc<Mutex<input::SecretInput>>,
+    keyfile_path_arc: Arc<Mutex<String>>,
     text_status_arc: Arc<Mutex<TextDisplay>>,
     is_windows: bool,
 ) {
@@ -36,7 +40,7 @@ pub fn singup_button_callback(
             return;
         }
     };
-    let input_pass = match input_pass_arc.lock() {
+    let mut input_pass = match input_pass_arc.lock() {
         Ok(object) => object,
         Err(err) => {
             eprintln!("ERROR: Failed to get value under input_pass ARC!\n{err}");
@@ -46,7 +50,7 @@ pub fn singup_button_callback(
             return;
         }
     };
-    let input_pass_again = match input_pass_again_arc.lock() {
+    let mut input_pass_again = match input_pass_again_arc.lock() {
         Ok(object) => object,
         Err(err) => {
             eprintln!("ERROR: Failed to get value under input_pass_again ARC!\n{err}");
@@ -57,10 +61,24 @@ pub fn singup_button_callback(
         }
     };
 
+    let keyfile_path = match keyfile_path_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under keyfile_path ARC!\n{err}");
+            let mut text_status_buf = fltk::text::TextBuffer::default();
+            text_status_buf.set_text("Status: There was a Poison Error, try to restart!");
+            text_status.set_buffer(text_status_buf);
+            return;
+        }
+    };
+
     // parse some stuff
     let vault_name = input_user.value();
-    let password = input_pass.value();
-    let password_again = input_pass_again.value();
+    // the passwords are wiped from memory once they're used, and from the inputs too
+    let password = Zeroizing::new(input_pass.value());
+    let password_again = Zeroizing::new(input_pass_again.value());
+    input_pass.set_value("");
+    input_pass_again.set_value("");
 
     // drop arc references
     drop(input_user);
@@ -84,9 +102,27 @@ pub fn singup_button_callback(
         }
     };
 
+    // read the keyfile, if one was selected
+    let keyfile_hash = if keyfile_path.is_empty() {
+        None
+    } else {
+        match utils::keyfile::read_keyfile(&keyfile_path) {
+            Some(hash) => Some(hash),
+            None => {
+                let mut text_status_buf = fltk::text::TextBuffer::default();
+                text_status_buf.set_text("Status: Failed to read the keyfile!");
+                text_status.set_buffer(text_status_buf);
+                return;
+            }
+        }
+    };
+
     // create necessary objects
     let mut ecies = ECIES::new();
-    let mut key_encrypt = KeyEncrypt::new();
+    let mut key_encrypt = match &keyfile_hash {
+        Some(hash) => KeyEncrypt::new_with_keyfile(hash),
+        None => KeyEncrypt::new(),
+    };
 
     if password == password_again {
         match super::core::create_vault(
@@ -97,9 +133,26 @@ pub fn singup_button_callback(
             &mut key_encrypt,
             is_windows,
         ) {
-            Ok(_) => {
+            Ok(vault) => {
+                // every vault gets a recovery code, usable instead of the password (and keyfile)
+                let recovery_code = utils::key_slots::generate_recovery_code();
                 let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: Successfully created account!");
+                match utils::key_slots::add_slot(
+                    &estashdb.vault_priv_key_db,
+                    &blake3::hash_str(&vault.vault_name),
+                    "Recovery code",
+                    utils::key_slots::KeySlotKind::RecoveryCode,
+                    &recovery_code,
+                    None,
+                    &vault.priv_key,
+                ) {
+                    Ok(_) => text_status_buf.set_text(&format!(
+                        "Status: Successfully created account!\nRecovery code (write it down, it's shown only once):\n{recovery_code}"
+                    )),
+                    Err(_) => text_status_buf.set_text(
+                        "Status: Successfully created account, but failed to store the recovery code!",
+                    ),
+                };
                 text_status.set_buffer(text_status_buf);
             }
             Err(SingupError::FailedToStorePublicKey(_)) => {
@@ -122,11 +175,6 @@ pub fn singup_button_callback(
                 text_status_buf.set_text("Status: Failed to access vaults db!");
                 text_status.set_buffer(text_status_buf);
             }
-            Err(SingupError::CorruptedVaultsDb(_)) => {
-                let mut text_status_buf = fltk::text::TextBuffer::default();
-                text_status_buf.set_text("Status: Vaults db is corrupted!");
-                text_status.set_buffer(text_status_buf);
-            }
             Err(SingupError::AlreadyExists(_)) => {
                 let mut text_status_buf = fltk::text::TextBuffer::default();
                 text_status_buf.set_text("Status: A vault with this credentials already exists!");
@@ -162,6 +210,7 @@ pub fn window_callback(
     input_user_arc: Arc<Mutex<input::Input>>,
     input_pass_arc: Arc<Mutex<input::SecretInput>>,
     input_pass_again_arc: Arc<Mutex<input::SecretInput>>,
+    but_keyfile_arc: Arc<Mutex<button::Button>>,
     but_signup_arc: Arc<Mutex<button::Button>>,
     text_status_arc: Arc<Mutex<TextDisplay>>,
     flex: &mut Flex,
@@ -215,6 +264,17 @@ pub fn window_callback(
         }
     };
 
+    match but_keyfile_arc.lock() {
+        Ok(mut o) => {
+            o.set_label_size(font_size / 2);
+        }
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error changing but_keyfile text size, arc poison error!\n{err}"
+            );
+        }
+    };
+
     match but_signup_arc.lock() {
         Ok(mut o) => {
             o.set_label_size(font_size);
@@ -1,22 +1,23 @@
 use crate::hasher::blake3;
 use crate::{
     encrypter::{ecies::ECIES, key_encrypt::KeyEncrypt},
-    utils::{self, db},
+    utils::{self, db, secure::SecretKey, Vault},
 };
 use serde::{Deserialize, Serialize};
-use std::str;
 use std::{fs, process};
 
 #[derive(Serialize, Deserialize)]
 pub struct VaultDbValue {
     id: u64,
+    // whether a keyfile is needed on top of the password
+    #[serde(default)]
+    keyfile: bool,
 }
 
 #[derive(Debug)]
 pub enum SingupError {
     AlreadyExists(u16),
     FailedToAccessVaultsDb(u16),
-    CorruptedVaultsDb(u16),
     FailedToCreateVault(u16),
     FailedToStoreCredentials(u16),
     FailedToStorePrivateKey(u16),
@@ -28,6 +29,8 @@ pub enum SingupError {
 /// Takes the info parsed from the window and creates a vault with it.
 /// Encrypted with keys encrypted with a key derived from the password.
 /// Very secure.
+/// If the key_encrypt has a keyfile the vault will need it for unlocking too.
+/// Returns the newly created vault, already unlocked.
 ///
 pub fn create_vault(
     vault_name: &str,
@@ -36,7 +39,7 @@ pub fn create_vault(
     ecies: &mut ECIES,
     key_encrypt: &mut KeyEncrypt,
     is_windows: bool,
-) -> Result<bool, SingupError> {
+) -> Result<Vault, SingupError> {
     // check if hashed vault_name isn't already present
     let hashed_vault_name = blake3::hash_str(&vault_name);
 
@@ -52,48 +55,9 @@ pub fn create_vault(
         }
     }
 
-    // get the last db id and increment that by 1 if there is one, if not set it to 1
-    let new_id = match estashdb.vault_db.last() {
-        Ok(entry) => match entry {
-            Some(ent) => {
-                let value_str = match str::from_utf8(&ent.1) {
-                    Ok(key) => key,
-                    Err(error) => {
-                        eprintln!("ERROR: The stored value in vaults db is not in utf8 meaning that vaults db is corrupted!\n{error}");
-                        return Err(SingupError::CorruptedVaultsDb(0));
-                    }
-                };
-
-                let parsed: serde_json::Value = match serde_json::from_str(value_str) {
-                    Ok(value) => value,
-                    Err(error) => {
-                        eprintln!("ERROR: Failed to covert the value to a json object, vaults db is probably corrupted!\n{error}");
-                        return Err(SingupError::CorruptedVaultsDb(0));
-                    }
-                };
-
-                let previous_id = match parsed.get("id") {
-                    Some(id) => id,
-                    None => {
-                        eprintln!("ERROR: Somehow successfully parsed the data under the key into the struct, but there is no id field?");
-                        return Err(SingupError::UnknownError(0));
-                    }
-                };
-
-                let previous_id_int = match previous_id.as_u64() {
-                    Some(id) => id,
-                    None => {
-                        eprintln!("ERROR: The previous id doesn't fit in an u64, meaning vaults db is probably corrupted!");
-                        return Err(SingupError::CorruptedVaultsDb(0));
-                    }
-                };
-
-                let new_id = previous_id_int + 1;
-
-                new_id
-            }
-            None => 1,
-        },
+    // get the biggest db id and increment that by 1, 1 if there are no vaults yet
+    let new_id = match estashdb.next_vault_id() {
+        Ok(id) => id,
         Err(error) => {
             eprintln!("ERROR: There was an error accessing vaults db!\n{error}");
             return Err(SingupError::FailedToAccessVaultsDb(0));
@@ -101,7 +65,10 @@ pub fn create_vault(
     };
 
     // create the vaule to store under the key (json of password and vault id)
-    let vault_value_obj = VaultDbValue { id: new_id };
+    let vault_value_obj = VaultDbValue {
+        id: new_id,
+        keyfile: key_encrypt.has_keyfile(),
+    };
     let vault_value_string = match serde_json::to_string(&vault_value_obj) {
         Ok(value) => value,
         Err(err) => {
@@ -114,7 +81,7 @@ pub fn create_vault(
     // gen key pair
     let key_pair = ecies.gen_key_pair();
     let public_key = key_pair.0;
-    let private_key = key_pair.1;
+    let mut private_key = key_pair.1;
 
     // encrypt private key
     let encrypted_private_key = match key_encrypt
@@ -254,5 +221,12 @@ pub fn create_vault(
         }
     };
 
-    Ok(true)
+    Ok(Vault {
+        vault_name: vault_name.to_string(),
+        id: new_id,
+        priv_key: SecretKey::new(&mut private_key),
+        pub_key: public_key,
+        has_keyfile: key_encrypt.has_keyfile(),
+        is_team: false,
+    })
 }
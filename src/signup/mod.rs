@@ -1,3 +1,3 @@
 mod callbacks;
-mod core;
+pub mod core;
 pub mod window;
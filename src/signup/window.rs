@@ -1,9 +1,11 @@
+use crate::utils;
 use fltk::{
     button::Button,
+    enums::Color,
     frame,
     group::{self, Flex},
     prelude::*,
-    window::{DoubleWindow, Window}, enums::Color,
+    window::{DoubleWindow, Window},
 };
 use std::sync::{Arc, Mutex};
 
@@ -55,6 +57,13 @@ pub fn create(
     input_pass_again.set_color(Color::from_rgb(31, 31, 31));
     input_pass_again.set_text_color(Color::from_rgb(140, 140, 140));
 
+    // keyfile button
+    let mut but_keyfile = Button::default().with_label(utils::keyfile::NO_KEYFILE_LABEL);
+    let but_keyfile_arc = Arc::new(Mutex::new(but_keyfile.clone()));
+    but_keyfile.set_label_color(Color::from_rgb(140, 140, 140));
+    but_keyfile.set_color(Color::from_rgb(43, 43, 43));
+    let keyfile_path_arc = Arc::new(Mutex::new(String::new()));
+
     // signup button
     let mut but_signup = Button::default().with_label("Signup");
     but_signup.set_color(but_signup.color().lighter());
@@ -96,6 +105,7 @@ pub fn create(
     let input_pass_arc_clone = input_pass_arc.clone();
     let input_pass_again_arc_clone = input_pass_again_arc.clone();
     let text_status_arc_clone = text_status_arc.clone();
+    let but_keyfile_arc_clone = but_keyfile_arc.clone();
     let but_signup_arc_clone = but_signup_arc.clone();
     let back_button_arc_clone = back_button_arc.clone();
     // window callback
@@ -108,6 +118,7 @@ pub fn create(
             input_user_arc_clone.clone(),
             input_pass_arc_clone.clone(),
             input_pass_again_arc_clone.clone(),
+            but_keyfile_arc_clone.clone(),
             but_signup_arc_clone.clone(),
             text_status_arc_clone.clone(),
             &mut flex,
@@ -118,6 +129,7 @@ pub fn create(
     let input_user_arc_clone = input_user_arc.clone();
     let input_pass_arc_clone = input_pass_arc.clone();
     let input_pass_again_arc_clone = input_pass_again_arc.clone();
+    let keyfile_path_arc_clone = keyfile_path_arc.clone();
     let text_status_arc_clone = Arc::new(Mutex::new(text_status.clone()));
     // signup button callback
     but_signup.set_callback(move |_| {
@@ -125,11 +137,17 @@ pub fn create(
             input_user_arc_clone.clone(),
             input_pass_arc_clone.clone(),
             input_pass_again_arc_clone.clone(),
+            keyfile_path_arc_clone.clone(),
             text_status_arc_clone.clone(),
             is_windows,
         )
     });
 
+    // keyfile button callback
+    but_keyfile.set_callback(move |b| {
+        utils::keyfile::keyfile_button_callback(b, keyfile_path_arc.clone());
+    });
+
     // clone the needed objects
     let mut wind_clone = wind.clone();
     // set back button callback
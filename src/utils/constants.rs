@@ -2,7 +2,9 @@ pub const VAULT_DB_PATH_UNIX: &str = "data/vault_db";
 pub const VAULT_PUB_KEY_DB_PATH_UNIX: &str = "data/vault_key_db";
 pub const VAULT_PRIV_KEY_DB_PATH_UNIX: &str = "data/vault_priv_key_db";
 pub const VAULTS_ROOT_PATH_UNIX: &str = "data/vaults/";
+pub const MANAGED_ROOT_PATH_UNIX: &str = "data/managed/";
 pub const VAULT_DB_PATH_WINDOWS: &str = "data\\vault_db";
 pub const VAULT_PUB_KEY_DB_PATH_WINDOWS: &str = "data\\vault_key_db";
 pub const VAULT_PRIV_KEY_DB_PATH_WINDOWS: &str = "data\\vault_priv_key_db";
 pub const VAULTS_ROOT_PATH_WINDOWS: &str = "data\\vaults\\";
+pub const MANAGED_ROOT_PATH_WINDOWS: &str = "data\\managed\\";
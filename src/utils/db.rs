@@ -77,4 +77,24 @@ impl EstashDb {
             })
         }
     }
+
+    ///
+    /// The next free vault id, one after the biggest one in the vaults db.
+    /// The vaults are ordered by the hash of their name, so the last one isn't the newest.
+    ///
+    pub fn next_vault_id(&self) -> Result<u64, sled::Error> {
+        let mut biggest_id = 0;
+        for entry in self.vault_db.iter() {
+            let value = entry?.1;
+            let parsed: serde_json::Value = match serde_json::from_slice(&value) {
+                Ok(parsed) => parsed,
+                Err(_) => continue,
+            };
+            if let Some(id) = parsed.get("id").and_then(|id| id.as_u64()) {
+                biggest_id = biggest_id.max(id);
+            }
+        }
+
+        Ok(biggest_id + 1)
+    }
 }
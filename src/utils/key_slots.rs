@@ -0,0 +1,451 @@
+use crate::encrypter::key_encrypt::KeyEncrypt;
+use rand::prelude::*;
+use rand_hc::Hc128Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+/// The name of the tree in the priv key db holding the additional key slots.
+pub const KEY_SLOTS_TREE: &str = "key_slots";
+
+/// The id of the slot kept directly under the vault name hash in the priv key db,
+/// the one made at signup.
+pub const PRIMARY_SLOT_ID: u64 = 0;
+
+/// Crockford's base32 alphabet, no I, L, O or U so codes are easy to read back.
+const RECOVERY_CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Random bytes in a recovery code, 160 bits.
+const RECOVERY_CODE_BYTES: usize = 20;
+
+/// The id of the slot that opened and the private key it held, wiped once dropped.
+pub type UnlockedSlot = (u64, Zeroizing<Vec<u8>>);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum KeySlotKind {
+    Password,
+    RecoveryCode,
+}
+
+///
+/// A copy of the vault private key, encrypted with a key derived
+/// from a password or a recovery code. Any slot unlocks the vault.
+///
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeySlot {
+    pub id: u64,
+    pub label: String,
+    pub kind: KeySlotKind,
+    // whether the keyfile is mixed in, recovery codes never need it
+    pub keyfile: bool,
+    #[serde(with = "serde_bytes")]
+    pub wrapped_key: Vec<u8>,
+}
+
+impl KeySlot {
+    ///
+    /// A line describing the slot, without anything secret in it.
+    ///
+    pub fn describe(&self) -> String {
+        let kind = match self.kind {
+            KeySlotKind::Password if self.keyfile => "password + keyfile",
+            KeySlotKind::Password => "password",
+            KeySlotKind::RecoveryCode => "recovery code",
+        };
+
+        format!("Slot {}: {} ({kind})", self.id, self.label)
+    }
+}
+
+#[derive(Debug)]
+pub enum KeySlotErr {
+    DbInaccesible(u16),
+    Corrupted(u16),
+    NotFound(u16),
+    LastSlot(u16),
+    EncryptFailed(u16),
+}
+
+///
+/// Lists every key slot of the vault, the primary one first (if it wasn't revoked).
+///
+pub fn list_slots(
+    priv_key_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    vault_has_keyfile: bool,
+) -> Result<Vec<KeySlot>, KeySlotErr> {
+    let mut slots = Vec::new();
+
+    match priv_key_db.get(vault_name_hashed) {
+        Ok(Some(wrapped_key)) => slots.push(KeySlot {
+            id: PRIMARY_SLOT_ID,
+            label: "Primary password".to_string(),
+            kind: KeySlotKind::Password,
+            keyfile: vault_has_keyfile,
+            wrapped_key: wrapped_key.to_vec(),
+        }),
+        Ok(None) => (),
+        Err(err) => {
+            eprintln!("ERROR: There was an error accessing priv key db!\n{err}");
+            return Err(KeySlotErr::DbInaccesible(0));
+        }
+    };
+
+    let slots_tree = open_slots_tree(priv_key_db)?;
+    for slot in slots_tree.scan_prefix(vault_name_hashed) {
+        let value = match slot {
+            Ok(kv) => kv.1,
+            Err(err) => {
+                eprintln!("ERROR: There was an error reading a key slot!\n{err}");
+                return Err(KeySlotErr::DbInaccesible(0));
+            }
+        };
+        match rmp_serde::from_slice::<KeySlot>(&value) {
+            Ok(slot) => slots.push(slot),
+            Err(err) => {
+                eprintln!(
+                    "ERROR: A key slot is not stored in estash's format, skipping it!\n{err}"
+                );
+                continue;
+            }
+        };
+    }
+
+    Ok(slots)
+}
+
+///
+/// Tries every slot of the vault with the given secret (and keyfile),
+/// returns the id of the slot that opened and the decrypted private key, wiped once dropped.
+/// If only_kind is given the slots of the other kind aren't tried at all.
+/// Every slot tried costs a full key derivation, so the recovery code slots are only
+/// tried for a secret shaped like a recovery code, and then before the password ones.
+///
+pub fn unlock(
+    priv_key_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    vault_has_keyfile: bool,
+    secret: &str,
+    keyfile_hash: Option<&[u8; 32]>,
+    only_kind: Option<KeySlotKind>,
+) -> Result<Option<UnlockedSlot>, KeySlotErr> {
+    let mut slots = list_slots(priv_key_db, vault_name_hashed, vault_has_keyfile)?;
+    let mut code = normalize_recovery_code(secret);
+    let code_shaped = is_recovery_code_shaped(&code);
+    code.zeroize();
+    if code_shaped {
+        // stable, so the password slots keep their order
+        slots.sort_by_key(|slot| slot.kind != KeySlotKind::RecoveryCode);
+    } else {
+        // it can't be the code of any slot, generated codes all have the same length
+        slots.retain(|slot| slot.kind != KeySlotKind::RecoveryCode);
+    }
+
+    for slot in slots {
+        if matches!(only_kind, Some(kind) if kind != slot.kind) {
+            continue;
+        }
+        let result = match slot.kind {
+            KeySlotKind::RecoveryCode => {
+                let mut code = normalize_recovery_code(secret);
+                let result = KeyEncrypt::new_for_recovery_code()
+                    .decrypt_with_password_bytes(code.as_bytes(), &slot.wrapped_key);
+                code.zeroize();
+                result
+            }
+            KeySlotKind::Password => match (slot.keyfile, keyfile_hash) {
+                (true, Some(hash)) => KeyEncrypt::new_with_keyfile(hash)
+                    .decrypt_with_password_bytes(secret.as_bytes(), &slot.wrapped_key),
+                // the slot can't be opened without the keyfile, so don't bother
+                (true, None) => continue,
+                (false, _) => KeyEncrypt::new()
+                    .decrypt_with_password_bytes(secret.as_bytes(), &slot.wrapped_key),
+            },
+        };
+
+        if let Ok(key) = result {
+            return Ok(Some((slot.id, key)));
+        }
+    }
+
+    Ok(None)
+}
+
+///
+/// Encrypts the private key with a key derived from the given secret
+/// and stores it as a new slot, returns the id of the new slot.
+///
+pub fn add_slot(
+    priv_key_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    label: &str,
+    kind: KeySlotKind,
+    secret: &str,
+    keyfile_hash: Option<&[u8; 32]>,
+    priv_key: &[u8; 32],
+) -> Result<u64, KeySlotErr> {
+    let wrapped_key = wrap_key(kind, secret, keyfile_hash, priv_key)?;
+
+    let id = match priv_key_db.generate_id() {
+        // 0 is the primary slot
+        Ok(id) => id + 1,
+        Err(err) => {
+            eprintln!("ERROR: Failed to generate an id for the key slot!\n{err}");
+            return Err(KeySlotErr::DbInaccesible(0));
+        }
+    };
+    let slot = KeySlot {
+        id,
+        label: label.to_owned(),
+        kind,
+        keyfile: kind == KeySlotKind::Password && keyfile_hash.is_some(),
+        wrapped_key,
+    };
+
+    store_slot(priv_key_db, vault_name_hashed, &slot)?;
+
+    Ok(id)
+}
+
+///
+/// Encrypts the private key again in an existing slot, with a new secret.
+/// The slot keeps its kind and whether it needs the keyfile.
+///
+pub fn rewrap_slot(
+    priv_key_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    slot: &KeySlot,
+    secret: &str,
+    keyfile_hash: Option<&[u8; 32]>,
+    priv_key: &[u8; 32],
+) -> Result<(), KeySlotErr> {
+    let keyfile_hash = if slot.keyfile { keyfile_hash } else { None };
+    if slot.keyfile && keyfile_hash.is_none() {
+        return Err(KeySlotErr::EncryptFailed(0));
+    }
+
+    let wrapped_key = wrap_key(slot.kind, secret, keyfile_hash, priv_key)?;
+
+    if slot.id == PRIMARY_SLOT_ID {
+        return match priv_key_db.insert(vault_name_hashed, wrapped_key) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                eprintln!("ERROR: Failed to store the private key!\n{err}");
+                Err(KeySlotErr::DbInaccesible(0))
+            }
+        };
+    }
+
+    let new_slot = KeySlot {
+        wrapped_key,
+        ..slot.to_owned()
+    };
+
+    store_slot(priv_key_db, vault_name_hashed, &new_slot)
+}
+
+///
+/// Removes a slot, the last one of a vault can't be removed.
+///
+pub fn revoke_slot(
+    priv_key_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    vault_has_keyfile: bool,
+    slot_id: u64,
+) -> Result<(), KeySlotErr> {
+    let slots = list_slots(priv_key_db, vault_name_hashed, vault_has_keyfile)?;
+    if !slots.iter().any(|slot| slot.id == slot_id) {
+        return Err(KeySlotErr::NotFound(0));
+    }
+    if slots.len() == 1 {
+        return Err(KeySlotErr::LastSlot(0));
+    }
+
+    let result = if slot_id == PRIMARY_SLOT_ID {
+        priv_key_db.remove(vault_name_hashed)
+    } else {
+        let slots_tree = open_slots_tree(priv_key_db)?;
+        slots_tree.remove(slot_key(vault_name_hashed, slot_id))
+    };
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to remove the key slot!\n{err}");
+            Err(KeySlotErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Removes every slot of the vault, after that nothing can unlock it.
+///
+pub fn remove_all_slots(
+    priv_key_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+) -> Result<(), KeySlotErr> {
+    if let Err(err) = priv_key_db.remove(vault_name_hashed) {
+        eprintln!("ERROR: Failed to remove the primary key slot!\n{err}");
+        return Err(KeySlotErr::DbInaccesible(0));
+    }
+
+    let slots_tree = open_slots_tree(priv_key_db)?;
+    for slot in slots_tree.scan_prefix(vault_name_hashed).keys() {
+        let removed = match slot {
+            Ok(key) => slots_tree.remove(key),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = removed {
+            eprintln!("ERROR: Failed to remove a key slot!\n{err}");
+            return Err(KeySlotErr::DbInaccesible(0));
+        }
+    }
+
+    match priv_key_db.flush() {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to flush the priv key db!\n{err}");
+            Err(KeySlotErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Generates a new random recovery code, formatted in groups of 4 characters
+/// so it can be written down easily.
+///
+pub fn generate_recovery_code() -> String {
+    let mut rng = Hc128Rng::from_entropy();
+    let mut bytes: [u8; RECOVERY_CODE_BYTES] = [0; RECOVERY_CODE_BYTES];
+    rng.fill_bytes(&mut bytes);
+
+    // 5 bytes make 8 characters
+    let mut code = String::new();
+    for group in bytes.chunks(5) {
+        let mut bits: u64 = 0;
+        for byte in group {
+            bits = (bits << 8) | *byte as u64;
+        }
+        for index in (0..8).rev() {
+            let value = ((bits >> (index * 5)) & 31) as usize;
+            code.push(RECOVERY_CODE_ALPHABET[value] as char);
+            if index == 4 {
+                code.push('-');
+            }
+        }
+        code.push('-');
+    }
+    code.pop();
+
+    bytes.zeroize();
+
+    code
+}
+
+///
+/// Brings a typed in recovery code to the form it was generated in,
+/// ignoring case, separators and the characters that are easy to mistake.
+///
+fn normalize_recovery_code(code: &str) -> String {
+    let mut normalized = String::new();
+    for c in code.chars() {
+        let c = match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+        };
+        if c.is_ascii() && RECOVERY_CODE_ALPHABET.contains(&(c as u8)) {
+            normalized.push(c);
+        }
+    }
+
+    // put the separators back where generate_recovery_code puts them
+    let mut formatted = String::new();
+    for (index, c) in normalized.chars().enumerate() {
+        if index > 0 && index % 4 == 0 {
+            formatted.push('-');
+        }
+        formatted.push(c);
+    }
+    normalized.zeroize();
+
+    formatted
+}
+
+///
+/// Whether the normalized secret has as many characters as a generated recovery code.
+///
+fn is_recovery_code_shaped(normalized: &str) -> bool {
+    normalized.chars().filter(|c| *c != '-').count() == RECOVERY_CODE_BYTES * 8 / 5
+}
+
+fn wrap_key(
+    kind: KeySlotKind,
+    secret: &str,
+    keyfile_hash: Option<&[u8; 32]>,
+    priv_key: &[u8; 32],
+) -> Result<Vec<u8>, KeySlotErr> {
+    let result = match kind {
+        KeySlotKind::RecoveryCode => {
+            let mut code = normalize_recovery_code(secret);
+            let result = KeyEncrypt::new_for_recovery_code()
+                .encrypt_with_password_bytes(code.as_bytes(), priv_key);
+            code.zeroize();
+            result
+        }
+        KeySlotKind::Password => match keyfile_hash {
+            Some(hash) => KeyEncrypt::new_with_keyfile(hash)
+                .encrypt_with_password_bytes(secret.as_bytes(), priv_key),
+            None => KeyEncrypt::new().encrypt_with_password_bytes(secret.as_bytes(), priv_key),
+        },
+    };
+
+    match result {
+        Ok(wrapped_key) => Ok(wrapped_key),
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error encrypting the private key for the key slot!\n{err}"
+            );
+            Err(KeySlotErr::EncryptFailed(0))
+        }
+    }
+}
+
+fn store_slot(
+    priv_key_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    slot: &KeySlot,
+) -> Result<(), KeySlotErr> {
+    let slots_tree = open_slots_tree(priv_key_db)?;
+
+    let value = match rmp_serde::to_vec_named(slot) {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the key slot!\n{err}");
+            return Err(KeySlotErr::Corrupted(0));
+        }
+    };
+
+    match slots_tree.insert(slot_key(vault_name_hashed, slot.id), value) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the key slot!\n{err}");
+            Err(KeySlotErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn slot_key(vault_name_hashed: &[u8; 32], slot_id: u64) -> Vec<u8> {
+    let mut key = vault_name_hashed.to_vec();
+    key.extend_from_slice(&slot_id.to_be_bytes());
+    key
+}
+
+fn open_slots_tree(priv_key_db: &sled::Db) -> Result<sled::Tree, KeySlotErr> {
+    match priv_key_db.open_tree(KEY_SLOTS_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the key slots tree of the priv key db!\n{err}");
+            Err(KeySlotErr::DbInaccesible(0))
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::hasher::blake3;
+use fltk::{button, dialog, prelude::*};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The label of the keyfile button while no keyfile is selected.
+pub const NO_KEYFILE_LABEL: &str = "Keyfile: none (optional)";
+
+///
+/// Brings up the native file explorer for picking a keyfile,
+/// returns the path of the picked file, if any.
+///
+pub fn choose_keyfile() -> Option<String> {
+    let mut file_dialog = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+    file_dialog.set_title("Select the keyfile");
+    file_dialog.show();
+
+    let path = file_dialog.filename().to_string_lossy().to_string();
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+///
+/// Reads the keyfile at the given path and returns the hash of its content,
+/// which is what gets mixed into the key derivation.
+///
+pub fn read_keyfile(path: &str) -> Option<[u8; 32]> {
+    match blake3::hash_file(path) {
+        Ok(hash) => Some(hash),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the keyfile!\n{err}");
+            None
+        }
+    }
+}
+
+///
+/// Callback for the keyfile buttons in the login and signup windows.
+/// Picks a keyfile, or if one is already picked, forgets about it.
+///
+pub fn keyfile_button_callback(
+    keyfile_button: &mut button::Button,
+    keyfile_path_arc: Arc<Mutex<String>>,
+) {
+    let mut keyfile_path = match keyfile_path_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under keyfile_path ARC!\n{err}");
+            return;
+        }
+    };
+
+    if !keyfile_path.is_empty() {
+        keyfile_path.clear();
+        keyfile_button.set_label(NO_KEYFILE_LABEL);
+        return;
+    }
+
+    if let Some(path) = choose_keyfile() {
+        let file_name = match Path::new(&path).file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => path.to_owned(),
+        };
+        keyfile_button.set_label(&format!("Keyfile: {file_name} (click to remove)"));
+        *keyfile_path = path;
+    }
+}
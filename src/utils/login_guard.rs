@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name of the tree in the vaults db holding the failed logins of every vault.
+pub const LOGIN_ATTEMPTS_TREE: &str = "login_attempts";
+
+/// Failed logins in a row allowed before having to wait between attempts.
+pub const FREE_ATTEMPTS: u32 = 3;
+
+/// The wait after the first failed login over the free ones, doubled after each one.
+const BASE_DELAY_SECS: u64 = 5;
+
+/// The longest wait between attempts.
+const MAX_DELAY_SECS: u64 = 60 * 60;
+
+/// The failed logins in a row after which the policy applies, if none was chosen.
+pub const DEFAULT_POLICY_AFTER: u32 = 10;
+
+///
+/// What happens once a vault had too many failed logins in a row,
+/// on top of the waits between attempts.
+///
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockoutPolicy {
+    #[default]
+    DelayOnly,
+    RequireRecovery,
+    SelfDestruct,
+}
+
+impl LockoutPolicy {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            LockoutPolicy::DelayOnly => "only wait between attempts",
+            LockoutPolicy::RequireRecovery => "require the recovery code",
+            LockoutPolicy::SelfDestruct => "erase the vault",
+        }
+    }
+}
+
+///
+/// The failed logins of a vault, kept outside of it since they're
+/// needed before it's unlocked.
+///
+#[derive(Serialize, Deserialize, Default)]
+pub struct LoginAttempts {
+    // failed logins since the last successful one
+    #[serde(default)]
+    pub failed: u32,
+    // unix time of the last failed login, in seconds
+    #[serde(default)]
+    pub last_failed: u64,
+    #[serde(default)]
+    pub policy: LockoutPolicy,
+    #[serde(default)]
+    pub policy_after: u32,
+}
+
+impl LoginAttempts {
+    ///
+    /// Seconds left before the vault can be tried again, never more than the delay
+    /// itself, so a clock that went back doesn't lock the vault for longer.
+    ///
+    pub fn delay_remaining(&self) -> u64 {
+        if self.failed < FREE_ATTEMPTS {
+            return 0;
+        }
+
+        let doublings = (self.failed - FREE_ATTEMPTS).min(16);
+        let delay = (BASE_DELAY_SECS << doublings).min(MAX_DELAY_SECS);
+
+        (self.last_failed.saturating_add(delay))
+            .saturating_sub(now())
+            .min(delay)
+    }
+
+    ///
+    /// Whether there were enough failed logins for the policy to apply.
+    ///
+    pub fn policy_applies(&self) -> bool {
+        self.policy != LockoutPolicy::DelayOnly && self.failed >= self.policy_after
+    }
+}
+
+#[derive(Debug)]
+pub enum LoginGuardErr {
+    DbInaccesible(u16),
+    Corrupted(u16),
+}
+
+///
+/// Reads the failed logins of the vault, a vault that never had one has none.
+/// A record that can't be read counts as all the free attempts used, so the waits
+/// still apply, and it's replaced by the next login, failed or not.
+///
+pub fn get_attempts(
+    vault_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+) -> Result<LoginAttempts, LoginGuardErr> {
+    let attempts_tree = open_attempts_tree(vault_db)?;
+
+    match attempts_tree.get(vault_name_hashed) {
+        Ok(Some(value)) => match rmp_serde::from_slice(&value) {
+            Ok(attempts) => Ok(attempts),
+            Err(err) => {
+                eprintln!("ERROR: The failed logins are not stored in estash's format, counting the free attempts as used!\n{err}");
+                Ok(LoginAttempts {
+                    failed: FREE_ATTEMPTS,
+                    ..LoginAttempts::default()
+                })
+            }
+        },
+        Ok(None) => Ok(LoginAttempts::default()),
+        Err(err) => {
+            eprintln!("ERROR: There was an error reading the failed logins!\n{err}");
+            Err(LoginGuardErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Counts a failed login, returns the updated attempts.
+///
+pub fn record_failure(
+    vault_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+) -> Result<LoginAttempts, LoginGuardErr> {
+    let mut attempts = get_attempts(vault_db, vault_name_hashed)?;
+    attempts.failed = attempts.failed.saturating_add(1);
+    attempts.last_failed = now();
+    store_attempts(vault_db, vault_name_hashed, &attempts)?;
+
+    Ok(attempts)
+}
+
+///
+/// Starts counting again after a successful login,
+/// returns how many failed logins there were since the last one.
+///
+pub fn record_success(
+    vault_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+) -> Result<u32, LoginGuardErr> {
+    let mut attempts = get_attempts(vault_db, vault_name_hashed)?;
+    let failed = attempts.failed;
+    if failed > 0 {
+        attempts.failed = 0;
+        store_attempts(vault_db, vault_name_hashed, &attempts)?;
+    }
+
+    Ok(failed)
+}
+
+///
+/// Sets what happens after the given number of failed logins in a row.
+///
+pub fn set_policy(
+    vault_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    policy: LockoutPolicy,
+    policy_after: u32,
+) -> Result<(), LoginGuardErr> {
+    let mut attempts = get_attempts(vault_db, vault_name_hashed)?;
+    attempts.policy = policy;
+    attempts.policy_after = policy_after;
+
+    store_attempts(vault_db, vault_name_hashed, &attempts)
+}
+
+///
+/// Forgets everything about the vault, for when it's removed.
+///
+pub fn remove_attempts(
+    vault_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+) -> Result<(), LoginGuardErr> {
+    let attempts_tree = open_attempts_tree(vault_db)?;
+
+    match attempts_tree.remove(vault_name_hashed) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to remove the failed logins!\n{err}");
+            Err(LoginGuardErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn store_attempts(
+    vault_db: &sled::Db,
+    vault_name_hashed: &[u8; 32],
+    attempts: &LoginAttempts,
+) -> Result<(), LoginGuardErr> {
+    let attempts_tree = open_attempts_tree(vault_db)?;
+    let value = match rmp_serde::to_vec_named(attempts) {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the failed logins!\n{err}");
+            return Err(LoginGuardErr::Corrupted(0));
+        }
+    };
+
+    // flushed right away, a crash must not forget a failed login
+    match attempts_tree.insert(vault_name_hashed, value) {
+        Ok(_) => match attempts_tree.flush() {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                eprintln!("ERROR: Failed to flush the failed logins!\n{err}");
+                Err(LoginGuardErr::DbInaccesible(0))
+            }
+        },
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the failed logins!\n{err}");
+            Err(LoginGuardErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn open_attempts_tree(vault_db: &sled::Db) -> Result<sled::Tree, LoginGuardErr> {
+    match vault_db.open_tree(LOGIN_ATTEMPTS_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the tree with the failed logins!\n{err}");
+            Err(LoginGuardErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    }
+}
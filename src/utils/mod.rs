@@ -1,6 +1,10 @@
 pub mod constants;
 pub mod db;
-use serde::{Deserialize, Serialize};
+pub mod key_slots;
+pub mod keyfile;
+pub mod login_guard;
+pub mod secure;
+use secure::SecretKey;
 
 const FORBIDDEN_WINDOWS_CHARS: [&str; 29] = [
     ">", "<", ":", "/", "|", "?", "*", "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4",
@@ -8,12 +12,18 @@ const FORBIDDEN_WINDOWS_CHARS: [&str; 29] = [
     "LPT8", "LPT9",
 ];
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+///
+/// An unlocked vault. The private key is locked in memory and wiped once the vault
+/// is dropped, which is why a vault can't be cloned or printed, share it behind an Arc.
+///
 pub struct Vault {
     pub vault_name: String,
     pub id: u64,
-    pub priv_key: [u8; 32],
+    pub priv_key: SecretKey,
     pub pub_key: [u8; 32],
+    pub has_keyfile: bool,
+    // team vaults are opened with a member's key instead of a password
+    pub is_team: bool,
 }
 
 ///
@@ -29,6 +39,79 @@ pub fn is_windows() -> bool {
     }
 }
 
+///
+/// Returns the name of this machine, or an empty string if it can't be found.
+///
+pub fn hostname() -> String {
+    // only sources that don't depend on how the app was started
+    if let Ok(name) = std::env::var("COMPUTERNAME") {
+        if !name.trim().is_empty() {
+            return name.trim().to_owned();
+        }
+    }
+
+    if let Ok(name) = std::fs::read_to_string("/etc/hostname") {
+        if !name.trim().is_empty() {
+            return name.trim().to_owned();
+        }
+    }
+
+    match std::process::Command::new("hostname").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_owned(),
+        Err(_) => String::new(),
+    }
+}
+
+///
+/// Returns the path of the folder holding every vault database,
+/// with a trailing path separator.
+///
+pub fn get_vaults_root_path(is_windows: bool) -> String {
+    if is_windows {
+        let document_dir = match dirs::document_dir() {
+            Some(dir) => dir,
+            None => {
+                eprintln!("ERROR: Failed to get document dir");
+                std::process::exit(200);
+            }
+        };
+        // unwrap here is alright
+        let estash_dir = document_dir.to_str().unwrap().to_owned() + "\\estash\\";
+        estash_dir + constants::VAULTS_ROOT_PATH_WINDOWS
+    } else {
+        let home_dir = match dirs::home_dir() {
+            Some(dir) => dir,
+            None => {
+                eprintln!("ERROR: Failed to get home dir");
+                std::process::exit(200);
+            }
+        };
+        // unwrap here is alright
+        let estash_dir = home_dir.to_str().unwrap().to_owned() + "/.estash/";
+        estash_dir + constants::VAULTS_ROOT_PATH_UNIX
+    }
+}
+
+///
+/// Returns the path of the folder holding the files estash manages, the ones
+/// symlinked to their install path, with a trailing path separator.
+/// It's next to the vaults root folder, not in it, so it's not taken for a vault.
+///
+pub fn get_managed_root_path(is_windows: bool) -> String {
+    let vaults_root_path = get_vaults_root_path(is_windows);
+    if is_windows {
+        let estash_dir = vaults_root_path
+            .strip_suffix(constants::VAULTS_ROOT_PATH_WINDOWS)
+            .unwrap_or(&vaults_root_path);
+        estash_dir.to_owned() + constants::MANAGED_ROOT_PATH_WINDOWS
+    } else {
+        let estash_dir = vaults_root_path
+            .strip_suffix(constants::VAULTS_ROOT_PATH_UNIX)
+            .unwrap_or(&vaults_root_path);
+        estash_dir.to_owned() + constants::MANAGED_ROOT_PATH_UNIX
+    }
+}
+
 ///
 /// Checks if the given path is valid on the current OS
 ///
@@ -0,0 +1,139 @@
+use std::alloc::{self, Layout};
+use std::ops::Deref;
+use std::ptr::NonNull;
+use zeroize::{Zeroize, Zeroizing};
+
+///
+/// A 32 byte secret key kept in a memory page of its own, locked in memory where
+/// the OS allows it so it's never written to swap, and wiped when dropped.
+/// Locking works on whole pages and isn't counted, so sharing a page with anything
+/// else would let one key's drop unlock the others.
+/// It can't be cloned or printed, so the key only ever lives in one place.
+///
+pub struct SecretKey {
+    page: NonNull<u8>,
+    layout: Layout,
+    locked: bool,
+}
+
+// the page is only ever reached through the SecretKey owning it, like a Box
+unsafe impl Send for SecretKey {}
+unsafe impl Sync for SecretKey {}
+
+impl SecretKey {
+    ///
+    /// Moves the key into a locked page and wipes the given copy.
+    ///
+    pub fn new(key: &mut [u8; 32]) -> SecretKey {
+        let page_size = page_size();
+        let layout = match Layout::from_size_align(page_size, page_size) {
+            Ok(layout) => layout,
+            Err(_) => panic!("The page size of the OS is not a power of two!"),
+        };
+        let page = match NonNull::new(unsafe { alloc::alloc_zeroed(layout) }) {
+            Some(page) => page,
+            None => alloc::handle_alloc_error(layout),
+        };
+
+        unsafe {
+            page.as_ptr()
+                .copy_from_nonoverlapping(key.as_ptr(), key.len());
+        }
+        key.zeroize();
+        let locked = lock_memory(page.as_ptr(), layout.size());
+
+        SecretKey {
+            page,
+            layout,
+            locked,
+        }
+    }
+
+    ///
+    /// Copies the key into a locked page, None if it's not 32 bytes.
+    /// The given copy is left to the caller to wipe, like a decrypted Zeroizing buffer.
+    ///
+    pub fn from_slice(key: &[u8]) -> Option<SecretKey> {
+        let mut key: Zeroizing<[u8; 32]> = Zeroizing::new(key.try_into().ok()?);
+
+        Some(SecretKey::new(&mut key))
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &[u8; 32] {
+        unsafe { &*(self.page.as_ptr() as *const [u8; 32]) }
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        unsafe {
+            std::slice::from_raw_parts_mut(self.page.as_ptr(), 32).zeroize();
+        }
+        if self.locked {
+            unlock_memory(self.page.as_ptr(), self.layout.size());
+        }
+        unsafe {
+            alloc::dealloc(self.page.as_ptr(), self.layout);
+        }
+    }
+}
+
+///
+/// The size of a memory page, what the OS locks at once.
+///
+fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as usize,
+            _ => 4096,
+        }
+    }
+    // windows always uses 4 KiB pages
+    #[cfg(not(unix))]
+    {
+        4096
+    }
+}
+
+///
+/// Locks the memory so it stays in RAM, returns false if the OS refused
+/// (like when over the limit of locked memory), the memory still works then.
+///
+pub fn lock_memory(ptr: *const u8, len: usize) -> bool {
+    #[cfg(unix)]
+    unsafe {
+        libc::mlock(ptr as *const libc::c_void, len) == 0
+    }
+    #[cfg(windows)]
+    unsafe {
+        winapi::um::memoryapi::VirtualLock(ptr as *mut winapi::ctypes::c_void, len) != 0
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (ptr, len);
+        false
+    }
+}
+
+///
+/// Unlocks memory locked with lock_memory, it has to be wiped before.
+///
+pub fn unlock_memory(ptr: *const u8, len: usize) {
+    #[cfg(unix)]
+    unsafe {
+        libc::munlock(ptr as *const libc::c_void, len);
+    }
+    #[cfg(windows)]
+    unsafe {
+        winapi::um::memoryapi::VirtualUnlock(ptr as *mut winapi::ctypes::c_void, len);
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (ptr, len);
+    }
+}
@@ -0,0 +1,542 @@
+use crate::encrypter::ecies::ECIES;
+use crate::hasher::blake3;
+use crate::utils::{db, Vault};
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+/// The name of the tree in the vault db holding the audit log, encrypted,
+/// under the big endian sequence number of each event.
+pub const AUDIT_LOG_TREE: &str = "audit_log";
+
+/// The name of the tree in the vaults db of this machine holding the newest event of the
+/// audit log of every vault, under the hash of its public key. It's kept outside of the
+/// vault so removing the newest events from the log can still be noticed.
+const AUDIT_HEADS_TREE: &str = "audit_heads";
+
+/// Context for deriving the key authenticating the newest event from the vault private key.
+const AUDIT_HEAD_CONTEXT: &str = "estash 2022-10 audit log head v1";
+
+/// The file extension used for exported audit logs, JSON lines.
+pub const AUDIT_EXPORT_FILE_EXTENSION: &str = "jsonl";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Opened,
+    Viewed,
+    Modified,
+    Installed,
+    Deleted,
+    FileSelected,
+    Imported,
+    Exported,
+    Uninstalled,
+}
+
+///
+/// One thing done to the vault. Every event holds the hash of the one before it
+/// as it's stored, so removing or reordering events breaks the chain.
+///
+#[derive(Serialize, Deserialize)]
+pub struct AuditEvent {
+    // unix time, in seconds
+    pub time: u64,
+    pub action: AuditAction,
+    #[serde(default)]
+    pub entry: String,
+    #[serde(default)]
+    pub detail: String,
+    #[serde(with = "serde_bytes")]
+    pub prev_hash: Vec<u8>,
+}
+
+///
+/// An event as it's exported, one JSON object per line.
+///
+#[derive(Serialize)]
+struct ExportedEvent<'a> {
+    seq: u64,
+    time: u64,
+    time_utc: String,
+    action: AuditAction,
+    entry: &'a str,
+    detail: &'a str,
+}
+
+///
+/// The log read back, with the sequence number of every event, and
+/// what couldn't be trusted in it.
+///
+pub struct AuditLog {
+    pub events: Vec<(u64, AuditEvent)>,
+    pub unreadable: usize,
+    // sequence numbers of the events that don't follow the one before them
+    pub broken_links: Vec<u64>,
+    // the newest events this machine stored are gone, or its record of them was changed
+    pub truncated: bool,
+}
+
+///
+/// The newest event of the log as this machine stored it.
+///
+struct AuditHead {
+    seq: u64,
+    hash: [u8; 32],
+    // whether it was stored with the key of the vault
+    authentic: bool,
+}
+
+#[derive(Debug)]
+pub enum AuditErr {
+    DbInaccesible(u16),
+    Corrupted(u16),
+    EncryptFailed(u16),
+}
+
+///
+/// Appends an event to the audit log of the vault, and remembers it
+/// as the newest one in the vaults db.
+///
+pub fn append_event(
+    vault_db: &Db,
+    estashdb: &db::EstashDb,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    action: AuditAction,
+    entry: &str,
+    detail: &str,
+) -> Result<(), AuditErr> {
+    let (seq, hash) = insert_event(vault_db, ecies, vault, now(), action, entry, detail)?;
+    store_head(estashdb, vault, seq, &hash)
+}
+
+///
+/// Appends every event of the log to the audit log of another vault,
+/// keeping their times, for when a vault is re-keyed.
+///
+pub fn copy_log(
+    vault_db: &Db,
+    estashdb: &db::EstashDb,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    log: &AuditLog,
+) -> Result<(), AuditErr> {
+    let mut head = None;
+    for (_, event) in &log.events {
+        head = Some(insert_event(
+            vault_db,
+            ecies,
+            vault,
+            event.time,
+            event.action,
+            &event.entry,
+            &event.detail,
+        )?);
+    }
+
+    match head {
+        Some((seq, hash)) => store_head(estashdb, vault, seq, &hash),
+        None => Ok(()),
+    }
+}
+
+///
+/// Appends an event from a callback, a failure is only logged
+/// so it doesn't get in the way of what was done.
+/// None of the arcs can be held by the caller, and neither can the vaults db,
+/// since it's opened here.
+///
+pub fn record(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    action: AuditAction,
+    entry: &str,
+    detail: &str,
+) {
+    let estashdb = match open_estashdb() {
+        Ok(estashdb) => estashdb,
+        Err(_) => {
+            eprintln!("ERROR: Failed to write to the audit log!");
+            return;
+        }
+    };
+
+    match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => {
+            if append_event(
+                &vault_db, &estashdb, &mut ecies, &vault, action, entry, detail,
+            )
+            .is_err()
+            {
+                eprintln!("ERROR: Failed to write to the audit log!");
+            }
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC, the audit event was lost!");
+        }
+    };
+}
+
+///
+/// Reads the whole audit log, checking that every event follows the one before it,
+/// and that the newest event this machine stored is still there.
+///
+pub fn read_log(
+    vault_db: &Db,
+    estashdb: &db::EstashDb,
+    ecies: &mut ECIES,
+    vault: &Vault,
+) -> Result<AuditLog, AuditErr> {
+    let log_tree = open_log_tree(vault_db)?;
+    let head = read_head(estashdb, vault)?;
+
+    let mut log = AuditLog {
+        events: Vec::new(),
+        unreadable: 0,
+        broken_links: Vec::new(),
+        truncated: false,
+    };
+    let mut head_found = false;
+    let mut expected_prev_hash = [0; 32];
+    for record in log_tree.iter() {
+        let (key, value) = match record {
+            Ok(kv) => kv,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read the audit log!\n{err}");
+                return Err(AuditErr::DbInaccesible(0));
+            }
+        };
+        let prev_hash = expected_prev_hash;
+        expected_prev_hash = blake3::hash_bytes(&value);
+
+        let seq = match key.as_ref().try_into() {
+            Ok(key) => u64::from_be_bytes(key),
+            Err(_) => {
+                log.unreadable += 1;
+                continue;
+            }
+        };
+        if let Some(head) = &head {
+            if head.seq == seq && head.hash == expected_prev_hash {
+                head_found = true;
+            }
+        }
+        let event_bytes = match ecies.decrypt_bytes_array(&value, &vault.priv_key, &vault.pub_key) {
+            Ok(plain) => plain,
+            Err(err) => {
+                eprintln!("ERROR: Failed to decrypt the audit event {seq}!\n{err}");
+                log.unreadable += 1;
+                continue;
+            }
+        };
+        let event = match rmp_serde::from_slice::<AuditEvent>(&event_bytes) {
+            Ok(event) => event,
+            Err(err) => {
+                eprintln!("ERROR: The audit event {seq} is not stored in estash's format!\n{err}");
+                log.unreadable += 1;
+                continue;
+            }
+        };
+
+        if event.prev_hash != prev_hash {
+            log.broken_links.push(seq);
+        }
+        log.events.push((seq, event));
+    }
+
+    // no head means nothing was logged on this machine yet
+    log.truncated = match head {
+        Some(head) => !head.authentic || !head_found,
+        None => false,
+    };
+
+    Ok(log)
+}
+
+///
+/// A line describing the event, for the viewer.
+///
+pub fn describe_event(seq: u64, event: &AuditEvent) -> String {
+    let action = match event.action {
+        AuditAction::Opened => "Opened the vault",
+        AuditAction::Viewed => "Viewed",
+        AuditAction::Modified => "Modified",
+        AuditAction::Installed => "Installed",
+        AuditAction::Deleted => "Deleted",
+        AuditAction::FileSelected => "Selected a file for",
+        AuditAction::Imported => "Imported",
+        AuditAction::Exported => "Exported every entry unencrypted",
+        AuditAction::Uninstalled => "Uninstalled",
+    };
+
+    let mut line = format!("#{seq}  {}  {action}", format_time(event.time));
+    if !event.entry.is_empty() {
+        line += &format!(" {}", event.entry);
+    }
+    if !event.detail.is_empty() {
+        line += &format!(" ({})", event.detail);
+    }
+
+    line
+}
+
+///
+/// The log as JSON lines, one event per line, oldest first.
+///
+pub fn export_json_lines(log: &AuditLog) -> Result<String, AuditErr> {
+    let mut lines = String::new();
+    for (seq, event) in &log.events {
+        let exported = ExportedEvent {
+            seq: *seq,
+            time: event.time,
+            time_utc: format_time(event.time),
+            action: event.action,
+            entry: &event.entry,
+            detail: &event.detail,
+        };
+        match serde_json::to_string(&exported) {
+            Ok(line) => {
+                lines += &line;
+                lines.push('\n');
+            }
+            Err(err) => {
+                eprintln!("ERROR: Failed to encode the audit event {seq} as json!\n{err}");
+                return Err(AuditErr::Corrupted(0));
+            }
+        };
+    }
+
+    Ok(lines)
+}
+
+///
+/// Stores the event after the last one, returns its sequence number and hash.
+///
+fn insert_event(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    time: u64,
+    action: AuditAction,
+    entry: &str,
+    detail: &str,
+) -> Result<(u64, [u8; 32]), AuditErr> {
+    let log_tree = open_log_tree(vault_db)?;
+
+    // the event after the last one, chained to it
+    let (seq, prev_hash) = match log_tree.last() {
+        Ok(Some((key, value))) => match key.as_ref().try_into() {
+            Ok(key) => (u64::from_be_bytes(key) + 1, blake3::hash_bytes(&value)),
+            Err(_) => {
+                eprintln!("ERROR: The audit log has a key that isn't a sequence number!");
+                return Err(AuditErr::Corrupted(0));
+            }
+        },
+        Ok(None) => (0, [0; 32]),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the audit log!\n{err}");
+            return Err(AuditErr::DbInaccesible(0));
+        }
+    };
+
+    let event = AuditEvent {
+        time,
+        action,
+        entry: entry.to_owned(),
+        detail: detail.to_owned(),
+        prev_hash: prev_hash.to_vec(),
+    };
+    let event_bytes = match rmp_serde::to_vec_named(&event) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the audit event!\n{err}");
+            return Err(AuditErr::Corrupted(0));
+        }
+    };
+    let event_encrypted =
+        match ecies.encrypt_bytes_array(&event_bytes, &vault.priv_key, &vault.pub_key) {
+            Ok(cipher) => cipher,
+            Err(err) => {
+                eprintln!("ERROR: Failed to encrypt the audit event!\n{err}");
+                return Err(AuditErr::EncryptFailed(0));
+            }
+        };
+
+    // only ever inserted under a new sequence number, nothing is overwritten
+    let hash = blake3::hash_bytes(&event_encrypted);
+    match log_tree.compare_and_swap(
+        seq.to_be_bytes(),
+        None as Option<&[u8]>,
+        Some(event_encrypted),
+    ) {
+        Ok(Ok(_)) => (),
+        Ok(Err(_)) => {
+            eprintln!("ERROR: There already is an audit event with the same sequence number!");
+            return Err(AuditErr::Corrupted(0));
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the audit event!\n{err}");
+            return Err(AuditErr::DbInaccesible(0));
+        }
+    };
+
+    match log_tree.flush() {
+        Ok(_) => Ok((seq, hash)),
+        Err(err) => {
+            eprintln!("ERROR: Failed to flush the audit log!\n{err}");
+            Err(AuditErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Remembers the newest event of the log on this machine, with a keyed hash
+/// so it can't be moved back without the vault key.
+///
+fn store_head(
+    estashdb: &db::EstashDb,
+    vault: &Vault,
+    seq: u64,
+    hash: &[u8; 32],
+) -> Result<(), AuditErr> {
+    let heads_tree = open_heads_tree(estashdb)?;
+
+    let mut head = seq.to_be_bytes().to_vec();
+    head.extend_from_slice(hash);
+    head.extend_from_slice(&head_mac(vault, seq, hash));
+    match heads_tree
+        .insert(blake3::hash_bytes(&vault.pub_key), head)
+        .and_then(|_| heads_tree.flush())
+    {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the newest audit event of the vault!\n{err}");
+            Err(AuditErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// The newest event of the log as this machine stored it, None if it never stored one.
+///
+fn read_head(estashdb: &db::EstashDb, vault: &Vault) -> Result<Option<AuditHead>, AuditErr> {
+    let heads_tree = open_heads_tree(estashdb)?;
+
+    // stored as the sequence number, the hash of the event and the keyed hash of both
+    let head = match heads_tree.get(blake3::hash_bytes(&vault.pub_key)) {
+        Ok(Some(head)) => head,
+        Ok(None) => return Ok(None),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the newest audit event of the vault!\n{err}");
+            return Err(AuditErr::DbInaccesible(0));
+        }
+    };
+    if head.len() != 72 {
+        return Ok(Some(AuditHead {
+            seq: 0,
+            hash: [0; 32],
+            authentic: false,
+        }));
+    }
+
+    let mut seq_bytes: [u8; 8] = [0; 8];
+    seq_bytes.copy_from_slice(&head[..8]);
+    let seq = u64::from_be_bytes(seq_bytes);
+    let mut hash: [u8; 32] = [0; 32];
+    hash.copy_from_slice(&head[8..40]);
+    let authentic = head_mac(vault, seq, &hash) == head[40..];
+
+    Ok(Some(AuditHead {
+        seq,
+        hash,
+        authentic,
+    }))
+}
+
+fn head_mac(vault: &Vault, seq: u64, hash: &[u8; 32]) -> [u8; 32] {
+    let mut mac_key = ::blake3::derive_key(AUDIT_HEAD_CONTEXT, &vault.priv_key[..]);
+    let mut hasher = ::blake3::Hasher::new_keyed(&mac_key);
+    mac_key.zeroize();
+    hasher.update(&vault.pub_key);
+    hasher.update(&seq.to_be_bytes());
+    hasher.update(hash);
+
+    *hasher.finalize().as_bytes()
+}
+
+///
+/// Opens the vaults db for the newest events, sled locks it so
+/// it can't be open anywhere else at the same time.
+///
+pub fn open_estashdb() -> Result<db::EstashDb, AuditErr> {
+    match db::EstashDb::new() {
+        Ok(db) => Ok(db),
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            Err(AuditErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn open_heads_tree(estashdb: &db::EstashDb) -> Result<sled::Tree, AuditErr> {
+    match estashdb.vault_db.open_tree(AUDIT_HEADS_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the tree with the newest audit events!\n{err}");
+            Err(AuditErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Formats a unix time as a UTC date and time, like 2022-10-19 13:05:09 UTC.
+///
+pub fn format_time(time: u64) -> String {
+    let days = time / 86400;
+    let secs = time % 86400;
+
+    // days since 1970-01-01 to a civil date, Howard Hinnant's algorithm
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+fn open_log_tree(vault_db: &Db) -> Result<sled::Tree, AuditErr> {
+    match vault_db.open_tree(AUDIT_LOG_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the tree with the audit log!\n{err}");
+            Err(AuditErr::DbInaccesible(0))
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    }
+}
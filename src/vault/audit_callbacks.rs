@@ -0,0 +1,162 @@
+use super::audit::{self, AuditLog};
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Audit Log/View Log menu item.
+/// Shows every event in the audit log of the vault, newest first,
+/// and warns about events that were removed or can't be read.
+///
+pub fn view_audit_log_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let log = match read_log(&mut status_label, vault_db_arc, vault_arc, ecies_arc) {
+        Some(log) => log,
+        None => return,
+    };
+    if log.events.is_empty() && log.unreadable == 0 && !log.truncated {
+        status_label.set_label("The audit log is empty!");
+        status_label.show();
+        return;
+    }
+
+    let mut report = String::new();
+    if log.unreadable > 0 {
+        report += &format!(
+            "WARNING: {} events can't be read, the vault db may be corrupted!\n",
+            log.unreadable
+        );
+    }
+    if !log.broken_links.is_empty() {
+        report += &format!(
+            "WARNING: The chain of events is broken at the events {:?}, events were removed or changed!\n",
+            log.broken_links
+        );
+    }
+    if log.truncated {
+        report += "WARNING: The newest events logged on this machine are gone, events were removed from the end of the log!\n";
+    }
+    if !report.is_empty() {
+        report.push('\n');
+    }
+    for (seq, event) in log.events.iter().rev() {
+        report += &audit::describe_event(*seq, event);
+        report.push('\n');
+    }
+
+    super::report_window::show("Audit Log", &report);
+}
+
+///
+/// Callback for the Audit Log/Export to JSON Lines menu item.
+/// Saves the audit log as a file with one JSON object per event.
+///
+pub fn export_audit_log_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let mut file_dialog =
+        dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+    file_dialog.set_title("Export the audit log");
+    file_dialog.set_preset_file(&format!("audit-log.{}", audit::AUDIT_EXPORT_FILE_EXTENSION));
+    file_dialog.show();
+    let path = file_dialog.filename().to_string_lossy().to_string();
+    if path.is_empty() {
+        return;
+    }
+
+    let log = match read_log(&mut status_label, vault_db_arc, vault_arc, ecies_arc) {
+        Some(log) => log,
+        None => return,
+    };
+    let lines = match audit::export_json_lines(&log) {
+        Ok(lines) => lines,
+        Err(_) => {
+            status_label.set_label("Failed to export the audit log, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    match fs::write(&path, lines) {
+        Ok(_) => {
+            let mut status = format!("Exported {} audit events, unencrypted", log.events.len());
+            if log.unreadable > 0 || !log.broken_links.is_empty() || log.truncated {
+                status += ", some events are missing, see View Log";
+            }
+            status_label.set_label(&(status + "!"));
+            status_label.show();
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to write the exported audit log!\n{err}");
+            status_label.set_label("Failed to write the file, try another location!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Reads the audit log, showing what went wrong if it can't be read.
+///
+fn read_log(
+    status_label: &mut frame::Frame,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) -> Option<AuditLog> {
+    let estashdb = match audit::open_estashdb() {
+        Ok(estashdb) => estashdb,
+        Err(_) => {
+            status_label.set_label("Failed to access the vaults db, try again or restart!");
+            status_label.show();
+            return None;
+        }
+    };
+
+    let read = match (vault_db_arc.lock(), vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault_db), Ok(vault), Ok(mut ecies)) => {
+            audit::read_log(&vault_db, &estashdb, &mut ecies, &vault)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return None;
+        }
+    };
+
+    match read {
+        Ok(log) => Some(log),
+        Err(_) => {
+            status_label.set_label("Failed to read the audit log, try again or restart!");
+            status_label.show();
+            None
+        }
+    }
+}
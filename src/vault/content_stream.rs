@@ -0,0 +1,356 @@
+use crate::encrypter::stream_encrypt::{StreamEncrypt, StreamErr, NONCE_PREFIX_SIZE};
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// The name of the tree in the vault db holding the encrypted chunks.
+pub const CONTENT_CHUNKS_TREE: &str = "content_chunks";
+
+/// Files bigger than this (1 MiB) are stored as a stream of chunks instead
+/// of inside the entry value.
+pub const STREAM_THRESHOLD: u64 = 1048576;
+
+/// Added to the install path for the file a big content is decrypted into before it
+/// replaces the installed file.
+const INSTALL_TEMP_EXTENSION: &str = "estash-tmp";
+
+///
+/// Describes a content stored as separately encrypted chunks.
+/// It's stored inside the (encrypted) entry value, so the key
+/// for the chunks is protected by the vault keys.
+///
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ContentStream {
+    #[serde(with = "serde_bytes")]
+    pub id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub key: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub nonce_prefix: Vec<u8>,
+    pub chunks: u32,
+    pub size: u64,
+}
+
+impl ContentStream {
+    ///
+    /// The key under which a chunk is stored in the chunks tree,
+    /// the stream id followed by the chunk index.
+    ///
+    pub fn chunk_key(&self, index: u32) -> Vec<u8> {
+        let mut chunk_key = self.id.to_owned();
+        chunk_key.extend_from_slice(&index.to_be_bytes());
+        chunk_key
+    }
+
+    ///
+    /// Get the key and nonce prefix as arrays, fails if the stored
+    /// values have the wrong size. The key is wiped once it's dropped.
+    ///
+    fn key_material(&self) -> Result<(Zeroizing<[u8; 32]>, [u8; NONCE_PREFIX_SIZE]), StreamErr> {
+        let key: Zeroizing<[u8; 32]> = match self.key.as_slice().try_into() {
+            Ok(key) => Zeroizing::new(key),
+            Err(_) => {
+                eprintln!(
+                    "ERROR: The key of the content stream is not 32 bytes, the entry is corrupted!"
+                );
+                return Err(StreamErr::Corrupted(0));
+            }
+        };
+        let nonce_prefix: [u8; NONCE_PREFIX_SIZE] = match self.nonce_prefix.as_slice().try_into() {
+            Ok(nonce) => nonce,
+            Err(_) => {
+                eprintln!("ERROR: The nonce of the content stream has the wrong size, the entry is corrupted!");
+                return Err(StreamErr::Corrupted(0));
+            }
+        };
+
+        Ok((key, nonce_prefix))
+    }
+}
+
+///
+/// Reads the file at the given path chunk by chunk, encrypting and
+/// storing every chunk in the vault db as it goes.
+///
+pub fn import_file<P: AsRef<Path>>(vault_db: &Db, path: P) -> Result<ContentStream, StreamErr> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the selected file!\n{err}");
+            return Err(StreamErr::ReadFailed(0));
+        }
+    };
+    let mut reader = BufReader::new(file);
+
+    import_reader(vault_db, &mut reader)
+}
+
+///
+/// Encrypts and stores a content that is already in memory as a stream,
+/// for big contents that didn't come from a file (like shared entries).
+///
+pub fn import_bytes(vault_db: &Db, mut bytes: &[u8]) -> Result<ContentStream, StreamErr> {
+    import_reader(vault_db, &mut bytes)
+}
+
+///
+/// Decrypts the whole stream into memory, for when the content
+/// has to be handed over as a whole (like sharing the entry).
+///
+pub fn read_to_vec(vault_db: &Db, content_stream: &ContentStream) -> Result<Vec<u8>, StreamErr> {
+    let mut content = Vec::with_capacity(content_stream.size as usize);
+    decrypt_stream(vault_db, content_stream, &mut content)?;
+
+    Ok(content)
+}
+
+///
+/// The blake3 hash of the decrypted content, hashed chunk by chunk
+/// so the content is never whole in memory.
+///
+pub fn hash_stream(vault_db: &Db, content_stream: &ContentStream) -> Result<[u8; 32], StreamErr> {
+    let mut hasher = blake3::Hasher::new();
+    decrypt_stream(vault_db, content_stream, &mut hasher)?;
+
+    Ok(hasher.finalize().as_bytes().to_owned())
+}
+
+///
+/// Decrypts the stream chunk by chunk into a temporary file next to the given path,
+/// which replaces the file at the path only once every chunk was decrypted, so a
+/// missing or changed chunk leaves the file that was there untouched.
+///
+pub fn install_to_file(
+    vault_db: &Db,
+    content_stream: &ContentStream,
+    path: &str,
+) -> Result<u64, StreamErr> {
+    let temp_path = format!("{path}.{INSTALL_TEMP_EXTENSION}");
+    let installed = decrypt_to_file(vault_db, content_stream, path, &temp_path);
+    if installed.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    installed
+}
+
+///
+/// Checks that every chunk of the stream is present and authentic,
+/// without writing the decrypted content anywhere.
+///
+pub fn verify_stream(vault_db: &Db, content_stream: &ContentStream) -> bool {
+    match decrypt_stream(vault_db, content_stream, &mut io::sink()) {
+        Ok(size) => size == content_stream.size,
+        Err(_) => false,
+    }
+}
+
+///
+/// Removes every chunk of the given stream from the vault db.
+///
+pub fn remove_stream(vault_db: &Db, content_stream: &ContentStream) -> Result<(), StreamErr> {
+    let chunks_tree = open_chunks_tree(vault_db)?;
+
+    remove_chunks(&chunks_tree, &content_stream.id)
+}
+
+///
+/// Copies the encrypted chunks of the stream as they are into another vault db.
+/// The key for them lives in the entry value, so they don't need re-encrypting.
+///
+pub fn copy_stream(
+    from_db: &Db,
+    to_db: &Db,
+    content_stream: &ContentStream,
+) -> Result<(), StreamErr> {
+    let from_tree = open_chunks_tree(from_db)?;
+    let to_tree = open_chunks_tree(to_db)?;
+
+    for chunk in from_tree.scan_prefix(&content_stream.id) {
+        let (key, value) = match chunk {
+            Ok(kv) => kv,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a chunk of the content from the vault db!\n{err}");
+                return Err(StreamErr::ReadFailed(0));
+            }
+        };
+        match to_tree.insert(key, value) {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("ERROR: Failed to store a chunk of the content in the vault db!\n{err}");
+                return Err(StreamErr::WriteFailed(0));
+            }
+        };
+    }
+
+    Ok(())
+}
+
+///
+/// Encrypts everything from the reader chunk by chunk, storing every chunk in the
+/// vault db as it goes.
+///
+fn import_reader<R: Read>(vault_db: &Db, reader: &mut R) -> Result<ContentStream, StreamErr> {
+    let chunks_tree = open_chunks_tree(vault_db)?;
+
+    let mut stream_encrypt = StreamEncrypt::new();
+    let (key, nonce_prefix) = stream_encrypt.gen_key();
+    let key = Zeroizing::new(key);
+    let id = stream_encrypt.gen_id();
+
+    let mut content_stream = ContentStream {
+        id: id.to_vec(),
+        key: key.to_vec(),
+        nonce_prefix: nonce_prefix.to_vec(),
+        chunks: 0,
+        size: 0,
+    };
+
+    let result =
+        stream_encrypt.encrypt_reader(
+            reader,
+            &key,
+            &nonce_prefix,
+            |index, cipher| match chunks_tree.insert(content_stream.chunk_key(index), cipher) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    eprintln!(
+                        "ERROR: Failed to store a chunk of the content in the vault db!\n{err}"
+                    );
+                    Err(StreamErr::WriteFailed(0))
+                }
+            },
+        );
+
+    match result {
+        Ok((chunks, size)) => {
+            content_stream.chunks = chunks;
+            content_stream.size = size;
+            Ok(content_stream)
+        }
+        Err(err) => {
+            // don't leave the half written stream behind
+            let _ = remove_chunks(&chunks_tree, &content_stream.id);
+            Err(err)
+        }
+    }
+}
+
+///
+/// Decrypts the stream into the temporary file, then moves it over the file at the path,
+/// keeping the permissions of the file it replaces.
+///
+fn decrypt_to_file(
+    vault_db: &Db,
+    content_stream: &ContentStream,
+    path: &str,
+    temp_path: &str,
+) -> Result<u64, StreamErr> {
+    let file = match fs::File::create(temp_path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("ERROR: Failed to create the file to install the content to!\n{err}");
+            return Err(StreamErr::WriteFailed(0));
+        }
+    };
+    let mut writer = BufWriter::new(file);
+
+    let size = decrypt_stream(vault_db, content_stream, &mut writer)?;
+
+    let file = match writer.into_inner() {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("ERROR: Failed to write the content to the file!\n{err}");
+            return Err(StreamErr::WriteFailed(0));
+        }
+    };
+    if let Err(err) = file.sync_all() {
+        eprintln!("ERROR: Failed to write the content to the file!\n{err}");
+        return Err(StreamErr::WriteFailed(0));
+    }
+    drop(file);
+
+    if let Ok(metadata) = fs::metadata(path) {
+        if let Err(err) = fs::set_permissions(temp_path, metadata.permissions()) {
+            eprintln!("ERROR: Failed to keep the permissions of the installed file!\n{err}");
+            return Err(StreamErr::WriteFailed(0));
+        }
+    }
+    match fs::rename(temp_path, path) {
+        Ok(_) => Ok(size),
+        Err(err) => {
+            eprintln!("ERROR: Failed to replace the file with the installed content!\n{err}");
+            Err(StreamErr::WriteFailed(0))
+        }
+    }
+}
+
+///
+/// Decrypts the stream into the given writer, returns the number of bytes written.
+///
+fn decrypt_stream<W: Write>(
+    vault_db: &Db,
+    content_stream: &ContentStream,
+    writer: &mut W,
+) -> Result<u64, StreamErr> {
+    let chunks_tree = open_chunks_tree(vault_db)?;
+    let (key, nonce_prefix) = content_stream.key_material()?;
+
+    let mut stream_encrypt = StreamEncrypt::new();
+    stream_encrypt.decrypt_to_writer(
+        writer,
+        &key,
+        &nonce_prefix,
+        content_stream.chunks,
+        |index| match chunks_tree.get(content_stream.chunk_key(index)) {
+            Ok(Some(cipher)) => Ok(cipher.to_vec()),
+            Ok(None) => {
+                eprintln!("ERROR: A chunk of the content is missing from the vault db!");
+                Err(StreamErr::ReadFailed(0))
+            }
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a chunk of the content from the vault db!\n{err}");
+                Err(StreamErr::ReadFailed(0))
+            }
+        },
+    )
+}
+
+///
+/// Removes every chunk starting with the given stream id.
+///
+fn remove_chunks(chunks_tree: &sled::Tree, id: &[u8]) -> Result<(), StreamErr> {
+    for chunk in chunks_tree.scan_prefix(id) {
+        let key = match chunk {
+            Ok(kv) => kv.0,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a chunk of the content from the vault db!\n{err}");
+                return Err(StreamErr::ReadFailed(0));
+            }
+        };
+        match chunks_tree.remove(key) {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!(
+                    "ERROR: Failed to remove a chunk of the content from the vault db!\n{err}"
+                );
+                return Err(StreamErr::WriteFailed(0));
+            }
+        };
+    }
+
+    Ok(())
+}
+
+fn open_chunks_tree(vault_db: &Db) -> Result<sled::Tree, StreamErr> {
+    match vault_db.open_tree(CONTENT_CHUNKS_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the content chunks tree of the vault db!\n{err}");
+            Err(StreamErr::ReadFailed(0))
+        }
+    }
+}
@@ -1,29 +1,193 @@
+use super::content_stream::{self, ContentStream};
+use super::install_targets::InstallTarget;
+use super::installs::InstallMode;
+use super::settings;
 use crate::encrypter::ecies::ECIES;
-use crate::utils::constants;
+use crate::utils;
 use crate::utils::Vault;
-use fltk::tree;
+use fltk::{prelude::*, tree};
 use serde::{Deserialize, Serialize};
-use sled::Db;
-use std::collections::HashMap;
+use sled::transaction::ConflictableTransactionError;
+use sled::{Db, Transactional};
 use std::process;
 use std::str;
 use std::sync::{Arc, Mutex};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Marks a value encoded as MessagePack, values without it are the old json ones.
+const VALUE_FORMAT_HEADER: &[u8] = b"ESV\x01";
+
+/// Marks a value encoded as MessagePack and then compressed with zstd.
+const VALUE_FORMAT_HEADER_ZSTD: &[u8] = b"ESV\x02";
+
+/// zstd level used for the values, the default one, a good balance for small text files.
+const VALUE_COMPRESSION_LEVEL: i32 = 3;
+
+/// The name of the tree in the vault db holding the encrypted entry names, under the same
+/// keys as the entries, it's only used for listing the entries.
+pub const ENTRY_NAMES_TREE: &str = "entry_names";
+
+/// Context for deriving the key used to hash the entry names from the vault private key.
+const ENTRY_KEY_CONTEXT: &str = "estash 2022-09 entry keys v1";
+
+/// The size of the entry keys (blake3 hashes), the old encrypted names are always bigger.
+pub const ENTRY_KEY_SIZE: usize = 32;
 
 #[derive(Serialize, Deserialize)]
 pub struct VaultValue {
+    // the name of the entry, the key is only a hash of it
+    #[serde(default)]
+    pub name: String,
     pub install_path: String,
+    #[serde(with = "serde_bytes")]
     pub content: Vec<u8>,
     pub notes: String,
+    // big contents are kept as separately encrypted chunks instead of in content
+    #[serde(default)]
+    pub content_stream: Option<ContentStream>,
+    // install paths for some OSes or hostnames, used instead of install_path on them
+    #[serde(default)]
+    pub install_targets: Vec<InstallTarget>,
+    // the content is a template, rendered with the variables of the machine on install
+    #[serde(default)]
+    pub is_template: bool,
+    // whether the content is copied to the install path or linked there
+    #[serde(default)]
+    pub install_mode: InstallMode,
+    // a command run after the entry is installed, empty for none
+    #[serde(default)]
+    pub post_install: String,
+}
+
+///
+/// The decrypted contents are wiped as soon as a value is dropped,
+/// so they don't stay around in freed memory.
+///
+impl Drop for VaultValue {
+    fn drop(&mut self) {
+        self.name.zeroize();
+        self.install_path.zeroize();
+        // each install target wipes its own path when dropped
+        self.install_targets.clear();
+        self.content.zeroize();
+        self.notes.zeroize();
+        self.post_install.zeroize();
+    }
 }
 
 impl VaultValue {
     pub fn new_empty() -> VaultValue {
         VaultValue {
+            name: String::new(),
             install_path: String::new(),
             content: Vec::new(),
             notes: String::new(),
+            content_stream: None,
+            install_targets: Vec::new(),
+            is_template: false,
+            install_mode: InstallMode::Copy,
+            post_install: String::new(),
         }
     }
+
+    ///
+    /// Encode the value as MessagePack (with field names, so new fields can
+    /// still be added with defaults) prefixed by the format header.
+    /// The content is kept as raw bytes instead of a list of numbers like in json.
+    /// If compress is set the encoded value is also compressed with zstd, which
+    /// one was used is recorded in the header so reading works either way.
+    ///
+    pub fn to_bytes(&self, compress: bool) -> Result<Vec<u8>, ValueFormatErr> {
+        let mut encoded = match rmp_serde::to_vec_named(self) {
+            Ok(encoded) => encoded,
+            Err(err) => {
+                eprintln!("ERROR: Failed to encode the entry value!\n{err}");
+                return Err(ValueFormatErr::Unencodable(0));
+            }
+        };
+
+        let header = if compress {
+            let compressed = match zstd::bulk::compress(&encoded, VALUE_COMPRESSION_LEVEL) {
+                Ok(compressed) => compressed,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to compress the entry value!\n{err}");
+                    encoded.zeroize();
+                    return Err(ValueFormatErr::Unencodable(0));
+                }
+            };
+            encoded.zeroize();
+            encoded = compressed;
+            VALUE_FORMAT_HEADER_ZSTD
+        } else {
+            VALUE_FORMAT_HEADER
+        };
+
+        let mut bytes = Vec::with_capacity(header.len() + encoded.len());
+        bytes.extend_from_slice(header);
+        bytes.extend_from_slice(&encoded);
+        encoded.zeroize();
+
+        Ok(bytes)
+    }
+
+    ///
+    /// Decode a value stored by to_bytes, values stored before as json
+    /// are still read transparently.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Result<VaultValue, ValueFormatErr> {
+        if let Some(encoded) = bytes.strip_prefix(VALUE_FORMAT_HEADER) {
+            return match rmp_serde::from_slice(encoded) {
+                Ok(value) => Ok(value),
+                Err(err) => {
+                    eprintln!("ERROR: Failed to decode the entry value!\n{err}");
+                    Err(ValueFormatErr::Undecodable(0))
+                }
+            };
+        }
+
+        if let Some(compressed) = bytes.strip_prefix(VALUE_FORMAT_HEADER_ZSTD) {
+            let mut encoded = match zstd::stream::decode_all(compressed) {
+                Ok(encoded) => encoded,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to decompress the entry value!\n{err}");
+                    return Err(ValueFormatErr::Undecodable(0));
+                }
+            };
+            let value = rmp_serde::from_slice(&encoded);
+            encoded.zeroize();
+            return match value {
+                Ok(value) => Ok(value),
+                Err(err) => {
+                    eprintln!("ERROR: Failed to decode the entry value!\n{err}");
+                    Err(ValueFormatErr::Undecodable(0))
+                }
+            };
+        }
+
+        // old values are utf-8 json
+        let value_string = match str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(err) => {
+                eprintln!(
+                    "ERROR: The entry value is neither in the binary format nor utf-8 json!\n{err}"
+                );
+                return Err(ValueFormatErr::Undecodable(0));
+            }
+        };
+        match serde_json::from_str(value_string) {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                eprintln!("ERROR: Failed to parse the old json entry value!\n{err}");
+                Err(ValueFormatErr::Undecodable(0))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ValueFormatErr {
+    Unencodable(u16),
+    Undecodable(u16),
 }
 
 #[derive(Debug)]
@@ -37,11 +201,17 @@ pub enum VaultValueErr {
 #[derive(Debug)]
 pub enum NewEntryErr {
     PoisonErr(u16),
+    AlreadyExists(u16),
     DbCorrupted(u16),
     DbInaccesible(u16),
     UnknownError(u16),
 }
 
+#[derive(Debug)]
+pub enum RemoveEntryErr {
+    DbInaccesible(u16),
+}
+
 pub enum UpdateContentErr {
     PoisonErr(u16),
     DisplayNotInSync(u16),
@@ -53,30 +223,7 @@ pub enum UpdateContentErr {
 /// Load the Vault object as a database we can use
 ///
 pub fn load_vault(is_windows: bool, vault: &Vault) -> Arc<Mutex<Db>> {
-    let vaults_root_path;
-    if is_windows {
-        let document_dir = match dirs::document_dir() {
-            Some(dir) => dir,
-            None => {
-                eprintln!("ERROR: Failed to get document dir");
-                process::exit(200);
-            }
-        };
-        // unwrap here is alright
-        let estash_dir = document_dir.to_str().unwrap().to_owned() + "\\estash\\";
-        vaults_root_path = (estash_dir + &constants::VAULTS_ROOT_PATH_WINDOWS).to_string();
-    } else {
-        let home_dir = match dirs::home_dir() {
-            Some(dir) => dir,
-            None => {
-                eprintln!("ERROR: Failed to get home dir");
-                process::exit(200);
-            }
-        };
-        // unwrap here is alright
-        let estash_dir = home_dir.to_str().unwrap().to_owned() + "/.estash/";
-        vaults_root_path = (estash_dir + constants::VAULTS_ROOT_PATH_UNIX).to_string();
-    }
+    let vaults_root_path = utils::get_vaults_root_path(is_windows);
     let vault_db = Arc::new(Mutex::new(
         match sled::open(vaults_root_path + &vault.id.to_string()) {
             Ok(db) => db,
@@ -91,16 +238,33 @@ pub fn load_vault(is_windows: bool, vault: &Vault) -> Arc<Mutex<Db>> {
 }
 
 ///
-/// Load the entries from the database.
-/// Display them in the tree object.
-/// And store them in memory for faster access.
+/// The key an entry is stored under in the vault db, a keyed blake3 hash of its name
+/// with a key derived from the vault private key. Being deterministic it can be used
+/// to look up an entry directly in the db, without knowing the rest of the names.
+/// The keys change with the private key: anything that moves entries to another key
+/// (like removing a team member) or between vaults has to rebuild every entry key and
+/// every other keyed hash derived through here, the installs records, their managed
+/// paths and the approved hooks.
+///
+pub fn entry_key(vault: &Vault, entry_name: &str) -> Vec<u8> {
+    let mut hash_key = blake3::derive_key(ENTRY_KEY_CONTEXT, &vault.priv_key[..]);
+    let key = blake3::keyed_hash(&hash_key, entry_name.as_bytes());
+    hash_key.zeroize();
+
+    key.as_bytes().to_vec()
+}
+
+///
+/// Load the entries names from the database.
+/// And display them in the tree object.
+/// Entries stored by older versions under their encrypted name are moved to their keyed hash.
 ///
 pub fn load_entries(
     vault: &Vault,
     vault_db: Arc<Mutex<Db>>,
     ecies: Arc<Mutex<ECIES>>,
     entries: &mut tree::Tree,
-) -> Arc<Mutex<HashMap<String, Vec<u8>>>> {
+) {
     // get value under vault_db arc
     let vault_db_locked = match vault_db.lock() {
         Ok(db) => db,
@@ -118,25 +282,26 @@ pub fn load_entries(
         }
     };
 
-    let db_entries = vault_db_locked.iter();
-    let db_entries_dict = Arc::new(Mutex::new(HashMap::new()));
+    migrate_legacy_entries(vault, &vault_db_locked, &mut ecies_locked);
 
-    let mut db_entries_dict_locked = match db_entries_dict.lock() {
-        Ok(object) => object,
+    let entry_names_tree = match vault_db_locked.open_tree(ENTRY_NAMES_TREE) {
+        Ok(tree) => tree,
         Err(err) => {
-            eprintln!("ERROR: Failed to get the value under db_entries_dict ARC!\n{err}");
+            eprintln!("ERROR: Failed to open the entry names tree of the vault db!\n{err}");
             process::exit(100);
         }
     };
 
-    for entry in db_entries {
+    for entry in entry_names_tree.iter() {
         let current_entry_encrypted = match entry {
-            Ok(cipher) => cipher.0.to_vec(),
+            Ok(cipher) => cipher.1.to_vec(),
             Err(err) => {
                 eprintln!("ERROR: Failed to get an entry that we just read, this error message should not be displayed, but if for some reason is, just try again, or post an issue on github!\n{err}");
                 process::exit(100);
             }
         };
+        // entries we can't read are skipped instead of closing the app, so the vault can still
+        // be opened and checked with the verify tool
         let current_entry_plain = match ecies_locked.decrypt_bytes(
             &current_entry_encrypted,
             &vault.priv_key,
@@ -144,27 +309,121 @@ pub fn load_entries(
         ) {
             Ok(plain) => plain,
             Err(err) => {
-                eprintln!("ERROR: Failed to decrypt the entry even though the vault keys were validated, try again, if it doesn't work then the db might be corrupted!\n{err}");
-                process::exit(100);
+                eprintln!("ERROR: Failed to decrypt an entry name even though the vault keys were validated, skipping it, use Verify Vault to check the db!\n{err}");
+                continue;
             }
         };
         let current_entry_string = match str::from_utf8(&current_entry_plain) {
             Ok(s) => s,
             Err(err) => {
-                eprintln!("ERROR: Failed to convert the decrypted bytes into a string, your current db is most likely corrupted, try again maybe!\n{err}");
-                process::exit(100);
+                eprintln!("ERROR: Failed to convert the decrypted bytes into a string, skipping the entry, use Verify Vault to check the db!\n{err}");
+                continue;
             }
         };
         entries.add(current_entry_string);
-
-        db_entries_dict_locked.insert(current_entry_string.to_owned(), current_entry_encrypted);
     }
 
     drop(vault_db_locked);
     drop(ecies_locked);
-    drop(db_entries_dict_locked);
+}
 
-    return db_entries_dict;
+///
+/// Lists the entries again, after the vault db was changed from outside the
+/// entries tree (like loading or syncing it).
+///
+pub fn reload_entries(
+    vault: &Vault,
+    vault_db: Arc<Mutex<Db>>,
+    ecies: Arc<Mutex<ECIES>>,
+    entries: &mut tree::Tree,
+) {
+    entries.clear();
+    load_entries(vault, vault_db, ecies, entries);
+    entries.redraw();
+}
+
+///
+/// Moves the entries stored by older versions, under the encrypted entry name,
+/// to the keyed hash of the name, with the name kept inside the value.
+/// Entries that can't be read are left as they are, Verify Vault reports them.
+///
+fn migrate_legacy_entries(vault: &Vault, vault_db: &Db, ecies: &mut ECIES) {
+    let entry_names_tree = match vault_db.open_tree(ENTRY_NAMES_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the entry names tree of the vault db!\n{err}");
+            return;
+        }
+    };
+    let compress = settings::is_compression_enabled(vault_db);
+
+    for entry in vault_db.iter() {
+        let (legacy_key, value_encrypted) = match entry {
+            Ok(kv) => kv,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read an entry from the vault db!\n{err}");
+                return;
+            }
+        };
+        if legacy_key.len() == ENTRY_KEY_SIZE {
+            continue;
+        }
+
+        let name = match ecies.decrypt_bytes(&legacy_key.to_vec(), &vault.priv_key, &vault.pub_key)
+        {
+            Ok(plain) => match String::from_utf8(plain) {
+                Ok(name) => name,
+                Err(_) => continue,
+            },
+            Err(_) => continue,
+        };
+        let value_plain =
+            match ecies.decrypt_bytes(&value_encrypted.to_vec(), &vault.priv_key, &vault.pub_key) {
+                Ok(plain) => plain,
+                Err(_) => continue,
+            };
+        let mut value = match VaultValue::from_bytes(&value_plain) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+
+        // the old keys didn't prevent duplicate names, so make the name unique if needed
+        let mut unique_name = name.to_owned();
+        let mut duplicate_index = 2;
+        while let Ok(true) = vault_db.contains_key(entry_key(vault, &unique_name)) {
+            unique_name = format!("{name} ({duplicate_index})");
+            duplicate_index += 1;
+        }
+        value.name = unique_name;
+
+        let value_bytes = match value.to_bytes(compress) {
+            Ok(bytes) => Zeroizing::new(bytes),
+            Err(_) => continue,
+        };
+        let new_value_encrypted =
+            match ecies.encrypt_bytes_array(&value_bytes, &vault.priv_key, &vault.pub_key) {
+                Ok(cipher) => cipher,
+                Err(_) => continue,
+            };
+        let name_encrypted =
+            match ecies.encrypt_bytes_array(value.name.as_bytes(), &vault.priv_key, &vault.pub_key)
+            {
+                Ok(cipher) => cipher,
+                Err(_) => continue,
+            };
+
+        // all at once, a half moved entry would be moved again as a duplicate next time
+        let new_key = entry_key(vault, &value.name);
+        let migrated = (&**vault_db, &entry_names_tree).transaction(|(entries, names)| {
+            entries.insert(new_key.as_slice(), new_value_encrypted.as_slice())?;
+            names.insert(new_key.as_slice(), name_encrypted.as_slice())?;
+            entries.remove(&legacy_key)?;
+            Ok::<(), ConflictableTransactionError>(())
+        });
+        if migrated.is_err() {
+            eprintln!("ERROR: Failed to move the entry {name} to its new key, it will be tried again the next time the vault is opened!");
+        }
+    }
 }
 
 ///
@@ -175,30 +434,16 @@ pub fn get_entry_value_plain(
     ecies_arc_clone: Arc<Mutex<ECIES>>,
     vault_arc_clone: Arc<Mutex<Vault>>,
     selected_item: &str,
-    db_entries_dict_arc_clone: Arc<Mutex<HashMap<String, Vec<u8>>>>,
 ) -> Result<VaultValue, VaultValueErr> {
-    // get value behind ARC
-    let db_entries_dict = match db_entries_dict_arc_clone.lock() {
-        Ok(dict) => dict,
+    // get the key the entry is stored under
+    let selected_item_key = match vault_arc_clone.lock() {
+        Ok(vault) => entry_key(&vault, selected_item),
         Err(err) => {
-            eprintln!("ERROR: Failed to get db_entries_dict value behind ARC!\n{err}");
+            eprintln!("ERROR: Failed to get vault value behind ARC!\n{err}");
             return Err(VaultValueErr::PoisonErr(0));
         }
     };
 
-    // get encrypted selected_item version in db
-    let selected_item_encrypted = match db_entries_dict.get(selected_item) {
-        Some(cipher) => cipher.to_owned(),
-        None => {
-            eprintln!("ERROR: The Values In Memory are not in sync with the ones on screen!");
-            return Err(VaultValueErr::DisplayNotInSync(0));
-        }
-    };
-
-    // drop arc ref
-    drop(db_entries_dict);
-    drop(db_entries_dict_arc_clone);
-
     // get value behind arc
     let vault_db = match vault_db_arc_clone.lock() {
         Ok(db) => db,
@@ -208,12 +453,12 @@ pub fn get_entry_value_plain(
         }
     };
 
-    let entry_value_encrypted = match vault_db.get(selected_item_encrypted) {
+    let entry_value_encrypted = match vault_db.get(selected_item_key) {
         Ok(val) => match val {
             Some(data) => data.to_vec(),
             None => {
-                eprintln!("ERROR: This Database is corrupted, not readable by estash, the key seems to exist, but there is not data associated with it!");
-                return Err(VaultValueErr::DbCorrupted(0));
+                eprintln!("ERROR: The Values On Screen are not in sync with the ones in storage, there is no entry with this name!");
+                return Err(VaultValueErr::DisplayNotInSync(0));
             }
         },
         Err(err) => {
@@ -242,7 +487,7 @@ pub fn get_entry_value_plain(
         }
     };
 
-    let entry_value_decrypted = match ecies.decrypt_bytes(
+    let mut entry_value_decrypted = match ecies.decrypt_bytes(
         &entry_value_encrypted,
         &vault.priv_key,
         &vault.pub_key,
@@ -260,24 +505,16 @@ pub fn get_entry_value_plain(
     drop(ecies);
     drop(ecies_arc_clone);
 
-    let entry_value_string = match str::from_utf8(&entry_value_decrypted) {
-        Ok(s) => s,
-        Err(err) => {
-            eprintln!("ERROR: This db may be corrupted, altough through the UI you are only able to store utf-8 values, we weren't able to parse one from storage!\n{err}");
-            return Err(VaultValueErr::DbCorrupted(0));
-        }
-    };
-
-    // parase entry value into json
-    let entry_value_json: VaultValue = match serde_json::from_str(entry_value_string) {
-        Ok(json) => json,
-        Err(err) => {
-            eprintln!("ERROR: This db may be corrupted, altough we were able to retrive all data there seems to be some adittional data in the db that prevents the conversion to json!\n{err}");
-            return Err(VaultValueErr::DbCorrupted(0));
+    // parse entry value, either the binary format or the old json
+    let value = VaultValue::from_bytes(&entry_value_decrypted);
+    entry_value_decrypted.zeroize();
+    match value {
+        Ok(value) => Ok(value),
+        Err(_) => {
+            eprintln!("ERROR: This db may be corrupted, altough we were able to decrypt the entry value it couldn't be parsed!");
+            Err(VaultValueErr::DbCorrupted(0))
         }
-    };
-
-    Ok(entry_value_json)
+    }
 }
 
 ///
@@ -307,6 +544,7 @@ pub fn add_new_entry(
         }
     };
 
+    // the name is kept encrypted in the names tree for displaying the entries
     let entrie_add_input_value_encrypted = match ecies.encrypt_bytes_array(
         entrie_name.as_bytes(),
         &vault.priv_key,
@@ -319,13 +557,14 @@ pub fn add_new_entry(
         }
     };
 
-    let empty_value = VaultValue::new_empty();
+    let mut empty_value = VaultValue::new_empty();
+    empty_value.name = entrie_name.to_owned();
 
     // there ain't no way in hell this fails, but... let's error handle it I guess
-    let emtpy_value_string = match serde_json::to_string(&empty_value) {
-        Ok(s) => s,
+    let emtpy_value_bytes = match empty_value.to_bytes(false) {
+        Ok(bytes) => bytes,
         Err(err) => {
-            eprintln!("ERROR: Somehow converting the static empty entry value typed in code failed to convert to a string... here is the error,\n{err}");
+            eprintln!("ERROR: Somehow encoding the static empty entry value typed in code failed... here is the error,\n{err:?}");
             return Err(NewEntryErr::UnknownError(0));
         }
     };
@@ -333,7 +572,7 @@ pub fn add_new_entry(
     // how would this even fail if we are encrypting something static with keys that have
     // worked before, regardless here is error handling it yayy
     let emtpy_value_encrypted = match ecies.encrypt_bytes_array(
-        emtpy_value_string.as_bytes(),
+        &emtpy_value_bytes,
         &vault.priv_key,
         &vault.pub_key,
     ) {
@@ -344,6 +583,8 @@ pub fn add_new_entry(
         }
     };
 
+    let new_entry_key = entry_key(&vault, entrie_name);
+
     // drop arc ref
     drop(vault);
     drop(vault_arc_clone);
@@ -359,84 +600,96 @@ pub fn add_new_entry(
         }
     };
 
-    match vault_db.insert(
-        (&entrie_add_input_value_encrypted).to_owned(),
-        emtpy_value_encrypted,
+    // only store the entry if there isn't one with the same name already
+    match vault_db.compare_and_swap(
+        &new_entry_key,
+        None as Option<&[u8]>,
+        Some(emtpy_value_encrypted),
     ) {
-        Ok(_) => (),
+        Ok(Ok(_)) => (),
+        Ok(Err(_)) => {
+            return Err(NewEntryErr::AlreadyExists(0));
+        }
         Err(err) => {
             eprintln!("ERROR: There was an error storing the given value in the db, for some reason the db in not accesible, please report this error on github if you are able to replicate it!\n{err}");
             return Err(NewEntryErr::DbInaccesible(0));
         }
     };
 
-    Ok(entrie_add_input_value_encrypted)
-}
+    let entry_names_tree = match vault_db.open_tree(ENTRY_NAMES_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the entry names tree of the vault db!\n{err}");
+            return Err(NewEntryErr::DbInaccesible(0));
+        }
+    };
+    match entry_names_tree.insert(&new_entry_key, entrie_add_input_value_encrypted) {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("ERROR: There was an error storing the entry name in the db, for some reason the db in not accesible!\n{err}");
+            return Err(NewEntryErr::DbInaccesible(0));
+        }
+    };
 
-pub fn update_content_in_entry(
-    current_selected_entry_arc: Arc<Mutex<String>>,
-    vault_db_arc: Arc<Mutex<Db>>,
-    ecies_arc: Arc<Mutex<ECIES>>,
-    vault_arc: Arc<Mutex<Vault>>,
-    db_entries_dict_arc: Arc<Mutex<HashMap<String, Vec<u8>>>>,
-    file_content: Vec<u8>,
-) -> Result<bool, UpdateContentErr> {
-    //
-    // Get current saved data entry in the database
-    //
+    Ok(new_entry_key)
+}
 
-    // get value under arc
-    let current_selected_entry = match current_selected_entry_arc.lock() {
-        Ok(object) => object,
+///
+/// Remove the entry under the given key, along with its name
+///
+pub fn remove_entry(vault_db: &Db, entry_key: &[u8]) -> Result<(), RemoveEntryErr> {
+    match vault_db.remove(entry_key) {
+        Ok(_) => (),
         Err(err) => {
-            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
-            return Err(UpdateContentErr::PoisonErr(0));
+            eprintln!("ERROR: There was an error removing the entry from the db!\n{err}");
+            return Err(RemoveEntryErr::DbInaccesible(0));
         }
     };
 
-    // get entry data
-    let entry_value_json = match super::core::get_entry_value_plain(
-        vault_db_arc.clone(),
-        ecies_arc.clone(),
-        vault_arc.clone(),
-        &current_selected_entry,
-        db_entries_dict_arc.clone(),
-    ) {
-        Ok(json) => json,
-        Err(VaultValueErr::DbCorrupted(_)) => {
-            process::exit(100);
-        }
-        Err(VaultValueErr::PoisonErr(_)) => {
-            return Err(UpdateContentErr::PoisonErr(0));
-        }
-        Err(VaultValueErr::DisplayNotInSync(_)) => {
-            return Err(UpdateContentErr::DisplayNotInSync(0));
-        }
-        Err(VaultValueErr::MemoryNotInSync(_)) => {
-            return Err(UpdateContentErr::MemoryNotInSync(0));
+    let entry_names_tree = match vault_db.open_tree(ENTRY_NAMES_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the entry names tree of the vault db!\n{err}");
+            return Err(RemoveEntryErr::DbInaccesible(0));
         }
     };
+    match entry_names_tree.remove(entry_key) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: There was an error removing the entry name from the db!\n{err}");
+            Err(RemoveEntryErr::DbInaccesible(0))
+        }
+    }
+}
 
-    //
-    // Save the entry with new data
-    //
-
-    let entry_value_json_new = VaultValue {
-        install_path: entry_value_json.install_path,
-        content: file_content,
-        notes: entry_value_json.notes,
+///
+/// Encrypt the given value and store it under the key of the entry name inside it
+///
+pub fn set_entry_value(
+    vault_db_arc: Arc<Mutex<Db>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    entry_value: &VaultValue,
+) -> Result<bool, UpdateContentErr> {
+    // check if the vault wants the values compressed
+    let compress = match vault_db_arc.lock() {
+        Ok(vault_db) => settings::is_compression_enabled(&vault_db),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            return Err(UpdateContentErr::PoisonErr(0));
+        }
     };
 
-    // shouldn't error, hopefully
-    let entry_value_string = match serde_json::to_string(&entry_value_json_new) {
-        Ok(s) => s,
+    // shouldn't error, hopefully, the plain value is wiped once it's encrypted
+    let entry_value_bytes = match entry_value.to_bytes(compress) {
+        Ok(bytes) => Zeroizing::new(bytes),
         Err(err) => {
-            eprintln!("ERROR: Somehow converting the struct to json error'd out, shouldn't have, here is the error,\n{err}");
+            eprintln!("ERROR: Somehow encoding the struct error'd out, shouldn't have, here is the error,\n{err:?}");
             return Err(UpdateContentErr::UnknownError(0));
         }
     };
 
-    // get value under ecies_arc arc
+    // get value under arc
     let mut ecies = match ecies_arc.lock() {
         Ok(object) => object,
         Err(err) => {
@@ -444,77 +697,157 @@ pub fn update_content_in_entry(
             return Err(UpdateContentErr::PoisonErr(0));
         }
     };
-
-    // get value under arc
     let vault = match vault_arc.lock() {
         Ok(object) => object,
         Err(err) => {
-            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
             return Err(UpdateContentErr::PoisonErr(0));
         }
     };
 
-    // there should be no way for this to error out since once the vault is loaded it means the
-    // keys work
     let entry_value_encrypted = match ecies.encrypt_bytes_array(
-        &entry_value_string.as_bytes(),
+        &entry_value_bytes,
         &vault.priv_key,
         &vault.pub_key,
     ) {
         Ok(cipher) => cipher,
         Err(err) => {
-            eprintln!("ERROR: Failed to encrypt the data input by user, there should be no way for this to error out since once the vault is loaded it means the keys work, anyways here is the error,\n{err}");
+            eprintln!("ERROR: Failed to encrypt the entry value, there should be no way for this to error out since once the vault is loaded it means the keys work, anyways here is the error,\n{err}");
             return Err(UpdateContentErr::UnknownError(0));
         }
     };
 
+    let value_key = entry_key(&vault, &entry_value.name);
+
     // drop arc ref
+    drop(vault);
+    drop(vault_arc);
     drop(ecies);
     drop(ecies_arc);
 
     // get value under arc
-    let db_entries_dict = match db_entries_dict_arc.lock() {
+    let vault_db = match vault_db_arc.lock() {
         Ok(object) => object,
         Err(err) => {
-            eprintln!("ERROR: Failed to get value under db_entries_dict_arc ARC!\n{err}");
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
             return Err(UpdateContentErr::PoisonErr(0));
         }
     };
 
-    // get value under arc
-    let selected_item = current_selected_entry.to_owned();
-
-    let selected_item_encrypted = match db_entries_dict.get(&selected_item) {
-        Some(cipher) => cipher.to_owned(),
-        None => {
-            eprintln!("ERROR: The Values In Memory are not in sync with the ones on screen!");
-            return Err(UpdateContentErr::DisplayNotInSync(0));
+    match vault_db.insert(value_key, entry_value_encrypted) {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("ERROR: There was an error storing the value in the db, for some reason the db in not accesible!\n{err}");
+            return Err(UpdateContentErr::MemoryNotInSync(0));
         }
     };
 
-    // drop arc
-    drop(selected_item);
-    drop(current_selected_entry);
-    drop(current_selected_entry_arc);
-    drop(db_entries_dict);
-    drop(db_entries_dict_arc);
+    Ok(true)
+}
+
+///
+/// Replace the content of the currently selected entry, keeping the rest of its value.
+/// Takes either the content itself or a content stream already stored in the vault db,
+/// any stream the entry had before is removed.
+///
+pub fn update_content_in_entry(
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    file_content: Vec<u8>,
+    content_stream: Option<ContentStream>,
+) -> Result<bool, UpdateContentErr> {
+    //
+    // Get current saved data entry in the database
+    //
 
     // get value under arc
-    let vault_db = match vault_db_arc.lock() {
-        Ok(object) => object,
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
         Err(err) => {
-            eprintln!("ERROR: Failedt to get value under vault_db_arc ARC!\n{err}");
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
             return Err(UpdateContentErr::PoisonErr(0));
         }
     };
 
-    match vault_db.insert(selected_item_encrypted, entry_value_encrypted) {
+    // drop arc ref
+    drop(current_selected_entry_arc);
+
+    // get entry data
+    let mut entry_value_json = match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &current_selected_entry,
+    ) {
+        Ok(json) => json,
+        Err(VaultValueErr::DbCorrupted(_)) => {
+            process::exit(100);
+        }
+        Err(VaultValueErr::PoisonErr(_)) => {
+            return Err(UpdateContentErr::PoisonErr(0));
+        }
+        Err(VaultValueErr::DisplayNotInSync(_)) => {
+            return Err(UpdateContentErr::DisplayNotInSync(0));
+        }
+        Err(VaultValueErr::MemoryNotInSync(_)) => {
+            return Err(UpdateContentErr::MemoryNotInSync(0));
+        }
+    };
+
+    //
+    // Save the entry with new data
+    //
+
+    let old_content_stream = entry_value_json.content_stream.take();
+    let entry_value_json_new = VaultValue {
+        name: current_selected_entry,
+        install_path: std::mem::take(&mut entry_value_json.install_path),
+        content: file_content,
+        notes: std::mem::take(&mut entry_value_json.notes),
+        content_stream,
+        install_targets: std::mem::take(&mut entry_value_json.install_targets),
+        is_template: entry_value_json.is_template,
+        install_mode: entry_value_json.install_mode,
+        post_install: std::mem::take(&mut entry_value_json.post_install),
+    };
+
+    match set_entry_value(
+        vault_db_arc.clone(),
+        ecies_arc,
+        vault_arc,
+        &entry_value_json_new,
+    ) {
         Ok(_) => (),
-        Err(err) => {
-            eprintln!("ERROR: There was an error storing the input value in the db, for some reason the db in not accesible, please report this error on github if you are able to replicate it!\n{err}");
+        Err(UpdateContentErr::MemoryNotInSync(_)) => {
+            eprintln!("ERROR: There was an error storing the input value in the db, for some reason the db in not accesible, please report this error on github if you are able to replicate it!");
             process::exit(100);
         }
+        Err(err) => return Err(err),
     };
 
+    // only remove the old chunks once nothing points to them anymore
+    if let Some(old_content_stream) = old_content_stream {
+        remove_old_stream(vault_db_arc, &old_content_stream);
+    }
+
     Ok(true)
 }
+
+///
+/// Removes the chunks of a content stream that is not used anymore.
+/// A failure here only leaves some unused chunks behind, so it's just logged.
+///
+pub fn remove_old_stream(vault_db_arc: Arc<Mutex<Db>>, old_content_stream: &ContentStream) {
+    match vault_db_arc.lock() {
+        Ok(vault_db) => {
+            if content_stream::remove_stream(&vault_db, old_content_stream).is_err() {
+                eprintln!("ERROR: Failed to remove the old content chunks, they will stay unused in the db!");
+            }
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+        }
+    };
+}
@@ -0,0 +1,81 @@
+use crate::hasher::blake3;
+use crate::utils::key_slots::{self, KeySlotKind};
+use crate::utils::{db, Vault};
+
+#[derive(Debug)]
+pub enum CredentialsErr {
+    WrongCredentials(u16),
+    FailedToAccessVaultsDb(u16),
+    FailedToStoreKey(u16),
+}
+
+///
+/// Encrypts the private key of the vault again, in the password slot the current password
+/// (and keyfile) opens, with a key derived from the new password. A slot that needs a keyfile
+/// keeps needing the same one, so keyfile_hash has to be given for those.
+///
+pub fn change_password(
+    vault: &Vault,
+    current_password: &str,
+    new_password: &str,
+    keyfile_hash: Option<&[u8; 32]>,
+) -> Result<(), CredentialsErr> {
+    let estashdb = match db::EstashDb::new() {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            return Err(CredentialsErr::FailedToAccessVaultsDb(0));
+        }
+    };
+    let vault_name_hashed = blake3::hash_str(&vault.vault_name);
+
+    // check the current credentials, and find out which slot they belong to,
+    // only the password slots since a recovery code can't be changed into a password
+    let slot_id = match key_slots::unlock(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        vault.has_keyfile,
+        current_password,
+        keyfile_hash,
+        Some(KeySlotKind::Password),
+    ) {
+        Ok(Some((slot_id, key))) => {
+            if key[..] != vault.priv_key[..] {
+                eprintln!("ERROR: The stored private key is not the one of the opened vault!");
+                return Err(CredentialsErr::WrongCredentials(0));
+            }
+            slot_id
+        }
+        Ok(None) => {
+            eprintln!("ERROR: The credentials are wrong, no key slot could be opened!");
+            return Err(CredentialsErr::WrongCredentials(0));
+        }
+        Err(_) => return Err(CredentialsErr::FailedToAccessVaultsDb(0)),
+    };
+
+    let slot = match key_slots::list_slots(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        vault.has_keyfile,
+    ) {
+        Ok(slots) => match slots.into_iter().find(|slot| slot.id == slot_id) {
+            Some(slot) => slot,
+            None => return Err(CredentialsErr::FailedToAccessVaultsDb(0)),
+        },
+        Err(_) => return Err(CredentialsErr::FailedToAccessVaultsDb(0)),
+    };
+
+    match key_slots::rewrap_slot(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        &slot,
+        new_password,
+        keyfile_hash,
+        &vault.priv_key,
+    ) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(CredentialsErr::FailedToStoreKey(0)),
+    }
+}
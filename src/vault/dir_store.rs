@@ -0,0 +1,598 @@
+use super::content_stream::CONTENT_CHUNKS_TREE;
+use super::core::{self, VaultValue, ENTRY_KEY_SIZE, ENTRY_NAMES_TREE};
+use super::keyring::CONTACTS_TREE;
+use super::settings;
+use super::team::MEMBERS_TREE;
+use crate::encrypter::ecies::ECIES;
+use crate::utils::Vault;
+use serde::{Deserialize, Serialize};
+use sled::transaction::ConflictableTransactionError;
+use sled::{Db, Transactional};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// The file marking a directory as a snapshot, with the public key of the vault it belongs to.
+pub const STORE_MARKER_FILE: &str = "estash-store";
+const STORE_MARKER_HEADER: &str = "EStash directory store v1";
+
+/// The encrypted manifest, the list of entries and the small vault trees.
+const MANIFEST_FILE: &str = "manifest";
+const MANIFEST_VERSION: u8 = 1;
+
+/// One file per entry, named by the hex of its keyed hash, holding the encrypted value.
+const ENTRIES_DIR: &str = "entries";
+
+/// One file per encrypted chunk of the content streams, they never change once written.
+const CHUNKS_DIR: &str = "chunks";
+
+///
+/// A key and value of a vault db tree, stored as they are (already encrypted).
+///
+#[derive(Serialize, Deserialize, PartialEq)]
+struct TreeRecord {
+    #[serde(with = "serde_bytes")]
+    key: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    value: Vec<u8>,
+}
+
+///
+/// Everything in the snapshot that isn't an entry or a chunk. It only changes when
+/// entries are added or removed, so editing entries doesn't touch it.
+///
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Manifest {
+    version: u8,
+    compress: bool,
+    entries: Vec<String>,
+    contacts: Vec<TreeRecord>,
+    members: Vec<TreeRecord>,
+}
+
+#[derive(Debug)]
+pub enum DirStoreErr {
+    DbInaccesible(u16),
+    DirInaccesible(u16),
+    NotAStore(u16),
+    OtherVault(u16),
+    Corrupted(u16),
+    Incomplete(u16),
+    EncryptFailed(u16),
+}
+
+///
+/// How many files a save wrote and removed, unchanged entries are left alone.
+///
+pub struct SaveSummary {
+    pub written: usize,
+    pub removed: usize,
+}
+
+///
+/// Writes the vault into the given directory, one encrypted file per entry and chunk
+/// plus the encrypted manifest. Files are only rewritten when what they hold changed,
+/// so a git diff of the directory shows exactly the entries that changed.
+/// The directory has to be empty or a snapshot of this same vault.
+///
+pub fn save_to_directory(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    dir: &str,
+) -> Result<SaveSummary, DirStoreErr> {
+    let dir = Path::new(dir);
+
+    claim_directory(dir, STORE_MARKER_FILE, STORE_MARKER_HEADER, vault)?;
+
+    let mut summary = SaveSummary {
+        written: 0,
+        removed: 0,
+    };
+
+    // entries, old ones not moved to their hash yet are left out
+    let entries = mirror_tree(
+        vault_db,
+        Some(ENTRY_KEY_SIZE),
+        &dir.join(ENTRIES_DIR),
+        &mut summary,
+    )?;
+
+    // chunks of the content streams
+    let chunks_tree = open_tree(vault_db, CONTENT_CHUNKS_TREE)?;
+    mirror_tree(&chunks_tree, None, &dir.join(CHUNKS_DIR), &mut summary)?;
+
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        compress: settings::is_compression_enabled(vault_db),
+        entries: entries.into_iter().collect(),
+        contacts: read_records(&open_tree(vault_db, CONTACTS_TREE)?)?,
+        members: read_records(&open_tree(vault_db, MEMBERS_TREE)?)?,
+    };
+
+    // the manifest is encrypted with a new nonce every time, only write it if it changed
+    let manifest_path = dir.join(MANIFEST_FILE);
+    if let Ok(Some(old_manifest)) = read_manifest(&manifest_path, ecies, vault) {
+        if old_manifest == manifest {
+            return Ok(summary);
+        }
+    }
+
+    let mut manifest_bytes = match rmp_serde::to_vec_named(&manifest) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the manifest of the directory snapshot!\n{err}");
+            return Err(DirStoreErr::EncryptFailed(0));
+        }
+    };
+    let manifest_encrypted =
+        ecies.encrypt_bytes_array(&manifest_bytes, &vault.priv_key, &vault.pub_key);
+    manifest_bytes.zeroize();
+    let manifest_encrypted = match manifest_encrypted {
+        Ok(cipher) => cipher,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encrypt the manifest of the directory snapshot!\n{err}");
+            return Err(DirStoreErr::EncryptFailed(0));
+        }
+    };
+    if write_if_changed(&manifest_path, &manifest_encrypted)? {
+        summary.written += 1;
+    }
+
+    Ok(summary)
+}
+
+///
+/// Replaces the contents of the vault with the ones of the directory snapshot.
+/// Every entry file is checked to decrypt with the vault keys and to be stored under the
+/// hash of its own name before anything in the vault is touched. Entries in the
+/// manifest that have no file mean a broken checkout, entry files missing from the
+/// manifest are still loaded, they come from merging entries added on two machines.
+/// Returns the number of entries loaded.
+///
+pub fn load_from_directory(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    dir: &str,
+) -> Result<usize, DirStoreErr> {
+    let dir = Path::new(dir);
+
+    if read_marker(dir, STORE_MARKER_FILE, STORE_MARKER_HEADER)? != vault.pub_key {
+        return Err(DirStoreErr::OtherVault(0));
+    }
+    let manifest = match read_manifest(&dir.join(MANIFEST_FILE), ecies, vault)? {
+        Some(manifest) => manifest,
+        None => {
+            eprintln!("ERROR: The directory snapshot has no manifest!");
+            return Err(DirStoreErr::Incomplete(0));
+        }
+    };
+    if manifest.version > MANIFEST_VERSION {
+        eprintln!("ERROR: The directory snapshot was saved by a newer version of estash!");
+        return Err(DirStoreErr::Corrupted(0));
+    }
+
+    // read and check every entry before changing anything
+    let entries_dir = dir.join(ENTRIES_DIR);
+    let chunk_files = list_files(&dir.join(CHUNKS_DIR))?;
+    let mut entries: Vec<(Vec<u8>, Vec<u8>, Vec<u8>)> = Vec::new();
+    for file_name in list_files(&entries_dir)? {
+        let key = match hex::decode(&file_name) {
+            Ok(key) if key.len() == ENTRY_KEY_SIZE => key,
+            _ => continue,
+        };
+        let value_encrypted = read_file(&entries_dir.join(&file_name))?;
+        let mut value = decrypt_entry(ecies, vault, &value_encrypted)?;
+
+        if core::entry_key(vault, &value.name) != key {
+            eprintln!("ERROR: The entry file {file_name} is not stored under the hash of its name, the snapshot was tampered with!");
+            value.content.zeroize();
+            return Err(DirStoreErr::Corrupted(0));
+        }
+        if let Some(content_stream) = &value.content_stream {
+            for index in 0..content_stream.chunks {
+                if !chunk_files.contains(&hex::encode(content_stream.chunk_key(index))) {
+                    eprintln!("ERROR: A chunk of the content of an entry is missing from the directory snapshot!");
+                    value.content.zeroize();
+                    return Err(DirStoreErr::Incomplete(0));
+                }
+            }
+        }
+
+        let name_encrypted =
+            match ecies.encrypt_bytes_array(value.name.as_bytes(), &vault.priv_key, &vault.pub_key)
+            {
+                Ok(cipher) => cipher,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to encrypt the name of an entry!\n{err}");
+                    value.content.zeroize();
+                    return Err(DirStoreErr::EncryptFailed(0));
+                }
+            };
+        value.content.zeroize();
+        entries.push((key, value_encrypted, name_encrypted));
+    }
+
+    let loaded: BTreeSet<String> = entries.iter().map(|entry| hex::encode(&entry.0)).collect();
+    if manifest
+        .entries
+        .iter()
+        .any(|file_name| !loaded.contains(file_name))
+    {
+        eprintln!("ERROR: Entries listed in the manifest are missing from the directory snapshot!");
+        return Err(DirStoreErr::Incomplete(0));
+    }
+
+    // the chunks go in first, they're only added, so the vault still works if it fails here
+    let chunks_tree = open_tree(vault_db, CONTENT_CHUNKS_TREE)?;
+    for file_name in &chunk_files {
+        let key = match hex::decode(file_name) {
+            Ok(key) => key,
+            Err(_) => continue,
+        };
+        let chunk = read_file(&dir.join(CHUNKS_DIR).join(file_name))?;
+        insert(&chunks_tree, &key, &chunk)?;
+    }
+
+    // then everything else is swapped in at once, either the whole snapshot is loaded or nothing
+    let entry_names_tree = open_tree(vault_db, ENTRY_NAMES_TREE)?;
+    let contacts_tree = open_tree(vault_db, CONTACTS_TREE)?;
+    let members_tree = open_tree(vault_db, MEMBERS_TREE)?;
+    let old_keys = [
+        read_keys(vault_db)?,
+        read_keys(&entry_names_tree)?,
+        read_keys(&contacts_tree)?,
+        read_keys(&members_tree)?,
+    ];
+    let swapped = (
+        &**vault_db,
+        &entry_names_tree,
+        &contacts_tree,
+        &members_tree,
+    )
+        .transaction(|(entries_tx, names_tx, contacts_tx, members_tx)| {
+            for (tree, keys) in [entries_tx, names_tx, contacts_tx, members_tx]
+                .into_iter()
+                .zip(&old_keys)
+            {
+                for key in keys {
+                    tree.remove(key.as_slice())?;
+                }
+            }
+            for (key, value_encrypted, name_encrypted) in &entries {
+                entries_tx.insert(key.as_slice(), value_encrypted.as_slice())?;
+                names_tx.insert(key.as_slice(), name_encrypted.as_slice())?;
+            }
+            for record in &manifest.contacts {
+                contacts_tx.insert(record.key.as_slice(), record.value.as_slice())?;
+            }
+            for record in &manifest.members {
+                members_tx.insert(record.key.as_slice(), record.value.as_slice())?;
+            }
+            Ok::<(), ConflictableTransactionError>(())
+        });
+    if let Err(err) = swapped {
+        eprintln!("ERROR: Failed to load the directory snapshot into the vault db, nothing was changed!\n{err}");
+        return Err(DirStoreErr::DbInaccesible(0));
+    }
+    if settings::set_compression_enabled(vault_db, manifest.compress).is_err() {
+        return Err(DirStoreErr::DbInaccesible(0));
+    }
+
+    // the chunks of the entries that were replaced are left over now
+    for key in read_keys(&chunks_tree)? {
+        if !chunk_files.contains(&hex::encode(&key)) {
+            if let Err(err) = chunks_tree.remove(key) {
+                eprintln!("ERROR: Failed to remove a chunk that's no longer used, it's left in the vault db!\n{err}");
+            }
+        }
+    }
+
+    match vault_db.flush() {
+        Ok(_) => Ok(entries.len()),
+        Err(err) => {
+            eprintln!(
+                "ERROR: Failed to flush the vault db after loading the directory snapshot!\n{err}"
+            );
+            Err(DirStoreErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Writes every key of the tree as a file named by its hex, removing the files
+/// of keys that aren't in the tree anymore. Returns the names of the files.
+///
+fn mirror_tree(
+    tree: &sled::Tree,
+    key_size: Option<usize>,
+    dir: &Path,
+    summary: &mut SaveSummary,
+) -> Result<BTreeSet<String>, DirStoreErr> {
+    if let Err(err) = fs::create_dir_all(dir) {
+        eprintln!("ERROR: Failed to create a folder of the directory snapshot!\n{err}");
+        return Err(DirStoreErr::DirInaccesible(0));
+    }
+
+    let mut file_names = BTreeSet::new();
+    for kv in tree.iter() {
+        let (key, value) = match kv {
+            Ok(kv) => kv,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read from the vault db!\n{err}");
+                return Err(DirStoreErr::DbInaccesible(0));
+            }
+        };
+        if matches!(key_size, Some(key_size) if key.len() != key_size) {
+            continue;
+        }
+        let file_name = hex::encode(&key);
+        if write_if_changed(&dir.join(&file_name), &value)? {
+            summary.written += 1;
+        }
+        file_names.insert(file_name);
+    }
+
+    for file_name in list_files(dir)? {
+        if file_names.contains(&file_name) {
+            continue;
+        }
+        match fs::remove_file(dir.join(&file_name)) {
+            Ok(_) => summary.removed += 1,
+            Err(err) => {
+                eprintln!(
+                    "ERROR: Failed to remove a stale file from the directory snapshot!\n{err}"
+                );
+                return Err(DirStoreErr::DirInaccesible(0));
+            }
+        };
+    }
+
+    Ok(file_names)
+}
+
+///
+/// Writes the file only if its content is different, through a temporary file
+/// so an interrupted save never leaves a half written entry behind.
+/// Returns whether the file was written.
+///
+pub fn write_if_changed(path: &Path, bytes: &[u8]) -> Result<bool, DirStoreErr> {
+    if let Ok(current) = fs::read(path) {
+        if current == bytes {
+            return Ok(false);
+        }
+    }
+
+    let temp_path = path.with_extension("tmp");
+    match fs::write(&temp_path, bytes).and_then(|_| fs::rename(&temp_path, path)) {
+        Ok(_) => Ok(true),
+        Err(err) => {
+            eprintln!("ERROR: Failed to write a file of the directory snapshot!\n{err}");
+            let _ = fs::remove_file(&temp_path);
+            Err(DirStoreErr::DirInaccesible(0))
+        }
+    }
+}
+
+///
+/// Checks the directory belongs to the vault, an empty (or missing) directory is
+/// claimed for it by writing the marker file.
+///
+pub fn claim_directory(
+    dir: &Path,
+    marker_file: &str,
+    marker_header: &str,
+    vault: &Vault,
+) -> Result<(), DirStoreErr> {
+    match read_marker(dir, marker_file, marker_header) {
+        Ok(pub_key) if pub_key == vault.pub_key => Ok(()),
+        Ok(_) => Err(DirStoreErr::OtherVault(0)),
+        Err(DirStoreErr::NotAStore(_)) => {
+            if !is_empty_dir(dir)? {
+                eprintln!("ERROR: The directory is neither empty nor used by this vault, refusing to write in it!");
+                return Err(DirStoreErr::NotAStore(0));
+            }
+            let marker = format!(
+                "{marker_header}\nPublic Key: {}\n",
+                hex::encode(vault.pub_key)
+            );
+            write_if_changed(&dir.join(marker_file), marker.as_bytes())?;
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+///
+/// Reads the public key of the vault the directory belongs to from its marker file.
+///
+pub fn read_marker(
+    dir: &Path,
+    marker_file: &str,
+    marker_header: &str,
+) -> Result<[u8; 32], DirStoreErr> {
+    let marker = match fs::read_to_string(dir.join(marker_file)) {
+        Ok(marker) => marker,
+        Err(_) => return Err(DirStoreErr::NotAStore(0)),
+    };
+    if marker.lines().next() != Some(marker_header) {
+        return Err(DirStoreErr::NotAStore(0));
+    }
+
+    let mut pub_key: [u8; 32] = [0; 32];
+    for line in marker.lines() {
+        if let Some(key_hex) = line.strip_prefix("Public Key:") {
+            if hex::decode_to_slice(key_hex.trim(), &mut pub_key).is_ok() {
+                return Ok(pub_key);
+            }
+        }
+    }
+
+    eprintln!("ERROR: The marker of the directory has no valid public key!");
+    Err(DirStoreErr::Corrupted(0))
+}
+
+///
+/// Reads and decrypts the manifest, None if there isn't one yet.
+///
+fn read_manifest(
+    path: &Path,
+    ecies: &mut ECIES,
+    vault: &Vault,
+) -> Result<Option<Manifest>, DirStoreErr> {
+    let manifest_encrypted = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(None),
+    };
+    let mut manifest_bytes =
+        match ecies.decrypt_bytes_array(&manifest_encrypted, &vault.priv_key, &vault.pub_key) {
+            Ok(plain) => plain,
+            Err(err) => {
+                eprintln!(
+                    "ERROR: Failed to decrypt the manifest of the directory snapshot!\n{err}"
+                );
+                return Err(DirStoreErr::Corrupted(0));
+            }
+        };
+    let manifest = rmp_serde::from_slice(&manifest_bytes);
+    manifest_bytes.zeroize();
+
+    match manifest {
+        Ok(manifest) => Ok(Some(manifest)),
+        Err(err) => {
+            eprintln!(
+                "ERROR: The manifest of the directory snapshot is not in estash's format!\n{err}"
+            );
+            Err(DirStoreErr::Corrupted(0))
+        }
+    }
+}
+
+pub fn decrypt_entry(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    value_encrypted: &[u8],
+) -> Result<VaultValue, DirStoreErr> {
+    let mut value_plain =
+        match ecies.decrypt_bytes_array(value_encrypted, &vault.priv_key, &vault.pub_key) {
+            Ok(plain) => plain,
+            Err(err) => {
+                eprintln!("ERROR: Failed to decrypt an entry of the directory snapshot!\n{err}");
+                return Err(DirStoreErr::Corrupted(0));
+            }
+        };
+    let value = VaultValue::from_bytes(&value_plain);
+    value_plain.zeroize();
+
+    match value {
+        Ok(value) => Ok(value),
+        Err(_) => Err(DirStoreErr::Corrupted(0)),
+    }
+}
+
+fn read_keys(tree: &sled::Tree) -> Result<Vec<Vec<u8>>, DirStoreErr> {
+    let mut keys = Vec::new();
+    for key in tree.iter().keys() {
+        match key {
+            Ok(key) => keys.push(key.to_vec()),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read from the vault db!\n{err}");
+                return Err(DirStoreErr::DbInaccesible(0));
+            }
+        };
+    }
+
+    Ok(keys)
+}
+
+fn read_records(tree: &sled::Tree) -> Result<Vec<TreeRecord>, DirStoreErr> {
+    let mut records = Vec::new();
+    for kv in tree.iter() {
+        match kv {
+            Ok((key, value)) => records.push(TreeRecord {
+                key: key.to_vec(),
+                value: value.to_vec(),
+            }),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read from the vault db!\n{err}");
+                return Err(DirStoreErr::DbInaccesible(0));
+            }
+        };
+    }
+
+    Ok(records)
+}
+
+///
+/// The names of the files in the directory, empty if it doesn't exist.
+///
+pub fn list_files(dir: &Path) -> Result<BTreeSet<String>, DirStoreErr> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return Ok(BTreeSet::new()),
+    };
+
+    let mut file_names = BTreeSet::new();
+    for dir_entry in read_dir {
+        match dir_entry {
+            Ok(dir_entry) => {
+                if let Some(file_name) = dir_entry.file_name().to_str() {
+                    file_names.insert(file_name.to_owned());
+                }
+            }
+            Err(err) => {
+                eprintln!("ERROR: Failed to list a folder of the directory snapshot!\n{err}");
+                return Err(DirStoreErr::DirInaccesible(0));
+            }
+        };
+    }
+
+    Ok(file_names)
+}
+
+fn is_empty_dir(dir: &Path) -> Result<bool, DirStoreErr> {
+    match fs::read_dir(dir) {
+        Ok(mut read_dir) => Ok(read_dir.next().is_none()),
+        Err(_) => match fs::create_dir_all(dir) {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                eprintln!("ERROR: Failed to create the directory snapshot!\n{err}");
+                Err(DirStoreErr::DirInaccesible(0))
+            }
+        },
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<Vec<u8>, DirStoreErr> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(bytes),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read a file of the directory snapshot!\n{err}");
+            Err(DirStoreErr::DirInaccesible(0))
+        }
+    }
+}
+
+fn insert(tree: &sled::Tree, key: &[u8], value: &[u8]) -> Result<(), DirStoreErr> {
+    match tree.insert(key, value) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!(
+                "ERROR: Failed to store a value from the directory snapshot in the vault db!\n{err}"
+            );
+            Err(DirStoreErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn open_tree(vault_db: &Db, name: &str) -> Result<sled::Tree, DirStoreErr> {
+    match vault_db.open_tree(name) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the {name} tree of the vault db!\n{err}");
+            Err(DirStoreErr::DbInaccesible(0))
+        }
+    }
+}
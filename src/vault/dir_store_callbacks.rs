@@ -0,0 +1,216 @@
+use super::dir_store::{self, DirStoreErr};
+use super::settings;
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Directory Snapshot/Save Snapshot menu item.
+/// Writes the vault into a directory snapshot, only touching the files
+/// of the entries that changed since the last save.
+///
+pub fn save_to_directory_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let dir = match choose_directory(
+        "Select the directory snapshot",
+        settings::get_store_directory(&vault_db),
+    ) {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    let saved = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => {
+            dir_store::save_to_directory(&vault_db, &mut ecies, &vault, &dir)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    match saved {
+        Ok(summary) => {
+            let _ = settings::set_store_directory(&vault_db, &dir);
+            status_label.set_label(&format!(
+                "Saved to the directory snapshot, {} files written, {} removed!",
+                summary.written, summary.removed
+            ));
+            status_label.show();
+        }
+        Err(DirStoreErr::NotAStore(_)) => {
+            status_label.set_label("Pick an empty directory or a snapshot of this vault!");
+            status_label.show();
+        }
+        Err(DirStoreErr::OtherVault(_)) => {
+            status_label.set_label("That directory is a snapshot of another vault!");
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to save to the directory snapshot, try again!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Directory Snapshot/Load Snapshot menu item.
+/// Replaces the contents of the vault with the ones of a directory snapshot,
+/// after checking every entry in it, and lists the loaded entries.
+///
+pub fn load_from_directory_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let dir = match choose_directory(
+        "Select the directory snapshot to load",
+        settings::get_store_directory(&vault_db),
+    ) {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    match dialog::choice2_default(
+        "Replace everything in the vault with the contents of the directory snapshot?",
+        "No",
+        "Replace",
+        "",
+    ) {
+        Some(1) => (),
+        _ => return,
+    };
+
+    let loaded = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => {
+            dir_store::load_from_directory(&vault_db, &mut ecies, &vault, &dir)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let loaded = match loaded {
+        Ok(loaded) => loaded,
+        Err(DirStoreErr::NotAStore(_)) => {
+            status_label.set_label("That directory is not a directory snapshot!");
+            status_label.show();
+            return;
+        }
+        Err(DirStoreErr::OtherVault(_)) => {
+            status_label.set_label("That directory is a snapshot of another vault!");
+            status_label.show();
+            return;
+        }
+        Err(DirStoreErr::Incomplete(_)) => {
+            status_label.set_label("The snapshot is missing files, finish the checkout or merge!");
+            status_label.show();
+            return;
+        }
+        Err(DirStoreErr::Corrupted(_)) => {
+            status_label.set_label("The snapshot has files that don't belong to this vault!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to load the directory snapshot, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let _ = settings::set_store_directory(&vault_db, &dir);
+
+    // drop arc ref
+    drop(vault_db);
+
+    // list the loaded entries instead of the old ones
+    let mut entries = match entries_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under entries_arc ARC!\n{err}");
+            status_label.set_label("Loaded the snapshot, reopen the vault to see the entries!");
+            status_label.show();
+            return;
+        }
+    };
+    match vault_arc.lock() {
+        Ok(vault) => {
+            super::core::reload_entries(&vault, vault_db_arc, ecies_arc, &mut entries);
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+        }
+    };
+
+    status_label.set_label(&format!(
+        "Loaded {loaded} entries from the directory snapshot!"
+    ));
+    status_label.show();
+}
+
+///
+/// Asks for a directory, starting from the one used last time.
+///
+pub fn choose_directory(title: &str, last_dir: Option<String>) -> Option<String> {
+    let mut dir_dialog = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseDir);
+    dir_dialog.set_title(title);
+    if let Some(last_dir) = last_dir {
+        let _ = dir_dialog.set_directory(&last_dir);
+    }
+    dir_dialog.show();
+
+    let dir = dir_dialog.filename().to_string_lossy().to_string();
+    if dir.is_empty() {
+        None
+    } else {
+        Some(dir)
+    }
+}
@@ -0,0 +1,371 @@
+use super::content_stream;
+use super::core::{UpdateContentErr, VaultValue, VaultValueErr, ENTRY_NAMES_TREE};
+use super::install_targets;
+use super::path_vars;
+use super::templates::{self, TemplateErr};
+use crate::encrypter::ecies::ECIES;
+use crate::hasher::blake3;
+use crate::utils::{self, Vault};
+use sled::Db;
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+///
+/// How the file at the install path of an entry compares to its content in the vault.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DriftState {
+    InSync,
+    Modified,
+    Missing,
+}
+
+///
+/// An entry installed to a path on this machine, compared to what's on disk.
+///
+pub struct Drift {
+    pub entry: String,
+    pub path: String,
+    pub state: DriftState,
+    pub is_template: bool,
+}
+
+pub struct DriftCheck {
+    pub drifts: Vec<Drift>,
+    // entries that install here but couldn't be compared, with why
+    pub unchecked: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum DriftErr {
+    PoisonErr(u16),
+    DbInaccesible(u16),
+    Corrupted(u16),
+    UnknownVariables(u16),
+    NotText(u16),
+    Unreadable(u16),
+}
+
+///
+/// Compares every entry with an install path on this machine to the file there,
+/// by the blake3 hash of the content it would install, templates rendered.
+/// Entries with no install path here are left out.
+/// The caller must not hold any of the arcs.
+///
+pub fn check_drift(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+) -> Result<DriftCheck, DriftErr> {
+    let entry_names = match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => {
+            list_entry_names(&vault_db, &mut ecies, &vault)?
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC!");
+            return Err(DriftErr::PoisonErr(0));
+        }
+    };
+
+    let mut check = DriftCheck {
+        drifts: Vec::new(),
+        unchecked: Vec::new(),
+    };
+    for entry_name in entry_names {
+        let mut value = match read_value(vault_db_arc, ecies_arc, vault_arc, &entry_name) {
+            Ok(value) => value,
+            Err(DriftErr::PoisonErr(_)) => return Err(DriftErr::PoisonErr(0)),
+            Err(_) => {
+                check.unchecked.push(format!(
+                    "{entry_name}: the entry can't be read, try verifying the vault"
+                ));
+                continue;
+            }
+        };
+        let path = match target_path(vault_db_arc, &value) {
+            Ok(Some(path)) => path,
+            Ok(None) => continue,
+            Err(DriftErr::UnknownVariables(_)) => {
+                check.unchecked.push(format!(
+                    "{entry_name}: its install path uses variables not set here"
+                ));
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        let expected_hash = match expected_hash(vault_db_arc, ecies_arc, vault_arc, &mut value) {
+            Ok(expected_hash) => expected_hash,
+            Err(DriftErr::UnknownVariables(_)) => {
+                check.unchecked.push(format!(
+                    "{entry_name}: the template uses variables not set here"
+                ));
+                continue;
+            }
+            Err(DriftErr::NotText(_)) => {
+                check
+                    .unchecked
+                    .push(format!("{entry_name}: the template is not text"));
+                continue;
+            }
+            Err(DriftErr::PoisonErr(_)) => return Err(DriftErr::PoisonErr(0)),
+            Err(_) => {
+                check.unchecked.push(format!(
+                    "{entry_name}: the content can't be read, try verifying the vault"
+                ));
+                continue;
+            }
+        };
+
+        // hashed as it's read, so big files aren't read into memory
+        let state = match blake3::hash_file(&path) {
+            Ok(installed_hash) if installed_hash == expected_hash => DriftState::InSync,
+            Ok(_) => DriftState::Modified,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => DriftState::Missing,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read the installed file {path}!\n{err}");
+                check
+                    .unchecked
+                    .push(format!("{entry_name}: {path} can't be read"));
+                continue;
+            }
+        };
+        check.drifts.push(Drift {
+            entry: entry_name,
+            path,
+            state,
+            is_template: value.is_template,
+        });
+    }
+    check.drifts.sort_by(|a, b| a.entry.cmp(&b.entry));
+
+    Ok(check)
+}
+
+///
+/// The path the entry installs to on this machine, with its variables expanded,
+/// None if no install path applies here.
+///
+pub fn target_path(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    value: &VaultValue,
+) -> Result<Option<String>, DriftErr> {
+    let resolved = match install_targets::resolve(
+        &value.install_path,
+        &value.install_targets,
+        std::env::consts::OS,
+        &utils::hostname(),
+    ) {
+        Some(resolved) => resolved,
+        None => return Ok(None),
+    };
+
+    let expanded_path = match vault_db_arc.lock() {
+        Ok(vault_db) => path_vars::expand_with_settings(&vault_db, &resolved.path),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            return Err(DriftErr::PoisonErr(0));
+        }
+    };
+    if !expanded_path.unknown.is_empty() {
+        return Err(DriftErr::UnknownVariables(0));
+    }
+
+    Ok(Some(expanded_path.path))
+}
+
+///
+/// The blake3 hash of the content Install writes for the entry on this machine,
+/// a big content that's not a template is hashed chunk by chunk as it's decrypted.
+///
+pub fn expected_hash(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    value: &mut VaultValue,
+) -> Result<[u8; 32], DriftErr> {
+    if !value.is_template {
+        if let Some(content_stream) = &value.content_stream {
+            return match vault_db_arc.lock() {
+                Ok(vault_db) => match content_stream::hash_stream(&vault_db, content_stream) {
+                    Ok(hash) => Ok(hash),
+                    Err(_) => Err(DriftErr::Corrupted(0)),
+                },
+                Err(err) => {
+                    eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                    Err(DriftErr::PoisonErr(0))
+                }
+            };
+        }
+    }
+
+    let mut expected = expected_content(vault_db_arc, ecies_arc, vault_arc, value)?;
+    let expected_hash = blake3::hash_bytes(&expected);
+    expected.zeroize();
+
+    Ok(expected_hash)
+}
+
+///
+/// The content Install writes for the entry on this machine, a big content read
+/// back from its chunks and a template rendered with the variables of this machine.
+///
+pub fn expected_content(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    value: &mut VaultValue,
+) -> Result<Vec<u8>, DriftErr> {
+    let mut content = match value.content_stream.take() {
+        Some(content_stream) => match vault_db_arc.lock() {
+            Ok(vault_db) => match content_stream::read_to_vec(&vault_db, &content_stream) {
+                Ok(content) => content,
+                Err(_) => return Err(DriftErr::Corrupted(0)),
+            },
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                return Err(DriftErr::PoisonErr(0));
+            }
+        },
+        None => std::mem::take(&mut value.content),
+    };
+    if !value.is_template {
+        return Ok(content);
+    }
+
+    let rendered = templates::render_with_vault(vault_db_arc, ecies_arc, vault_arc, &content);
+    content.zeroize();
+    match rendered {
+        Ok(rendered) if rendered.unknown.is_empty() => Ok(rendered.text.as_bytes().to_vec()),
+        Ok(_) => Err(DriftErr::UnknownVariables(0)),
+        Err(TemplateErr::NotText(_)) => Err(DriftErr::NotText(0)),
+        Err(TemplateErr::PoisonErr(_)) => Err(DriftErr::PoisonErr(0)),
+        Err(_) => Err(DriftErr::Corrupted(0)),
+    }
+}
+
+///
+/// Replaces the content of the entry with the file installed at the path,
+/// a big file is stored chunk by chunk like a big selected file.
+/// The caller must not hold any of the arcs.
+///
+pub fn pull_into_vault(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    entry_name: &str,
+    path: &str,
+) -> Result<(), DriftErr> {
+    let file_size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the installed file {path}!\n{err}");
+            return Err(DriftErr::Unreadable(0));
+        }
+    };
+
+    let (content, content_stream) = if file_size > content_stream::STREAM_THRESHOLD {
+        match vault_db_arc.lock() {
+            Ok(vault_db) => match content_stream::import_file(&vault_db, path) {
+                Ok(content_stream) => (Vec::new(), Some(content_stream)),
+                Err(_) => return Err(DriftErr::Unreadable(0)),
+            },
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                return Err(DriftErr::PoisonErr(0));
+            }
+        }
+    } else {
+        match fs::read(path) {
+            Ok(content) => (content, None),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read the installed file {path}!\n{err}");
+                return Err(DriftErr::Unreadable(0));
+            }
+        }
+    };
+
+    match super::core::update_content_in_entry(
+        Arc::new(Mutex::new(entry_name.to_owned())),
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        content,
+        content_stream,
+    ) {
+        Ok(_) => Ok(()),
+        Err(UpdateContentErr::PoisonErr(_)) => Err(DriftErr::PoisonErr(0)),
+        Err(_) => Err(DriftErr::DbInaccesible(0)),
+    }
+}
+
+///
+/// The stored value of the entry.
+///
+pub fn read_value(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    entry_name: &str,
+) -> Result<VaultValue, DriftErr> {
+    match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        entry_name,
+    ) {
+        Ok(value) => Ok(value),
+        Err(VaultValueErr::PoisonErr(_)) => Err(DriftErr::PoisonErr(0)),
+        Err(_) => Err(DriftErr::Corrupted(0)),
+    }
+}
+
+///
+/// The names of every entry in the vault, the ones that can't be decrypted are
+/// skipped like when the vault is opened.
+///
+fn list_entry_names(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+) -> Result<Vec<String>, DriftErr> {
+    let entry_names_tree = match vault_db.open_tree(ENTRY_NAMES_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the entry names tree of the vault db!\n{err}");
+            return Err(DriftErr::DbInaccesible(0));
+        }
+    };
+
+    let mut entry_names = Vec::new();
+    for entry in entry_names_tree.iter() {
+        let entry_name_encrypted = match entry {
+            Ok(kv) => kv.1.to_vec(),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read the entry names of the vault db!\n{err}");
+                return Err(DriftErr::DbInaccesible(0));
+            }
+        };
+        let entry_name = match ecies.decrypt_bytes(
+            &entry_name_encrypted,
+            &vault.priv_key,
+            &vault.pub_key,
+        ) {
+            Ok(plain) => String::from_utf8(plain),
+            Err(err) => {
+                eprintln!("ERROR: Failed to decrypt an entry name, skipping it, use Verify Vault to check the db!\n{err}");
+                continue;
+            }
+        };
+        match entry_name {
+            Ok(entry_name) => entry_names.push(entry_name),
+            Err(_) => {
+                eprintln!("ERROR: An entry name is not utf8, skipping it, use Verify Vault to check the db!");
+            }
+        };
+    }
+
+    Ok(entry_names)
+}
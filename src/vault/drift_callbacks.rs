@@ -0,0 +1,317 @@
+use super::audit::{self, AuditAction};
+use super::drift::{self, Drift, DriftErr, DriftState};
+use super::installs::{self, InstallContent, InstallsErr};
+use super::installs_callbacks;
+use super::report_window;
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+///
+/// Callback for the Installed Files/Check Drift menu item.
+/// Lists the entries installed on this machine as in sync, modified on disk or
+/// missing, then offers to pull each modified file into the vault or to
+/// re-install it, and to re-install each missing one.
+///
+pub fn check_drift_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    content_arc: Arc<Mutex<input::MultilineInput>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let check = match drift::check_drift(&vault_db_arc, &ecies_arc, &vault_arc) {
+        Ok(check) => check,
+        Err(DriftErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the entries, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let count = |state| {
+        check
+            .drifts
+            .iter()
+            .filter(|drift| drift.state == state)
+            .count()
+    };
+    let (in_sync, modified, missing) = (
+        count(DriftState::InSync),
+        count(DriftState::Modified),
+        count(DriftState::Missing),
+    );
+
+    let mut report = String::from("Installed entries compared to the vault on this machine\n");
+    for (state, title) in [
+        (DriftState::Modified, "Modified on disk"),
+        (DriftState::Missing, "Missing"),
+        (DriftState::InSync, "In sync"),
+    ] {
+        report += &format!("\n{title}:\n");
+        let mut listed = false;
+        for drift in check.drifts.iter().filter(|drift| drift.state == state) {
+            report += &format!("  {}  ->  {}\n", drift.entry, drift.path);
+            listed = true;
+        }
+        if !listed {
+            report += "  none\n";
+        }
+    }
+    if !check.unchecked.is_empty() {
+        report += "\nNot checked:\n";
+        for unchecked in &check.unchecked {
+            report += &format!("  {unchecked}\n");
+        }
+    }
+    report_window::show("Drift Check", &report);
+
+    let mut pulled = 0;
+    let mut reinstalled = 0;
+    for drift in &check.drifts {
+        let choice = match drift.state {
+            DriftState::InSync => continue,
+            DriftState::Modified => dialog::choice2_default(
+                &format!(
+                    "{} was modified at {}.\nPull the file into the vault, or re-install the vault's content over it?",
+                    drift.entry, drift.path
+                ),
+                "Skip",
+                "Pull into Vault",
+                "Re-install",
+            ),
+            DriftState::Missing => dialog::choice2_default(
+                &format!(
+                    "{} is missing from {}.\nRe-install it from the vault?",
+                    drift.entry, drift.path
+                ),
+                "Skip",
+                "Re-install",
+                "",
+            ),
+        };
+
+        let done = match (drift.state, choice) {
+            (DriftState::Modified, Some(1)) if drift.is_template => {
+                status_label.set_label(&format!(
+                    "{} is a template, edit it instead of pulling the rendered file!",
+                    drift.entry
+                ));
+                status_label.show();
+                continue;
+            }
+            (DriftState::Modified, Some(1)) => {
+                pulled += 1;
+                pull(
+                    &mut status_label,
+                    &content_arc,
+                    &current_selected_entry_arc,
+                    &vault_db_arc,
+                    &vault_arc,
+                    &ecies_arc,
+                    drift,
+                )
+            }
+            (DriftState::Modified, Some(2)) | (DriftState::Missing, Some(1)) => {
+                reinstalled += 1;
+                reinstall(
+                    &mut status_label,
+                    &vault_db_arc,
+                    &vault_arc,
+                    &ecies_arc,
+                    drift,
+                )
+            }
+            (_, Some(_)) => continue,
+            (_, None) => break,
+        };
+        if !done {
+            return;
+        }
+    }
+
+    status_label.set_label(&format!(
+        "{in_sync} in sync, {modified} modified, {missing} missing, pulled {pulled} and re-installed {reinstalled}!"
+    ));
+    status_label.show();
+}
+
+///
+/// Replaces the content of the entry with its modified file, and shows it
+/// in the content box when it's the selected entry. False when it failed,
+/// with the status label saying why.
+///
+fn pull(
+    status_label: &mut frame::Frame,
+    content_arc: &Arc<Mutex<input::MultilineInput>>,
+    current_selected_entry_arc: &Arc<Mutex<String>>,
+    vault_db_arc: &Arc<Mutex<Db>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    drift: &Drift,
+) -> bool {
+    match drift::pull_into_vault(
+        vault_db_arc,
+        ecies_arc,
+        vault_arc,
+        &drift.entry,
+        &drift.path,
+    ) {
+        Ok(_) => (),
+        Err(DriftErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return false;
+        }
+        Err(DriftErr::Unreadable(_)) => {
+            status_label.set_label(&format!(
+                "Failed to read {}, nothing was pulled!",
+                drift.path
+            ));
+            status_label.show();
+            return false;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to save the entry, try again or restart!");
+            status_label.show();
+            return false;
+        }
+    };
+    audit::record(
+        vault_db_arc,
+        ecies_arc,
+        vault_arc,
+        AuditAction::Modified,
+        &drift.entry,
+        &format!("pulled from {}", drift.path),
+    );
+
+    // the content box would save the old content back over the pulled one
+    let is_selected = match current_selected_entry_arc.lock() {
+        Ok(current_selected_entry) => *current_selected_entry == drift.entry,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            false
+        }
+    };
+    if !is_selected {
+        return true;
+    }
+    let value = match drift::read_value(vault_db_arc, ecies_arc, vault_arc, &drift.entry) {
+        Ok(value) => value,
+        Err(_) => return true,
+    };
+    match content_arc.lock() {
+        Ok(mut content) => {
+            match std::str::from_utf8(&value.content) {
+                Ok(text) if value.content_stream.is_none() && value.content.len() < 32767 => {
+                    content.set_value(text);
+                    content.activate();
+                }
+                _ => {
+                    content.set_value("File content is too big to be displayed!\nContent has been saved to the database automatically!");
+                    content.deactivate();
+                }
+            };
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under content_arc ARC!\n{err}");
+        }
+    };
+    true
+}
+
+///
+/// Installs the content of the entry from the vault again, in its install mode.
+/// False when it failed, with the status label saying why.
+///
+fn reinstall(
+    status_label: &mut frame::Frame,
+    vault_db_arc: &Arc<Mutex<Db>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    drift: &Drift,
+) -> bool {
+    let mut value = match drift::read_value(vault_db_arc, ecies_arc, vault_arc, &drift.entry) {
+        Ok(value) => value,
+        Err(DriftErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return false;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the entry, try verifying the vault!");
+            status_label.show();
+            return false;
+        }
+    };
+    // a big content that's not a template is installed chunk by chunk, like Install does
+    let content_stream = if value.is_template {
+        None
+    } else {
+        value.content_stream.take()
+    };
+    let mut content = match content_stream {
+        Some(_) => Vec::new(),
+        None => match drift::expected_content(vault_db_arc, ecies_arc, vault_arc, &mut value) {
+            Ok(content) => content,
+            Err(_) => {
+                status_label.set_label(&format!(
+                    "Failed to read the content of {}, try verifying the vault!",
+                    drift.entry
+                ));
+                status_label.show();
+                return false;
+            }
+        },
+    };
+
+    let installed = installs::install_content(
+        vault_db_arc,
+        ecies_arc,
+        vault_arc,
+        &drift.entry,
+        &drift.path,
+        value.install_mode,
+        match &content_stream {
+            Some(content_stream) => InstallContent::Stream(content_stream),
+            None => InstallContent::Bytes(&content),
+        },
+    );
+    content.zeroize();
+    match installed {
+        Ok(_) | Err(InstallsErr::NotRecorded(_)) => (),
+        Err(err) => {
+            installs_callbacks::show_install_err(status_label, err);
+            return false;
+        }
+    };
+    audit::record(
+        vault_db_arc,
+        ecies_arc,
+        vault_arc,
+        AuditAction::Installed,
+        &drift.entry,
+        &drift.path,
+    );
+
+    true
+}
@@ -1,5 +1,7 @@
+use super::path_vars;
 use crate::utils;
 use fltk::{prelude::*, *};
+use sled::Db;
 use std::sync::{Arc, Mutex};
 
 ///
@@ -9,6 +11,7 @@ use std::sync::{Arc, Mutex};
 pub fn install_path_check_button_callback(
     status_label_arc: Arc<Mutex<frame::Frame>>,
     install_path_arc_clone: Arc<Mutex<input::Input>>,
+    vault_db_arc: Arc<Mutex<Db>>,
 ) {
     // get the actual object from arcs
     let mut status_label = match status_label_arc.lock() {
@@ -29,12 +32,28 @@ pub fn install_path_check_button_callback(
     };
 
     if install_path.active() {
-        if !utils::is_path_os_valid(&install_path.value()) {
+        let expanded_path = match vault_db_arc.lock() {
+            Ok(vault_db) => path_vars::expand_with_settings(&vault_db, &install_path.value()),
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                status_label.set_label("There was a Poison Error, try again, or try to restart!");
+                status_label.show();
+                return;
+            }
+        };
+        if !expanded_path.unknown.is_empty() {
+            status_label.set_label(&format!(
+                "${} not set on this machine, set it in Path Variables!",
+                expanded_path.unknown.join(", $")
+            ));
+            status_label.show();
+            return;
+        } else if !utils::is_path_os_valid(&expanded_path.path) {
             status_label.set_label("The given path is invalid on the current operating system!");
             status_label.show();
             return;
         } else {
-            status_label.set_label("The given path is valid on the current operating system!");
+            status_label.set_label(&format!("Valid here, installs to {}", expanded_path.path));
             status_label.show();
             return;
         }
@@ -54,6 +73,7 @@ pub fn wind_resize_callback(
     h: i32,
     entrie_add_input_arc: Arc<Mutex<input::Input>>,
     entrie_add_button_arc: Arc<Mutex<button::Button>>,
+    vault_menu_arc: Arc<Mutex<menu::MenuButton>>,
     entrie_name_arc: Arc<Mutex<frame::Frame>>,
     install_path_label_arc: Arc<Mutex<frame::Frame>>,
     enable_install_path_arc: Arc<Mutex<button::Button>>,
@@ -95,6 +115,16 @@ pub fn wind_resize_callback(
         }
     };
 
+    match vault_menu_arc.lock() {
+        Ok(mut o) => {
+            o.set_label_size(font_size_25);
+            o.set_text_size(font_size_25);
+        }
+        Err(err) => {
+            eprintln!("ERROR: There was an error getting value behind vault_menu_arc ARC!\n {err}");
+        }
+    };
+
     match entrie_name_arc.lock() {
         Ok(mut o) => {
             o.set_label_size(font_size);
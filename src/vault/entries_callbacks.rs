@@ -1,10 +1,10 @@
+use super::audit::{self, AuditAction};
 use super::core::{NewEntryErr, VaultValueErr};
 use crate::utils::Vault;
 use crate::ECIES;
 use fltk::{prelude::*, *};
 use sled;
 use sled::Db;
-use std::collections::HashMap;
 use std::process;
 use std::str;
 use std::sync::{Arc, Mutex};
@@ -30,7 +30,6 @@ pub fn entries_callback(
     install_button_arc: Arc<Mutex<button::Button>>,
     status_label_arc: Arc<Mutex<frame::Frame>>,
     current_selected_entry_arc_clone: Arc<Mutex<String>>,
-    db_entries_dict_arc_clone: Arc<Mutex<HashMap<String, Vec<u8>>>>,
     vault_db_arc_clone: Arc<Mutex<Db>>,
     ecies_arc_clone: Arc<Mutex<ECIES>>,
     vault_arc_clone: Arc<Mutex<Vault>>,
@@ -220,7 +219,6 @@ pub fn entries_callback(
             ecies_arc_clone.clone(),
             vault_arc_clone.clone(),
             selected_item,
-            db_entries_dict_arc_clone.clone(),
         ) {
             Ok(json) => json,
             Err(VaultValueErr::DbCorrupted(_)) => {
@@ -246,12 +244,19 @@ pub fn entries_callback(
                 return;
             }
         };
+        audit::record(
+            &vault_db_arc_clone,
+            &ecies_arc_clone,
+            &vault_arc_clone,
+            AuditAction::Viewed,
+            selected_item,
+            "",
+        );
 
         // drop arc ref
         drop(vault_db_arc_clone);
         drop(ecies_arc_clone);
         drop(vault_arc_clone);
-        drop(db_entries_dict_arc_clone);
 
         // set value
         install_path.set_value("");
@@ -264,7 +269,7 @@ pub fn entries_callback(
             enable_install_path.set_label("-");
         }
 
-        if &entry_value_json.content.len() >= &32767 {
+        if entry_value_json.content_stream.is_some() || &entry_value_json.content.len() >= &32767 {
             content.set_value("File content is too big to be displayed!\nThe actual value is kept in the database.");
             content.deactivate();
         } else {
@@ -282,6 +287,13 @@ pub fn entries_callback(
 
         notes.set_value(&entry_value_json.notes);
 
+        // the content box shows the template itself, it's rendered on install
+        if entry_value_json.is_template {
+            content_label.set_label("Content (template)");
+        } else {
+            content_label.set_label("Content");
+        }
+
         // Unhide widgets
         entrie_name.show();
         install_path_label.show();
@@ -311,7 +323,6 @@ pub fn entrie_add_button_callback(
     vault_db_arc_clone: Arc<Mutex<Db>>,
     vault_arc_clone: Arc<Mutex<Vault>>,
     ecies_arc_clone: Arc<Mutex<ECIES>>,
-    db_entries_dict_arc_clone: Arc<Mutex<HashMap<String, Vec<u8>>>>,
     entries_arc_clone: Arc<Mutex<tree::Tree>>,
 ) {
     // get reference from arc
@@ -338,13 +349,17 @@ pub fn entrie_add_button_callback(
     };
 
     if entrie_add_input_value != "ROOT" {
-        let entrie_add_input_value_encrypted = match super::core::add_new_entry(
+        match super::core::add_new_entry(
             vault_db_arc_clone.clone(),
             vault_arc_clone.clone(),
             entrie_add_input_value,
             ecies_arc_clone.clone(),
         ) {
-            Ok(cipher) => cipher,
+            Ok(_) => (),
+            Err(NewEntryErr::AlreadyExists(_)) => {
+                entrie_add_input.set_value("name already exists");
+                return;
+            }
             Err(NewEntryErr::DbCorrupted(_)) => {
                 process::exit(100);
             }
@@ -364,22 +379,6 @@ pub fn entrie_add_button_callback(
         drop(vault_arc_clone);
         drop(ecies_arc_clone);
 
-        match db_entries_dict_arc_clone.lock() {
-            Ok(mut object) => {
-                object.insert(
-                    entrie_add_input_value.to_string(),
-                    entrie_add_input_value_encrypted,
-                );
-            }
-            Err(err) => {
-                eprintln!("ERROR: Failed to get value under db_entries_dict_arc ARC!\n{err}");
-                return;
-            }
-        }
-
-        // drop arc ref
-        drop(db_entries_dict_arc_clone);
-
         entries.add(entrie_add_input_value);
         entries.redraw();
     } else {
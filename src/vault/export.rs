@@ -0,0 +1,524 @@
+use super::content_stream;
+use super::core::{VaultValue, ENTRY_NAMES_TREE};
+use super::import::{ImportErr, ImportedEntry};
+use super::install_targets;
+use super::installs::InstallMode;
+use crate::encrypter::ecies::ECIES;
+use crate::utils::Vault;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::fmt;
+use zeroize::Zeroize;
+
+/// The value of the format field of a JSON export, so other JSON files are refused.
+pub const PLAIN_EXPORT_FORMAT: &str = "estash-plain-export";
+
+/// The version of the export schema, bumped on changes old importers can't read.
+pub const PLAIN_EXPORT_VERSION: u32 = 3;
+
+/// The columns of a CSV export, in order, its first row.
+pub const PLAIN_EXPORT_CSV_HEADER: [&str; 9] = [
+    "name",
+    "install_path",
+    "install_targets",
+    "notes",
+    "template",
+    "install_mode",
+    "post_install",
+    "content_encoding",
+    "content",
+];
+
+/// The columns of the CSV exports made before entries had install targets, templates,
+/// an install mode or a post-install hook, still read.
+const PLAIN_EXPORT_CSV_OLD_HEADERS: [&[&str]; 3] = [
+    &[
+        "name",
+        "install_path",
+        "notes",
+        "content_encoding",
+        "content",
+    ],
+    &[
+        "name",
+        "install_path",
+        "install_targets",
+        "notes",
+        "template",
+        "content_encoding",
+        "content",
+    ],
+    &[
+        "name",
+        "install_path",
+        "install_targets",
+        "notes",
+        "template",
+        "install_mode",
+        "content_encoding",
+        "content",
+    ],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+///
+/// How the content of an entry is written, text stays readable,
+/// anything that isn't valid UTF-8 is base64 so it comes back byte for byte.
+///
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    Utf8,
+    Base64,
+}
+
+impl ContentEncoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Utf8 => "utf8",
+            ContentEncoding::Base64 => "base64",
+        }
+    }
+}
+
+///
+/// An entry as it's exported, the same fields in JSON and CSV.
+///
+#[derive(Serialize, Deserialize)]
+pub struct PlainEntry {
+    pub name: String,
+    #[serde(default)]
+    pub install_path: String,
+    #[serde(default)]
+    pub install_targets: PlainTargets,
+    #[serde(default)]
+    pub notes: String,
+    // the content is a template, written as it is, not rendered
+    #[serde(default)]
+    pub template: bool,
+    // copy or symlink
+    #[serde(default)]
+    pub install_mode: InstallMode,
+    // the command run after the entry is installed
+    #[serde(default)]
+    pub post_install: String,
+    pub content_encoding: ContentEncoding,
+    #[serde(default)]
+    pub content: String,
+}
+
+impl Drop for PlainEntry {
+    fn drop(&mut self) {
+        self.install_path.zeroize();
+        self.notes.zeroize();
+        self.post_install.zeroize();
+        self.content.zeroize();
+    }
+}
+
+///
+/// An install target as it's exported, the machine written as an OS name or host:NAME.
+///
+#[derive(Serialize, Deserialize)]
+pub struct PlainTarget {
+    pub machine: String,
+    pub path: String,
+}
+
+impl Drop for PlainTarget {
+    fn drop(&mut self) {
+        self.machine.zeroize();
+        self.path.zeroize();
+    }
+}
+
+///
+/// The install targets of an entry, a list in JSON, and the same list as JSON text
+/// in a CSV cell. Exports from before version 3 wrote them as machine=path separated by ;
+/// which is still read.
+///
+#[derive(Serialize, Default)]
+#[serde(transparent)]
+pub struct PlainTargets(pub Vec<PlainTarget>);
+
+impl<'de> Deserialize<'de> for PlainTargets {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PlainTargetsVisitor)
+    }
+}
+
+struct PlainTargetsVisitor;
+
+impl<'de> Visitor<'de> for PlainTargetsVisitor {
+    type Value = PlainTargets;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of install targets")
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+        if text.trim_start().starts_with('[') {
+            return serde_json::from_str(text).map_err(E::custom);
+        }
+
+        match install_targets::parse_targets(text) {
+            Ok(targets) => Ok(PlainTargets(
+                targets
+                    .iter()
+                    .map(|target| PlainTarget {
+                        machine: install_targets::format_machine(&target.machine),
+                        path: target.path.to_owned(),
+                    })
+                    .collect(),
+            )),
+            Err(_) => Err(E::custom("the install targets are malformed")),
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut targets = Vec::new();
+        while let Some(target) = seq.next_element()? {
+            targets.push(target);
+        }
+
+        Ok(PlainTargets(targets))
+    }
+}
+
+///
+/// The whole JSON export.
+///
+#[derive(Serialize, Deserialize)]
+struct PlainExport {
+    format: String,
+    version: u32,
+    entries: Vec<PlainEntry>,
+}
+
+pub struct ExportSummary {
+    pub exported: usize,
+    // entries that couldn't be read, Verify Vault lists them
+    pub skipped: usize,
+}
+
+#[derive(Debug)]
+pub enum ExportErr {
+    DbInaccesible(u16),
+    Unencodable(u16),
+}
+
+///
+/// Decrypts every entry of the vault and writes them all, unencrypted, as one
+/// JSON or CSV document following the export schema.
+/// Entries that can't be read are skipped and counted.
+///
+pub fn export_entries(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    format: ExportFormat,
+) -> Result<(String, ExportSummary), ExportErr> {
+    let entry_names_tree = match vault_db.open_tree(ENTRY_NAMES_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the entry names tree of the vault db!\n{err}");
+            return Err(ExportErr::DbInaccesible(0));
+        }
+    };
+
+    let mut summary = ExportSummary {
+        exported: 0,
+        skipped: 0,
+    };
+    let mut entries = Vec::new();
+    for key in entry_names_tree.iter().keys() {
+        let key = match key {
+            Ok(key) => key,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read the entry names of the vault db!\n{err}");
+                return Err(ExportErr::DbInaccesible(0));
+            }
+        };
+        let value_encrypted = match vault_db.get(&key) {
+            Ok(Some(value)) => value,
+            Ok(None) => continue,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read an entry from the vault db!\n{err}");
+                return Err(ExportErr::DbInaccesible(0));
+            }
+        };
+
+        match read_entry(vault_db, ecies, vault, &value_encrypted) {
+            Some(entry) => entries.push(entry),
+            None => summary.skipped += 1,
+        };
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    summary.exported = entries.len();
+
+    let document = match format {
+        ExportFormat::Json => {
+            let export = PlainExport {
+                format: PLAIN_EXPORT_FORMAT.to_string(),
+                version: PLAIN_EXPORT_VERSION,
+                entries,
+            };
+            match serde_json::to_string_pretty(&export) {
+                Ok(document) => document,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to encode the export as json!\n{err}");
+                    return Err(ExportErr::Unencodable(0));
+                }
+            }
+        }
+        ExportFormat::Csv => write_csv(&entries)?,
+    };
+
+    Ok((document, summary))
+}
+
+///
+/// Reads an export written by export_entries back into entries, the content
+/// decoded to exactly the bytes that were exported.
+///
+pub fn read_plain_export(
+    document: &str,
+    format: ExportFormat,
+) -> Result<Vec<ImportedEntry>, ImportErr> {
+    let plain_entries = match format {
+        ExportFormat::Json => {
+            let export: PlainExport = match serde_json::from_str(document) {
+                Ok(export) => export,
+                Err(err) => {
+                    eprintln!("ERROR: The file is not an estash export!\n{err}");
+                    return Err(ImportErr::Corrupted(0));
+                }
+            };
+            if export.format != PLAIN_EXPORT_FORMAT {
+                eprintln!("ERROR: The file is not an estash export!");
+                return Err(ImportErr::Corrupted(0));
+            }
+            if export.version > PLAIN_EXPORT_VERSION {
+                eprintln!("ERROR: The export was made by a newer version of estash!");
+                return Err(ImportErr::Unsupported(0));
+            }
+            export.entries
+        }
+        ExportFormat::Csv => read_csv(document)?,
+    };
+
+    let mut entries = Vec::new();
+    for mut plain_entry in plain_entries {
+        let content = match plain_entry.content_encoding {
+            ContentEncoding::Utf8 => plain_entry.content.as_bytes().to_vec(),
+            ContentEncoding::Base64 => match base64::decode(&plain_entry.content) {
+                Ok(content) => content,
+                Err(err) => {
+                    eprintln!(
+                        "ERROR: The content of {} is not valid base64!\n{err}",
+                        plain_entry.name
+                    );
+                    return Err(ImportErr::Corrupted(0));
+                }
+            },
+        };
+        let install_targets = match read_targets(&plain_entry.install_targets) {
+            Ok(install_targets) => install_targets,
+            Err(_) => {
+                eprintln!(
+                    "ERROR: The install targets of {} are malformed!",
+                    plain_entry.name
+                );
+                return Err(ImportErr::Corrupted(0));
+            }
+        };
+
+        entries.push(ImportedEntry {
+            name: std::mem::take(&mut plain_entry.name),
+            install_path: std::mem::take(&mut plain_entry.install_path),
+            content,
+            content_file: None,
+            notes: std::mem::take(&mut plain_entry.notes),
+            install_targets,
+            is_template: plain_entry.template,
+            install_mode: plain_entry.install_mode,
+            post_install: std::mem::take(&mut plain_entry.post_install),
+        });
+    }
+
+    Ok(entries)
+}
+
+///
+/// Decrypts an entry, with a big content read back from its chunks.
+///
+fn read_entry(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    value_encrypted: &[u8],
+) -> Option<PlainEntry> {
+    let mut value_plain =
+        match ecies.decrypt_bytes_array(value_encrypted, &vault.priv_key, &vault.pub_key) {
+            Ok(plain) => plain,
+            Err(err) => {
+                eprintln!("ERROR: Failed to decrypt an entry for the export!\n{err}");
+                return None;
+            }
+        };
+    let value = VaultValue::from_bytes(&value_plain);
+    value_plain.zeroize();
+    let mut value = match value {
+        Ok(value) => value,
+        Err(_) => return None,
+    };
+
+    let mut content = match value.content_stream.take() {
+        Some(content_stream) => match content_stream::read_to_vec(vault_db, &content_stream) {
+            Ok(content) => content,
+            Err(_) => {
+                eprintln!(
+                    "ERROR: Failed to read the content of {} for the export!",
+                    value.name
+                );
+                return None;
+            }
+        },
+        None => std::mem::take(&mut value.content),
+    };
+    let (content_encoding, encoded_content) = match std::str::from_utf8(&content) {
+        Ok(text) => (ContentEncoding::Utf8, text.to_owned()),
+        Err(_) => (ContentEncoding::Base64, base64::encode(&content)),
+    };
+    content.zeroize();
+
+    Some(PlainEntry {
+        name: std::mem::take(&mut value.name),
+        install_path: std::mem::take(&mut value.install_path),
+        install_targets: PlainTargets(
+            value
+                .install_targets
+                .iter()
+                .map(|target| PlainTarget {
+                    machine: install_targets::format_machine(&target.machine),
+                    path: target.path.to_owned(),
+                })
+                .collect(),
+        ),
+        notes: std::mem::take(&mut value.notes),
+        template: value.is_template,
+        install_mode: value.install_mode,
+        post_install: std::mem::take(&mut value.post_install),
+        content_encoding,
+        content: encoded_content,
+    })
+}
+
+///
+/// The exported targets back as install targets, with their paths exactly as exported.
+///
+fn read_targets(
+    plain_targets: &PlainTargets,
+) -> Result<Vec<install_targets::InstallTarget>, install_targets::InstallTargetErr> {
+    let mut targets = Vec::new();
+    for plain_target in &plain_targets.0 {
+        let machine = install_targets::parse_machine(&plain_target.machine)?;
+        install_targets::add_target(&mut targets, machine, &plain_target.path)?;
+    }
+
+    Ok(targets)
+}
+
+fn write_csv(entries: &[PlainEntry]) -> Result<String, ExportErr> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if let Err(err) = writer.write_record(PLAIN_EXPORT_CSV_HEADER) {
+        eprintln!("ERROR: Failed to encode the export as csv!\n{err}");
+        return Err(ExportErr::Unencodable(0));
+    }
+    for entry in entries {
+        // the targets are written as JSON, so any path comes back as it was
+        let mut install_targets = match serde_json::to_string(&entry.install_targets) {
+            Ok(install_targets) => install_targets,
+            Err(err) => {
+                eprintln!("ERROR: Failed to encode the install targets as json!\n{err}");
+                return Err(ExportErr::Unencodable(0));
+            }
+        };
+        let written = writer.write_record([
+            entry.name.as_str(),
+            entry.install_path.as_str(),
+            install_targets.as_str(),
+            entry.notes.as_str(),
+            if entry.template { "true" } else { "false" },
+            entry.install_mode.as_str(),
+            entry.post_install.as_str(),
+            entry.content_encoding.as_str(),
+            entry.content.as_str(),
+        ]);
+        install_targets.zeroize();
+        if let Err(err) = written {
+            eprintln!("ERROR: Failed to encode the export as csv!\n{err}");
+            return Err(ExportErr::Unencodable(0));
+        }
+    }
+
+    let bytes = match writer.into_inner() {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the export as csv!\n{err}");
+            return Err(ExportErr::Unencodable(0));
+        }
+    };
+    match String::from_utf8(bytes) {
+        Ok(document) => Ok(document),
+        Err(err) => {
+            let mut bytes = err.into_bytes();
+            bytes.zeroize();
+            eprintln!("ERROR: The csv export is not valid UTF-8!");
+            Err(ExportErr::Unencodable(0))
+        }
+    }
+}
+
+fn read_csv(document: &str) -> Result<Vec<PlainEntry>, ImportErr> {
+    let mut reader = csv::Reader::from_reader(document.as_bytes());
+    match reader.headers() {
+        Ok(headers)
+            if headers.iter().eq(PLAIN_EXPORT_CSV_HEADER.iter().copied())
+                || PLAIN_EXPORT_CSV_OLD_HEADERS
+                    .iter()
+                    .any(|old_header| headers.iter().eq(old_header.iter().copied())) => {}
+        _ => {
+            eprintln!("ERROR: The file is not an estash csv export, the columns don't match!");
+            return Err(ImportErr::Corrupted(0));
+        }
+    };
+
+    let mut entries = Vec::new();
+    for record in reader.deserialize::<PlainEntry>() {
+        match record {
+            Ok(entry) => entries.push(entry),
+            Err(err) => {
+                eprintln!("ERROR: A row of the csv export is malformed!\n{err}");
+                return Err(ImportErr::Corrupted(0));
+            }
+        };
+    }
+
+    Ok(entries)
+}
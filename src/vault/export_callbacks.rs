@@ -0,0 +1,108 @@
+use super::audit::{self, AuditAction};
+use super::export::{self, ExportFormat};
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+///
+/// Callback for the Export/Plaintext JSON and Export/Plaintext CSV menu items.
+/// After a warning that has to be confirmed, writes every entry of the vault,
+/// unencrypted, to a file following the export schema.
+///
+pub fn export_plain_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    format: ExportFormat,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    match dialog::choice2_default(
+        "The export holds every entry of the vault UNENCRYPTED, anyone who can read the file can read all of them.\nOnly export to a place you trust and delete the file once you're done with it.",
+        "Cancel",
+        "Export Unencrypted",
+        "",
+    ) {
+        Some(1) => (),
+        _ => return,
+    };
+
+    let vault_name = match vault_arc.lock() {
+        Ok(vault) => vault.vault_name.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut file_dialog =
+        dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+    file_dialog.set_title("Export the vault unencrypted");
+    file_dialog.set_preset_file(&format!("{vault_name}-export.{}", format.extension()));
+    file_dialog.show();
+    let path = file_dialog.filename().to_string_lossy().to_string();
+    if path.is_empty() {
+        return;
+    }
+
+    let exported = match (vault_db_arc.lock(), vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault_db), Ok(vault), Ok(mut ecies)) => {
+            export::export_entries(&vault_db, &mut ecies, &vault, format)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let (mut document, summary) = match exported {
+        Ok(exported) => exported,
+        Err(_) => {
+            status_label.set_label("Failed to export the vault, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let written = fs::write(&path, &document);
+    document.zeroize();
+    if let Err(err) = written {
+        eprintln!("ERROR: Failed to write the export!\n{err}");
+        status_label.set_label("Failed to write the file, try another location!");
+        status_label.show();
+        return;
+    }
+
+    audit::record(
+        &vault_db_arc,
+        &ecies_arc,
+        &vault_arc,
+        AuditAction::Exported,
+        "",
+        &format!("{} entries to {path}", summary.exported),
+    );
+
+    let mut status = format!("Exported {} entries UNENCRYPTED", summary.exported);
+    if summary.skipped > 0 {
+        status += &format!(
+            ", {} unreadable ones were left out, see Verify Vault",
+            summary.skipped
+        );
+    }
+    status_label.set_label(&(status + "!"));
+    status_label.show();
+}
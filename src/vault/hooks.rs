@@ -0,0 +1,225 @@
+use super::settings::{self, SettingsErr};
+use crate::utils::{self, Vault};
+use sled::Db;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long a post-install hook can run before it's killed, in seconds.
+pub const HOOK_TIMEOUT_SECS: u64 = 60;
+
+/// How often a running hook is checked on.
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How much of a hook's output is kept, the rest from the start is dropped
+/// since only the last lines are shown.
+const HOOK_OUTPUT_KEPT_BYTES: usize = 65536;
+
+/// How many of the last lines of a hook's output are shown in the status area.
+const HOOK_OUTPUT_LINES: usize = 3;
+
+/// How many characters of a hook's output are shown in the status area at most.
+const HOOK_OUTPUT_CHARS: usize = 300;
+
+///
+/// What a post-install hook printed, stdout then stderr, and how it exited.
+///
+pub struct HookOutput {
+    pub success: bool,
+    // None when the hook was killed by a signal
+    pub code: Option<i32>,
+    pub output: String,
+    // it ran for longer than the timeout and was killed
+    pub timed_out: bool,
+}
+
+#[derive(Debug)]
+pub enum HookErr {
+    DbInaccesible(u16),
+    NotStarted(u16),
+    WaitFailed(u16),
+}
+
+///
+/// Whether the hook of the entry was allowed to run on this machine before.
+/// A changed command has to be allowed again.
+///
+pub fn is_approved(vault_db: &Db, vault: &Vault, entry_name: &str, command: &str) -> bool {
+    settings::get_approved_hooks(vault_db).contains(&approval_key(vault, entry_name, command))
+}
+
+///
+/// Allows the hook of the entry to run on this machine from now on.
+///
+pub fn approve(
+    vault_db: &Db,
+    vault: &Vault,
+    entry_name: &str,
+    command: &str,
+) -> Result<(), HookErr> {
+    let mut approved_hooks = settings::get_approved_hooks(vault_db);
+    approved_hooks.insert(approval_key(vault, entry_name, command));
+    match settings::set_approved_hooks(vault_db, &approved_hooks) {
+        Ok(_) => Ok(()),
+        Err(SettingsErr::DbInaccesible(_)) => Err(HookErr::DbInaccesible(0)),
+    }
+}
+
+///
+/// Runs the hook with the shell of this machine, in the folder of the installed
+/// file, which is also given to it in ESTASH_INSTALL_PATH, along with the name
+/// of the entry in ESTASH_ENTRY. Waits for it to exit, killing it after the timeout,
+/// so it has to be called off the FLTK thread.
+///
+pub fn run_hook(
+    command: &str,
+    entry_name: &str,
+    install_path: &str,
+) -> Result<HookOutput, HookErr> {
+    let mut shell = if utils::is_windows() {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .env("ESTASH_ENTRY", entry_name)
+        .env("ESTASH_INSTALL_PATH", install_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(install_dir) = Path::new(install_path).parent() {
+        if install_dir.is_dir() {
+            shell.current_dir(install_dir);
+        }
+    }
+
+    let mut child = match shell.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("ERROR: Failed to start the post-install hook of {entry_name}!\n{err}");
+            return Err(HookErr::NotStarted(0));
+        }
+    };
+
+    // the pipes are read as the hook runs, so it doesn't block on a full one
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let stderr = Arc::new(Mutex::new(Vec::new()));
+    let stdout_reader = read_pipe(child.stdout.take(), stdout.clone());
+    let stderr_reader = read_pipe(child.stderr.take(), stderr.clone());
+
+    let deadline = Instant::now() + Duration::from_secs(HOOK_TIMEOUT_SECS);
+    let mut status = None;
+    let mut timed_out = false;
+    loop {
+        if status.is_none() {
+            status = match child.try_wait() {
+                Ok(status) => status,
+                Err(err) => {
+                    eprintln!(
+                        "ERROR: Failed to wait for the post-install hook of {entry_name}!\n{err}"
+                    );
+                    let _ = child.kill();
+                    return Err(HookErr::WaitFailed(0));
+                }
+            };
+        }
+        // something the hook started in the background may keep the pipes open,
+        // its output is only waited for until the timeout
+        if status.is_some() && stdout_reader.is_finished() && stderr_reader.is_finished() {
+            break;
+        }
+        if Instant::now() >= deadline {
+            if status.is_none() {
+                eprintln!("ERROR: The post-install hook of {entry_name} timed out, killing it!");
+                timed_out = true;
+                let _ = child.kill();
+                status = child.wait().ok();
+            }
+            break;
+        }
+        thread::sleep(HOOK_POLL_INTERVAL);
+    }
+
+    let mut text = match stdout.lock() {
+        Ok(stdout) => String::from_utf8_lossy(&stdout).into_owned(),
+        Err(_) => String::new(),
+    };
+    if let Ok(stderr) = stderr.lock() {
+        text += &String::from_utf8_lossy(&stderr);
+    }
+    Ok(HookOutput {
+        success: !timed_out && status.map_or(false, |status| status.success()),
+        code: status.and_then(|status| status.code()),
+        output: text,
+        timed_out,
+    })
+}
+
+///
+/// The last lines of what the hook printed, short enough for the status area.
+///
+pub fn summarize(output: &str) -> String {
+    let lines: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let summary = lines[lines.len().saturating_sub(HOOK_OUTPUT_LINES)..].join(" | ");
+
+    match summary.char_indices().nth(HOOK_OUTPUT_CHARS) {
+        Some((end, _)) => format!("{}...", &summary[..end]),
+        None => summary,
+    }
+}
+
+///
+/// Reads the pipe into the buffer on a thread of its own until it's closed,
+/// keeping only the end of what was printed.
+///
+fn read_pipe<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    buffer: Arc<Mutex<Vec<u8>>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut pipe = match pipe {
+            Some(pipe) => pipe,
+            None => return,
+        };
+        let mut chunk = [0; 4096];
+        loop {
+            let read = match pipe.read(&mut chunk) {
+                Ok(0) | Err(_) => return,
+                Ok(read) => read,
+            };
+            let mut buffer = match buffer.lock() {
+                Ok(buffer) => buffer,
+                Err(_) => return,
+            };
+            buffer.extend_from_slice(&chunk[..read]);
+            if buffer.len() > HOOK_OUTPUT_KEPT_BYTES {
+                let dropped = buffer.len() - HOOK_OUTPUT_KEPT_BYTES;
+                buffer.drain(..dropped);
+            }
+        }
+    })
+}
+
+///
+/// The approvals are stored unencrypted, as a keyed hash of the machine, entry
+/// and command, so they don't show the commands and don't carry over when the
+/// vault db is copied to another machine.
+///
+fn approval_key(vault: &Vault, entry_name: &str, command: &str) -> String {
+    hex::encode(super::core::entry_key(
+        vault,
+        &format!("{}\0{entry_name}\0{command}", utils::hostname()),
+    ))
+}
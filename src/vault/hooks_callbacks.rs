@@ -0,0 +1,239 @@
+use super::audit::{self, AuditAction};
+use super::core::{UpdateContentErr, VaultValueErr};
+use super::hooks;
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+///
+/// Callback for the Post-Install Hook/Edit Hook menu item.
+/// Sets the command run after the selected entry is installed, empty for none.
+///
+pub fn edit_hook_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the entry to edit the post-install hook of first!");
+        status_label.show();
+        return;
+    }
+
+    let mut value = match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &current_selected_entry,
+    ) {
+        Ok(value) => value,
+        Err(VaultValueErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the entry, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let command = match dialog::input_default(
+        "Command run after this entry is installed, like ssh-add \"$ESTASH_INSTALL_PATH\".\nIt runs in the folder of the installed file, with sh (cmd on Windows), leave it empty to remove it.",
+        &value.post_install,
+    ) {
+        Some(command) => command.trim().to_owned(),
+        None => return,
+    };
+    if command == value.post_install {
+        return;
+    }
+
+    value.post_install = command;
+    match super::core::set_entry_value(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &value,
+    ) {
+        Ok(_) => (),
+        Err(UpdateContentErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to save the post-install hook, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    audit::record(
+        &vault_db_arc,
+        &ecies_arc,
+        &vault_arc,
+        AuditAction::Modified,
+        &current_selected_entry,
+        "post-install hook",
+    );
+
+    if value.post_install.is_empty() {
+        status_label.set_label(&format!(
+            "Removed the post-install hook of {current_selected_entry}!"
+        ));
+    } else {
+        status_label.set_label(&format!(
+            "Saved the post-install hook of {current_selected_entry}, it runs after Install!"
+        ));
+    }
+    status_label.show();
+}
+
+///
+/// Runs the post-install hook of an entry that was just installed, asking first
+/// if it never ran on this machine. The hook runs on a thread of its own, so the
+/// window stays usable, and what it printed is put in the status label after the
+/// install status once it exits. The caller must not hold the status label arc.
+///
+pub fn run_post_install_hook(
+    status_label_arc: &Arc<Mutex<frame::Frame>>,
+    installed_status: &str,
+    vault_db_arc: &Arc<Mutex<Db>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    entry_name: &str,
+    command: &str,
+    install_path: &str,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let approved = match (vault_db_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(vault)) => hooks::is_approved(&vault_db, &vault, entry_name, command),
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc or vault_arc ARC!");
+            status_label.set_label(&format!(
+                "{installed_status} The post-install hook wasn't run, there was a Poison Error!"
+            ));
+            status_label.show();
+            return;
+        }
+    };
+
+    // a hook never runs on a machine without being allowed there, it may come from a sync
+    if !approved {
+        match dialog::choice2_default(
+            &format!("{entry_name} runs this command after it's installed:\n\n{command}\n\nAllow it to run on this machine? You're asked again if the command changes."),
+            "Don't Run",
+            "Run",
+            "",
+        ) {
+            Some(1) => (),
+            _ => {
+                status_label.set_label(&format!(
+                    "{installed_status} The post-install hook wasn't run."
+                ));
+                status_label.show();
+                return;
+            }
+        };
+        let stored = match (vault_db_arc.lock(), vault_arc.lock()) {
+            (Ok(vault_db), Ok(vault)) => hooks::approve(&vault_db, &vault, entry_name, command),
+            _ => Err(hooks::HookErr::DbInaccesible(0)),
+        };
+        if stored.is_err() {
+            eprintln!("ERROR: Failed to remember the post-install hook as allowed, it will be asked again!");
+        }
+    }
+
+    status_label.set_label(&format!(
+        "{installed_status} Running the post-install hook..."
+    ));
+    status_label.show();
+    drop(status_label);
+
+    let status_label_arc = status_label_arc.clone();
+    let installed_status = installed_status.to_owned();
+    let entry_name = entry_name.to_owned();
+    let command = command.to_owned();
+    let install_path = install_path.to_owned();
+    thread::spawn(move || {
+        let ran = hooks::run_hook(&command, &entry_name, &install_path);
+        let status = hook_status(&installed_status, ran);
+
+        match status_label_arc.lock() {
+            Ok(mut status_label) => {
+                status_label.set_label(&status);
+                status_label.show();
+            }
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            }
+        };
+        app::awake();
+    });
+}
+
+///
+/// What's shown in the status label once the hook exited.
+///
+fn hook_status(installed_status: &str, ran: Result<hooks::HookOutput, hooks::HookErr>) -> String {
+    match ran {
+        Ok(output) if output.success => {
+            let summary = hooks::summarize(&output.output);
+            if summary.is_empty() {
+                format!("{installed_status} The post-install hook ran.")
+            } else {
+                format!("{installed_status} Hook: {summary}")
+            }
+        }
+        Ok(output) if output.timed_out => format!(
+            "{installed_status} The post-install hook was stopped after {} seconds: {}",
+            hooks::HOOK_TIMEOUT_SECS,
+            hooks::summarize(&output.output)
+        ),
+        Ok(output) => {
+            let exit = match output.code {
+                Some(code) => format!("exit code {code}"),
+                None => "killed".to_string(),
+            };
+            format!(
+                "{installed_status} The post-install hook failed ({exit}): {}",
+                hooks::summarize(&output.output)
+            )
+        }
+        Err(hooks::HookErr::WaitFailed(_)) => {
+            format!("{installed_status} Lost track of the post-install hook, it was stopped!")
+        }
+        Err(_) => format!("{installed_status} The post-install hook couldn't be started!"),
+    }
+}
@@ -0,0 +1,647 @@
+use super::audit::{self, AuditAction};
+use super::content_stream::{self, ContentStream};
+use super::core::{NewEntryErr, VaultValue};
+use super::install_targets::InstallTarget;
+use super::installs::InstallMode;
+use crate::utils::Vault;
+use crate::ECIES;
+use serde::Deserialize;
+use sled::Db;
+use std::fs;
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+/// The most times a number is added to a taken name before giving up on the entry.
+const MAX_NAME_SUFFIX: usize = 1000;
+
+/// The content of an entry, in memory, or stored as chunks if it's over the stream threshold.
+type EntryContent = (Vec<u8>, Option<ContentStream>);
+
+/// The file extension of the encrypted files in a pass store.
+const PASS_FILE_EXTENSION: &str = "gpg";
+
+/// Directories that are never walked into when importing dotfiles.
+const SKIPPED_DOTFILE_DIRS: [&str; 3] = [".git", ".hg", ".svn"];
+
+///
+/// An entry read from another password manager, before it's added to the vault.
+/// The secrets in it are wiped once it's dropped.
+///
+#[derive(Default)]
+pub struct ImportedEntry {
+    pub name: String,
+    pub install_path: String,
+    pub content: Vec<u8>,
+    // the file the content is read from when the entry is added, instead of content,
+    // so a big one is streamed into the vault without being kept in memory
+    pub content_file: Option<PathBuf>,
+    pub notes: String,
+    pub install_targets: Vec<InstallTarget>,
+    pub is_template: bool,
+    pub install_mode: InstallMode,
+    pub post_install: String,
+}
+
+impl Drop for ImportedEntry {
+    fn drop(&mut self) {
+        self.content.zeroize();
+        self.notes.zeroize();
+        self.post_install.zeroize();
+    }
+}
+
+#[derive(Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    // entries whose name was taken, they got a number added to it
+    pub renamed: usize,
+    // entries whose content file couldn't be read, they weren't added
+    pub unreadable: usize,
+}
+
+#[derive(Debug)]
+pub enum ImportErr {
+    FileInaccesible(u16),
+    WrongCredentials(u16),
+    Unsupported(u16),
+    Corrupted(u16),
+    ToolFailed(u16),
+    DbInaccesible(u16),
+    PoisonErr(u16),
+}
+
+///
+/// Bitwarden's unencrypted JSON export, only the parts that are imported.
+///
+#[derive(Deserialize)]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<BitwardenFolder>,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenFolder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenItem {
+    #[serde(default)]
+    folder_id: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    login: Option<BitwardenLogin>,
+    #[serde(default)]
+    card: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    identity: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    fields: Vec<BitwardenField>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Vec<BitwardenUri>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenUri {
+    #[serde(default)]
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenField {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+}
+
+///
+/// Adds the entries to the vault, one at a time. A name that's already taken,
+/// in the vault or earlier in the import, gets a number added to it.
+/// The source is recorded in the audit log with every entry.
+///
+pub fn add_entries(
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    entries: Vec<ImportedEntry>,
+    source: &str,
+) -> Result<ImportSummary, ImportErr> {
+    let mut summary = ImportSummary::default();
+
+    for mut entry in entries {
+        // the content is read first, so an unreadable file doesn't leave an empty entry
+        let (content, content_stream) = match entry.content_file.take() {
+            Some(file) => match read_content_file(&vault_db_arc, &file)? {
+                Some(read) => read,
+                None => {
+                    summary.unreadable += 1;
+                    continue;
+                }
+            },
+            None => stream_big_content(&vault_db_arc, std::mem::take(&mut entry.content))?,
+        };
+
+        if entry.name.trim().is_empty() {
+            entry.name = "Untitled".to_string();
+        }
+        let name = add_with_free_name(
+            vault_db_arc.clone(),
+            vault_arc.clone(),
+            ecies_arc.clone(),
+            &entry.name,
+        )?;
+        if name != entry.name {
+            summary.renamed += 1;
+        }
+
+        // the entry keeps the install path and notes, the content goes in
+        // separately so big ones are kept as chunks, like a big selected file
+        let mut value = VaultValue::new_empty();
+        value.name = name.to_owned();
+        value.install_path = std::mem::take(&mut entry.install_path);
+        value.install_targets = std::mem::take(&mut entry.install_targets);
+        value.is_template = entry.is_template;
+        value.install_mode = entry.install_mode;
+        value.post_install = std::mem::take(&mut entry.post_install);
+        value.notes = std::mem::take(&mut entry.notes);
+        if super::core::set_entry_value(
+            vault_db_arc.clone(),
+            ecies_arc.clone(),
+            vault_arc.clone(),
+            &value,
+        )
+        .is_err()
+        {
+            eprintln!("ERROR: Failed to store the imported entry {name}!");
+            return Err(ImportErr::DbInaccesible(0));
+        }
+
+        if super::core::update_content_in_entry(
+            Arc::new(Mutex::new(name.to_owned())),
+            vault_db_arc.clone(),
+            ecies_arc.clone(),
+            vault_arc.clone(),
+            content,
+            content_stream,
+        )
+        .is_err()
+        {
+            eprintln!("ERROR: Failed to store the content of the imported entry {name}!");
+            return Err(ImportErr::DbInaccesible(0));
+        }
+        audit::record(
+            &vault_db_arc,
+            &ecies_arc,
+            &vault_arc,
+            AuditAction::Imported,
+            &name,
+            source,
+        );
+        summary.imported += 1;
+    }
+
+    Ok(summary)
+}
+
+///
+/// Stores a content over the stream threshold as chunks, wiping it from memory.
+///
+fn stream_big_content(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    mut content: Vec<u8>,
+) -> Result<EntryContent, ImportErr> {
+    if content.len() as u64 <= content_stream::STREAM_THRESHOLD {
+        return Ok((content, None));
+    }
+
+    let vault_db = match vault_db_arc.lock() {
+        Ok(vault_db) => vault_db,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            return Err(ImportErr::PoisonErr(0));
+        }
+    };
+    let content_stream = match content_stream::import_bytes(&vault_db, &content) {
+        Ok(content_stream) => content_stream,
+        Err(_) => return Err(ImportErr::DbInaccesible(0)),
+    };
+    // only the chunks keep the content
+    content.zeroize();
+
+    Ok((Vec::new(), Some(content_stream)))
+}
+
+///
+/// Reads the content of an entry from its file, streaming it straight into chunks
+/// if it's over the stream threshold. None if the file can't be read.
+///
+fn read_content_file(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    file: &Path,
+) -> Result<Option<EntryContent>, ImportErr> {
+    let size = match fs::metadata(file) {
+        Ok(metadata) => metadata.len(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the file {}!\n{err}", file.display());
+            return Ok(None);
+        }
+    };
+
+    if size <= content_stream::STREAM_THRESHOLD {
+        return match fs::read(file) {
+            // it may have grown since it was checked
+            Ok(content) => stream_big_content(vault_db_arc, content).map(Some),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read the file {}!\n{err}", file.display());
+                Ok(None)
+            }
+        };
+    }
+
+    let vault_db = match vault_db_arc.lock() {
+        Ok(vault_db) => vault_db,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            return Err(ImportErr::PoisonErr(0));
+        }
+    };
+    match content_stream::import_file(&vault_db, file) {
+        Ok(content_stream) => Ok(Some((Vec::new(), Some(content_stream)))),
+        Err(_) => {
+            eprintln!("ERROR: Failed to stream the file {}!", file.display());
+            Ok(None)
+        }
+    }
+}
+
+///
+/// Reads a Bitwarden unencrypted JSON export. Logins keep their password as the
+/// content, everything else about an item (username, urls, card and identity
+/// details, custom fields) goes in the notes. Items are named after their folder.
+///
+pub fn read_bitwarden_json(text: &str) -> Result<Vec<ImportedEntry>, ImportErr> {
+    let export: BitwardenExport = match serde_json::from_str(text) {
+        Ok(export) => export,
+        Err(err) => {
+            eprintln!("ERROR: The file is not a Bitwarden JSON export!\n{err}");
+            return Err(ImportErr::Corrupted(0));
+        }
+    };
+    if export.encrypted {
+        eprintln!("ERROR: Encrypted Bitwarden exports can't be imported, export it unencrypted!");
+        return Err(ImportErr::Unsupported(0));
+    }
+
+    let mut entries = Vec::new();
+    for item in export.items {
+        let folder = item
+            .folder_id
+            .as_ref()
+            .and_then(|folder_id| export.folders.iter().find(|folder| &folder.id == folder_id));
+        let mut entry = ImportedEntry::default();
+        entry.name = match folder {
+            Some(folder) => format!("{}/{}", folder.name, item.name),
+            None => item.name.to_owned(),
+        };
+
+        let mut details = Vec::new();
+        if let Some(login) = &item.login {
+            if let Some(password) = &login.password {
+                entry.content = password.as_bytes().to_vec();
+            }
+            if let Some(username) = &login.username {
+                details.push(format!("Username: {username}"));
+            }
+            for uri in login.uris.iter().filter_map(|uri| uri.uri.as_ref()) {
+                details.push(format!("URL: {uri}"));
+            }
+            if let Some(totp) = &login.totp {
+                details.push(format!("TOTP: {totp}"));
+            }
+        }
+        for section in [&item.card, &item.identity].into_iter().flatten() {
+            for (key, value) in section {
+                match value {
+                    serde_json::Value::Null => (),
+                    serde_json::Value::String(value) => details.push(format!("{key}: {value}")),
+                    value => details.push(format!("{key}: {value}")),
+                };
+            }
+        }
+        for field in &item.fields {
+            details.push(format!(
+                "{}: {}",
+                field.name.as_deref().unwrap_or(""),
+                field.value.as_deref().unwrap_or("")
+            ));
+        }
+
+        entry.notes = join_notes(item.notes.as_deref().unwrap_or(""), &details);
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+///
+/// Reads a pass (password-store) directory, decrypting every file with gpg.
+/// The given identity (a key id, fingerprint or email) is the secret key gpg tries,
+/// empty lets gpg pick it. The first line of a file is the password and becomes
+/// the content, the rest goes in the notes, entries are named after the path.
+///
+pub fn read_pass_store(dir: &str, identity: &str) -> Result<Vec<ImportedEntry>, ImportErr> {
+    let mut files = Vec::new();
+    list_pass_files(Path::new(dir), &mut files)?;
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files {
+        let relative_path = match file.strip_prefix(dir) {
+            Ok(path) => path.with_extension(""),
+            Err(_) => continue,
+        };
+        let name = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join("/");
+
+        let mut plain = decrypt_with_gpg(&file, identity)?;
+        let text = String::from_utf8_lossy(&plain).to_string();
+        plain.zeroize();
+
+        let mut entry = ImportedEntry::default();
+        entry.name = name;
+        match text.split_once('\n') {
+            Some((password, rest)) => {
+                entry.content = password.trim_end_matches('\r').as_bytes().to_vec();
+                entry.notes = rest.trim_end().to_string();
+            }
+            None => entry.content = text.as_bytes().to_vec(),
+        };
+        let mut text = text;
+        text.zeroize();
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+///
+/// Lists every file under the given paths, walking through directories, to onboard
+/// the dotfiles of a machine. Entries are named after their path relative to
+/// relative_to if given, or else to the home folder, and install back where the
+/// file was, written as ~/... if home_relative is set and the file is in the home folder.
+/// The files are only read when the entries are added.
+/// Returns the entries and the number of paths that couldn't be read.
+///
+pub fn read_dotfiles(
+    paths: &[PathBuf],
+    relative_to: Option<&Path>,
+    home_relative: bool,
+) -> (Vec<ImportedEntry>, usize) {
+    let home_dir = dirs::home_dir();
+    let current_dir = std::env::current_dir().unwrap_or_default();
+
+    let mut files = Vec::new();
+    let mut unreadable = 0;
+    for path in paths {
+        let path = current_dir.join(path);
+        if path.is_dir() {
+            list_dotfiles(&path, &mut files, &mut unreadable);
+        } else if path.is_file() {
+            files.push(path);
+        } else {
+            eprintln!("ERROR: {} is not a file or directory!", path.display());
+            unreadable += 1;
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    let mut entries = Vec::new();
+    for file in files {
+        let in_home = home_dir
+            .as_ref()
+            .and_then(|home_dir| file.strip_prefix(home_dir).ok());
+        let relative = relative_to
+            .and_then(|relative_to| file.strip_prefix(current_dir.join(relative_to)).ok())
+            .or(in_home)
+            .unwrap_or(&file);
+        let name = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<String>>()
+            .join("/");
+        let install_path = match in_home {
+            Some(in_home) if home_relative => format!("~{MAIN_SEPARATOR}{}", in_home.display()),
+            _ => file.display().to_string(),
+        };
+
+        entries.push(ImportedEntry {
+            name,
+            install_path,
+            content: Vec::new(),
+            content_file: Some(file),
+            notes: String::new(),
+            install_targets: Vec::new(),
+            is_template: false,
+            install_mode: InstallMode::Copy,
+            post_install: String::new(),
+        });
+    }
+
+    (entries, unreadable)
+}
+
+///
+/// The notes followed by the details, one per line.
+///
+pub fn join_notes(notes: &str, details: &[String]) -> String {
+    let mut joined = notes.trim_end().to_string();
+    if !details.is_empty() {
+        if !joined.is_empty() {
+            joined += "\n\n";
+        }
+        joined += &details.join("\n");
+    }
+
+    joined
+}
+
+///
+/// Adds an empty entry under the name, or the name with the first number that's free.
+///
+fn add_with_free_name(
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    name: &str,
+) -> Result<String, ImportErr> {
+    for suffix in 1..MAX_NAME_SUFFIX {
+        let candidate = if suffix == 1 {
+            name.to_owned()
+        } else {
+            format!("{name} ({suffix})")
+        };
+
+        match super::core::add_new_entry(
+            vault_db_arc.clone(),
+            vault_arc.clone(),
+            &candidate,
+            ecies_arc.clone(),
+        ) {
+            Ok(_) => return Ok(candidate),
+            Err(NewEntryErr::AlreadyExists(_)) => continue,
+            Err(NewEntryErr::PoisonErr(_)) => return Err(ImportErr::PoisonErr(0)),
+            Err(_) => return Err(ImportErr::DbInaccesible(0)),
+        };
+    }
+
+    eprintln!("ERROR: There is no free name left for the imported entry {name}!");
+    Err(ImportErr::DbInaccesible(0))
+}
+
+fn list_pass_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ImportErr> {
+    let dir_entries = match fs::read_dir(dir) {
+        Ok(dir_entries) => dir_entries,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the pass store directory!\n{err}");
+            return Err(ImportErr::FileInaccesible(0));
+        }
+    };
+
+    for dir_entry in dir_entries {
+        let path = match dir_entry {
+            Ok(dir_entry) => dir_entry.path(),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read the pass store directory!\n{err}");
+                return Err(ImportErr::FileInaccesible(0));
+            }
+        };
+        // .git, .gpg-id and the like aren't entries
+        let hidden = match path.file_name() {
+            Some(file_name) => file_name.to_string_lossy().starts_with('.'),
+            None => true,
+        };
+        if hidden {
+            continue;
+        }
+
+        if path.is_dir() {
+            list_pass_files(&path, files)?;
+        } else if matches!(path.extension(), Some(extension) if extension == PASS_FILE_EXTENSION) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Lists the files in the directory and the ones under it. Linked files (like the
+/// ones stow makes) are listed, linked directories aren't walked so there are no loops.
+///
+fn list_dotfiles(dir: &Path, files: &mut Vec<PathBuf>, unreadable: &mut usize) {
+    let dir_entries = match fs::read_dir(dir) {
+        Ok(dir_entries) => dir_entries,
+        Err(err) => {
+            eprintln!(
+                "ERROR: Failed to read the directory {}!\n{err}",
+                dir.display()
+            );
+            *unreadable += 1;
+            return;
+        }
+    };
+
+    for dir_entry in dir_entries {
+        let dir_entry = match dir_entry {
+            Ok(dir_entry) => dir_entry,
+            Err(err) => {
+                eprintln!(
+                    "ERROR: Failed to read the directory {}!\n{err}",
+                    dir.display()
+                );
+                *unreadable += 1;
+                continue;
+            }
+        };
+        let path = dir_entry.path();
+        let file_type = match dir_entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => {
+                *unreadable += 1;
+                continue;
+            }
+        };
+
+        if file_type.is_dir() {
+            let skipped = SKIPPED_DOTFILE_DIRS
+                .iter()
+                .any(|skipped| dir_entry.file_name() == **skipped);
+            if !skipped {
+                list_dotfiles(&path, files, unreadable);
+            }
+        } else if file_type.is_file() || (file_type.is_symlink() && path.is_file()) {
+            files.push(path);
+        }
+    }
+}
+
+fn decrypt_with_gpg(file: &Path, identity: &str) -> Result<Vec<u8>, ImportErr> {
+    let mut command = Command::new("gpg");
+    command.args(["--batch", "--quiet", "--decrypt"]);
+    if !identity.trim().is_empty() {
+        command.args(["--try-secret-key", identity.trim()]);
+    }
+    command
+        .arg(file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("ERROR: Failed to run gpg, is it installed?\n{err}");
+            return Err(ImportErr::ToolFailed(0));
+        }
+    };
+    if !output.status.success() {
+        eprintln!(
+            "ERROR: gpg failed to decrypt {}!\n{}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(ImportErr::WrongCredentials(0));
+    }
+
+    Ok(output.stdout)
+}
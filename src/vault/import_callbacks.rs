@@ -0,0 +1,513 @@
+use super::export::{self, ExportFormat};
+use super::import::{self, ImportErr, ImportedEntry};
+use super::kdbx;
+use super::path_vars;
+use super::settings;
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zeroize::{Zeroize, Zeroizing};
+
+///
+/// Callback for the Import/KeePass (KDBX) menu item.
+/// Asks for the database, its password and keyfile, and adds its entries to the vault.
+///
+pub fn import_keepass_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let path = match choose_file("Select the KeePass database", "*.kdbx") {
+        Some(path) => path,
+        None => return,
+    };
+    let password = match dialog::password_default("Password of the KeePass database:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    let keyfile = match dialog::choice2_default(
+        "Is the KeePass database also locked with a keyfile?",
+        "No",
+        "Yes",
+        "",
+    ) {
+        Some(1) => match choose_file("Select the keyfile", "*") {
+            Some(keyfile_path) => match fs::read(keyfile_path) {
+                Ok(keyfile) => Some(Zeroizing::new(keyfile)),
+                Err(err) => {
+                    eprintln!("ERROR: Failed to read the keyfile!\n{err}");
+                    status_label.set_label("Failed to read the keyfile!");
+                    status_label.show();
+                    return;
+                }
+            },
+            None => return,
+        },
+        Some(0) => None,
+        _ => return,
+    };
+
+    let file = match fs::read(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the KeePass database!\n{err}");
+            status_label.set_label("Failed to read the KeePass database!");
+            status_label.show();
+            return;
+        }
+    };
+    let read = kdbx::read_kdbx(
+        &file,
+        &password,
+        keyfile.as_ref().map(|keyfile| keyfile.as_slice()),
+    );
+    let entries = match read {
+        Ok(entries) => entries,
+        Err(ImportErr::WrongCredentials(_)) => {
+            status_label.set_label("Wrong password or keyfile for the KeePass database!");
+            status_label.show();
+            return;
+        }
+        Err(ImportErr::Unsupported(_)) => {
+            status_label.set_label(
+                "Only KDBX 4 databases can be imported, save it again with a recent KeePass!",
+            );
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("The KeePass database is corrupted!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let status = add_imported(
+        entries_arc,
+        vault_db_arc,
+        vault_arc,
+        ecies_arc,
+        entries,
+        "KeePass",
+    );
+    status_label.set_label(&status);
+    status_label.show();
+}
+
+///
+/// Callback for the Import/Bitwarden JSON menu item.
+/// Adds the items of an unencrypted Bitwarden JSON export to the vault.
+///
+pub fn import_bitwarden_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let path = match choose_file("Select the Bitwarden export", "*.json") {
+        Some(path) => path,
+        None => return,
+    };
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the Bitwarden export!\n{err}");
+            status_label.set_label("Failed to read the Bitwarden export!");
+            status_label.show();
+            return;
+        }
+    };
+    let read = import::read_bitwarden_json(&text);
+    text.zeroize();
+    let entries = match read {
+        Ok(entries) => entries,
+        Err(ImportErr::Unsupported(_)) => {
+            status_label.set_label(
+                "Encrypted Bitwarden exports can't be imported, export it as unencrypted JSON!",
+            );
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("The file is not a Bitwarden JSON export!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let status = add_imported(
+        entries_arc,
+        vault_db_arc,
+        vault_arc,
+        ecies_arc,
+        entries,
+        "Bitwarden",
+    );
+    status_label.set_label(&status);
+    status_label.show();
+}
+
+///
+/// Callback for the Import/pass Store menu item.
+/// Decrypts every file of a pass store with gpg and adds them to the vault.
+///
+pub fn import_pass_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let last_dir =
+        dirs::home_dir().map(|home| home.join(".password-store").to_string_lossy().to_string());
+    let dir = match super::dir_store_callbacks::choose_directory("Select the pass store", last_dir)
+    {
+        Some(dir) => dir,
+        None => return,
+    };
+    let identity = match dialog::input_default(
+        "GPG key to decrypt with (id, fingerprint or email), empty for the default:",
+        "",
+    ) {
+        Some(identity) => identity,
+        None => return,
+    };
+
+    status_label.set_label("Decrypting the pass store, gpg may ask for the passphrase...");
+    status_label.show();
+    app::flush();
+
+    let entries = match import::read_pass_store(&dir, &identity) {
+        Ok(entries) => entries,
+        Err(ImportErr::ToolFailed(_)) => {
+            status_label.set_label("Failed to run gpg, make sure it's installed!");
+            status_label.show();
+            return;
+        }
+        Err(ImportErr::WrongCredentials(_)) => {
+            status_label.set_label("gpg failed to decrypt the pass store, check the key!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the pass store directory!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let status = add_imported(
+        entries_arc,
+        vault_db_arc,
+        vault_arc,
+        ecies_arc,
+        entries,
+        "pass",
+    );
+    status_label.set_label(&status);
+    status_label.show();
+}
+
+///
+/// Callback for the Import/EStash Plaintext Export menu item.
+/// Adds the entries of a JSON or CSV export made by Export, told apart by the extension.
+///
+pub fn import_plain_export_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let path = match choose_file("Select the estash export", "*.{json,csv}") {
+        Some(path) => path,
+        None => return,
+    };
+    let format = if path.to_lowercase().ends_with(".csv") {
+        ExportFormat::Csv
+    } else {
+        ExportFormat::Json
+    };
+    let mut document = match fs::read_to_string(&path) {
+        Ok(document) => document,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the export!\n{err}");
+            status_label.set_label("Failed to read the export!");
+            status_label.show();
+            return;
+        }
+    };
+    let read = export::read_plain_export(&document, format);
+    document.zeroize();
+    let entries = match read {
+        Ok(entries) => entries,
+        Err(ImportErr::Unsupported(_)) => {
+            status_label
+                .set_label("The export was made by a newer version of estash, update it first!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("The file is not an estash export, or it was changed!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let status = add_imported(
+        entries_arc,
+        vault_db_arc,
+        vault_arc,
+        ecies_arc,
+        entries,
+        "an estash export",
+    );
+    status_label.set_label(&status);
+    status_label.show();
+}
+
+///
+/// Callback for the Import/Dotfiles from Directory menu item.
+/// Adds every file under a directory as an entry named after its path in the
+/// directory, with the install path set to where the file is.
+///
+pub fn import_dotfiles_dir_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let last_dir = dirs::home_dir().map(|home| home.to_string_lossy().to_string());
+    let dir = match super::dir_store_callbacks::choose_directory(
+        "Select the directory with the dotfiles",
+        last_dir,
+    ) {
+        Some(dir) => dir,
+        None => return,
+    };
+    let home_relative = match ask_home_relative() {
+        Some(home_relative) => home_relative,
+        None => return,
+    };
+
+    let (entries, unreadable) =
+        import::read_dotfiles(&[PathBuf::from(&dir)], Some(Path::new(&dir)), home_relative);
+    let mut status = add_imported(
+        entries_arc,
+        vault_db_arc,
+        vault_arc,
+        ecies_arc,
+        entries,
+        "dotfiles",
+    );
+    if unreadable > 0 {
+        status += &format!(" {unreadable} files couldn't be read!");
+    }
+    status_label.set_label(&status);
+    status_label.show();
+}
+
+///
+/// Callback for the Import/Dotfiles from Paths menu item.
+/// Adds the listed files, and every file under the listed directories, as entries
+/// named after their path in the home folder, with the install path set to where they are.
+///
+pub fn import_dotfiles_paths_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let paths = match dialog::input_default(
+        "Files and directories to import, separated by commas (~ and path variables are expanded):",
+        "~/.bashrc, ~/.gitconfig, $XDG_CONFIG_HOME/nvim",
+    ) {
+        Some(paths) => paths,
+        None => return,
+    };
+    let variables = match vault_db_arc.lock() {
+        Ok(vault_db) => settings::get_path_variables(&vault_db),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let paths: Vec<PathBuf> = paths
+        .split(',')
+        .map(|path| path.trim())
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(path_vars::expand(path, &variables).path))
+        .collect();
+    if paths.is_empty() {
+        status_label.set_label("There were no paths given!");
+        status_label.show();
+        return;
+    }
+    let home_relative = match ask_home_relative() {
+        Some(home_relative) => home_relative,
+        None => return,
+    };
+
+    let (entries, unreadable) = import::read_dotfiles(&paths, None, home_relative);
+    let mut status = add_imported(
+        entries_arc,
+        vault_db_arc,
+        vault_arc,
+        ecies_arc,
+        entries,
+        "dotfiles",
+    );
+    if unreadable > 0 {
+        status += &format!(" {unreadable} paths couldn't be read!");
+    }
+    status_label.set_label(&status);
+    status_label.show();
+}
+
+///
+/// Adds the imported entries to the vault and lists the entries again,
+/// returns the status to show.
+///
+fn add_imported(
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    entries: Vec<ImportedEntry>,
+    source: &str,
+) -> String {
+    if entries.is_empty() {
+        return "There was nothing to import!".to_string();
+    }
+
+    let added = import::add_entries(
+        vault_db_arc.clone(),
+        vault_arc.clone(),
+        ecies_arc.clone(),
+        entries,
+        source,
+    );
+
+    // the entries added before a failure are kept, so list them either way
+    match (entries_arc.lock(), vault_arc.lock()) {
+        (Ok(mut entries), Ok(vault)) => {
+            super::core::reload_entries(&vault, vault_db_arc, ecies_arc, &mut entries);
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under entries_arc or vault_arc ARC!");
+        }
+    };
+
+    match added {
+        Ok(summary) => {
+            let mut status = format!("Imported {} entries from {source}", summary.imported);
+            if summary.renamed > 0 {
+                status += &format!(", {} renamed since the name was taken", summary.renamed);
+            }
+            if summary.unreadable > 0 {
+                status += &format!(", {} files couldn't be read", summary.unreadable);
+            }
+            status + "!"
+        }
+        Err(_) => "The import stopped on an error, some entries may be missing!".to_string(),
+    }
+}
+
+///
+/// Asks whether the install paths of the dotfiles in the home folder
+/// are written relative to it, None if cancelled.
+///
+fn ask_home_relative() -> Option<bool> {
+    match dialog::choice2_default(
+        "Install the files in the home folder to paths relative to it (~/...), so the entries work for other users and machines, or to their absolute paths?",
+        "Cancel",
+        "Absolute",
+        "Relative to ~",
+    ) {
+        Some(1) => Some(false),
+        Some(2) => Some(true),
+        _ => None,
+    }
+}
+
+///
+/// Asks for a file to import.
+///
+fn choose_file(title: &str, filter: &str) -> Option<String> {
+    let mut file_dialog = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+    file_dialog.set_title(title);
+    file_dialog.set_filter(filter);
+    file_dialog.show();
+
+    let path = file_dialog.filename().to_string_lossy().to_string();
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// The operating systems a target can be for, as named by std::env::consts::OS.
+pub const KNOWN_OS_NAMES: [&str; 10] = [
+    "linux",
+    "macos",
+    "windows",
+    "freebsd",
+    "openbsd",
+    "netbsd",
+    "dragonfly",
+    "solaris",
+    "illumos",
+    "android",
+];
+
+/// Written before a hostname in the text form of the targets, the rest are OS names.
+const HOSTNAME_PREFIX: &str = "host:";
+
+///
+/// The machines an install target applies to.
+///
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub enum TargetMachine {
+    Os(String),
+    Hostname(String),
+}
+
+///
+/// An install path used instead of the one of the entry on some machines.
+///
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstallTarget {
+    pub machine: TargetMachine,
+    pub path: String,
+}
+
+impl Drop for InstallTarget {
+    fn drop(&mut self) {
+        match &mut self.machine {
+            TargetMachine::Os(os) => os.zeroize(),
+            TargetMachine::Hostname(hostname) => hostname.zeroize(),
+        };
+        self.path.zeroize();
+    }
+}
+
+#[derive(Debug)]
+pub enum InstallTargetErr {
+    Malformed(u16),
+    UnknownOs(u16),
+    Duplicated(u16),
+}
+
+///
+/// The install path picked for a machine, with what it was picked by.
+///
+pub struct ResolvedTarget {
+    pub path: String,
+    pub machine: Option<TargetMachine>,
+}
+
+impl ResolvedTarget {
+    ///
+    /// What the path was picked by, for the status messages.
+    ///
+    pub fn description(&self) -> String {
+        match &self.machine {
+            Some(TargetMachine::Os(os)) => format!("the {os} target"),
+            Some(TargetMachine::Hostname(hostname)) => format!("the target for {hostname}"),
+            None => "the install path of the entry".to_string(),
+        }
+    }
+}
+
+impl TargetMachine {
+    ///
+    /// Checks if the target applies to the machine with that OS and hostname,
+    /// hostnames are compared ignoring case since Windows writes them uppercase.
+    ///
+    pub fn matches(&self, os: &str, hostname: &str) -> bool {
+        match self {
+            TargetMachine::Os(target_os) => target_os == os,
+            TargetMachine::Hostname(target_hostname) => {
+                !hostname.is_empty() && target_hostname.eq_ignore_ascii_case(hostname)
+            }
+        }
+    }
+}
+
+///
+/// Picks the install path for the machine with that OS and hostname: a target for
+/// its hostname first, then one for its OS, then the install path of the entry.
+/// Returns None when none of them applies.
+///
+pub fn resolve(
+    default_path: &str,
+    targets: &[InstallTarget],
+    os: &str,
+    hostname: &str,
+) -> Option<ResolvedTarget> {
+    let hostname_target = targets.iter().find(|target| {
+        matches!(target.machine, TargetMachine::Hostname(_)) && target.machine.matches(os, hostname)
+    });
+    let os_target = targets.iter().find(|target| {
+        matches!(target.machine, TargetMachine::Os(_)) && target.machine.matches(os, hostname)
+    });
+
+    match hostname_target.or(os_target) {
+        Some(target) => Some(ResolvedTarget {
+            path: target.path.to_owned(),
+            machine: Some(target.machine.clone()),
+        }),
+        None if !default_path.is_empty() => Some(ResolvedTarget {
+            path: default_path.to_owned(),
+            machine: None,
+        }),
+        None => None,
+    }
+}
+
+///
+/// Writes the targets as text, one machine=path per target separated by ;
+/// with hostnames written as host:NAME.
+///
+pub fn format_targets(targets: &[InstallTarget]) -> String {
+    targets
+        .iter()
+        .map(|target| format!("{}={}", format_machine(&target.machine), target.path))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+///
+/// Reads targets written by format_targets, separated by ; or new lines.
+/// The paths are kept as written, only the space before a machine is skipped.
+///
+pub fn parse_targets(text: &str) -> Result<Vec<InstallTarget>, InstallTargetErr> {
+    let mut targets: Vec<InstallTarget> = Vec::new();
+    for written in text.split([';', '\n']) {
+        let written = written.trim_start();
+        let written = written.strip_suffix('\r').unwrap_or(written);
+        if written.trim().is_empty() {
+            continue;
+        }
+
+        let (machine, path) = match written.split_once('=') {
+            Some((machine, path)) => (machine, path),
+            None => {
+                eprintln!("ERROR: The install target {written} is not written as machine=path!");
+                return Err(InstallTargetErr::Malformed(0));
+            }
+        };
+        add_target(&mut targets, parse_machine(machine)?, path)?;
+    }
+
+    Ok(targets)
+}
+
+///
+/// Writes the machine of a target, an OS name or host:NAME.
+///
+pub fn format_machine(machine: &TargetMachine) -> String {
+    match machine {
+        TargetMachine::Os(os) => os.to_owned(),
+        TargetMachine::Hostname(hostname) => format!("{HOSTNAME_PREFIX}{hostname}"),
+    }
+}
+
+///
+/// Reads a machine written by format_machine.
+///
+pub fn parse_machine(written: &str) -> Result<TargetMachine, InstallTargetErr> {
+    let written = written.trim();
+    if written.is_empty() {
+        eprintln!("ERROR: An install target has no machine!");
+        return Err(InstallTargetErr::Malformed(0));
+    }
+
+    match written.strip_prefix(HOSTNAME_PREFIX) {
+        Some(hostname) if !hostname.trim().is_empty() => {
+            Ok(TargetMachine::Hostname(hostname.trim().to_owned()))
+        }
+        Some(_) => {
+            eprintln!("ERROR: The install target for {written} has no hostname!");
+            Err(InstallTargetErr::Malformed(0))
+        }
+        None => {
+            let os = written.to_lowercase();
+            if !KNOWN_OS_NAMES.contains(&os.as_str()) {
+                eprintln!("ERROR: The install target for {written} is for an unknown OS!");
+                return Err(InstallTargetErr::UnknownOs(0));
+            }
+            Ok(TargetMachine::Os(os))
+        }
+    }
+}
+
+///
+/// Adds a target, refusing an empty path or a second target for the same machine.
+///
+pub fn add_target(
+    targets: &mut Vec<InstallTarget>,
+    machine: TargetMachine,
+    path: &str,
+) -> Result<(), InstallTargetErr> {
+    if path.trim().is_empty() {
+        eprintln!("ERROR: An install target has no path!");
+        return Err(InstallTargetErr::Malformed(0));
+    }
+
+    let duplicated = targets
+        .iter()
+        .any(|target| match (&target.machine, &machine) {
+            (TargetMachine::Hostname(a), TargetMachine::Hostname(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a == b,
+        });
+    if duplicated {
+        eprintln!("ERROR: There are two install targets for the same machine!");
+        return Err(InstallTargetErr::Duplicated(0));
+    }
+
+    targets.push(InstallTarget {
+        machine,
+        path: path.to_owned(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use super::audit::{self, AuditAction};
+use super::core::{UpdateContentErr, VaultValue, VaultValueErr};
+use super::install_targets::{self, InstallTargetErr, TargetMachine};
+use super::path_vars;
+use super::report_window;
+use crate::utils::{self, Vault};
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Install Targets/Edit Targets menu item.
+/// Replaces the install paths the selected entry uses on specific OSes or hostnames,
+/// written as machine=path separated by ;
+///
+pub fn edit_install_targets_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the entry to edit the install targets of first!");
+        status_label.show();
+        return;
+    }
+
+    let mut value = match read_value(
+        &vault_db_arc,
+        &vault_arc,
+        &ecies_arc,
+        &current_selected_entry,
+    ) {
+        Ok(value) => value,
+        Err(status) => {
+            status_label.set_label(status);
+            status_label.show();
+            return;
+        }
+    };
+
+    let written = match dialog::input_default(
+        &format!(
+            "Install paths used instead of the entry's one on some machines, as machine=path separated by ;\nThe machine is an OS (linux, macos, windows...) or host:NAME, a hostname wins over an OS.\nThis machine is {} with hostname {}, leave it empty to remove every target.",
+            std::env::consts::OS,
+            utils::hostname()
+        ),
+        &install_targets::format_targets(&value.install_targets),
+    ) {
+        Some(written) => written,
+        None => return,
+    };
+    let targets = match install_targets::parse_targets(&written) {
+        Ok(targets) => targets,
+        Err(InstallTargetErr::UnknownOs(_)) => {
+            status_label.set_label(&format!(
+                "Unknown OS, use one of {} or host:NAME!",
+                install_targets::KNOWN_OS_NAMES.join(", ")
+            ));
+            status_label.show();
+            return;
+        }
+        Err(InstallTargetErr::Duplicated(_)) => {
+            status_label.set_label("There are two targets for the same machine!");
+            status_label.show();
+            return;
+        }
+        Err(InstallTargetErr::Malformed(_)) => {
+            status_label.set_label("Write every target as machine=path, like linux=~/.ssh/config!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // the targets of other machines can't be checked here, their variables may not be set
+    let hostname = utils::hostname();
+    for target in &targets {
+        if !target.machine.matches(std::env::consts::OS, &hostname) {
+            continue;
+        }
+        let expanded_path = match vault_db_arc.lock() {
+            Ok(vault_db) => path_vars::expand_with_settings(&vault_db, &target.path),
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                status_label.set_label("There was a Poison Error, try again, or try to restart!");
+                status_label.show();
+                return;
+            }
+        };
+        if expanded_path.unknown.is_empty() && !utils::is_path_os_valid(&expanded_path.path) {
+            status_label.set_label(&format!(
+                "The path {} is invalid on the current operating system!",
+                target.path
+            ));
+            status_label.show();
+            return;
+        }
+    }
+
+    let count = targets.len();
+    value.install_targets = targets;
+    match super::core::set_entry_value(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &value,
+    ) {
+        Ok(_) => (),
+        Err(UpdateContentErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to save the install targets, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    audit::record(
+        &vault_db_arc,
+        &ecies_arc,
+        &vault_arc,
+        AuditAction::Modified,
+        &current_selected_entry,
+        "install targets",
+    );
+
+    status_label.set_label(&format!(
+        "Saved {count} install targets for {current_selected_entry}!"
+    ));
+    status_label.show();
+}
+
+///
+/// Callback for the Install Targets/Show Targets menu item.
+/// Lists the install targets of the selected entry and which one this machine uses.
+///
+pub fn show_install_targets_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the entry to show the install targets of first!");
+        status_label.show();
+        return;
+    }
+
+    let value = match read_value(
+        &vault_db_arc,
+        &vault_arc,
+        &ecies_arc,
+        &current_selected_entry,
+    ) {
+        Ok(value) => value,
+        Err(status) => {
+            status_label.set_label(status);
+            status_label.show();
+            return;
+        }
+    };
+
+    let os = std::env::consts::OS;
+    let hostname = utils::hostname();
+    let resolved =
+        install_targets::resolve(&value.install_path, &value.install_targets, os, &hostname);
+
+    let mut report = format!("Install targets of {current_selected_entry}\n\n");
+    report += &format!("This machine: {os}, hostname {hostname}\n\n");
+    if value.install_targets.is_empty() {
+        report += "No targets, the install path of the entry is used everywhere.\n";
+    }
+    for target in &value.install_targets {
+        let machine = match &target.machine {
+            TargetMachine::Os(os) => os.to_owned(),
+            TargetMachine::Hostname(hostname) => format!("host {hostname}"),
+        };
+        let used = match &resolved {
+            Some(resolved) if resolved.machine.as_ref() == Some(&target.machine) => {
+                "   <- used here"
+            }
+            _ => "",
+        };
+        report += &format!("  {machine}: {}{used}\n", target.path);
+    }
+    report += &format!(
+        "\nInstall path of the entry, used when no target applies: {}\n",
+        if value.install_path.is_empty() {
+            "(none)"
+        } else {
+            &value.install_path
+        }
+    );
+    match &resolved {
+        Some(resolved) => report += &format!("\nThis machine installs to {}\n", resolved.path),
+        None => report += "\nNo install path applies to this machine.\n",
+    };
+
+    report_window::show("Install Targets", &report);
+}
+
+///
+/// The stored value of the entry, or the status to show when it can't be read.
+///
+fn read_value(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    entry_name: &str,
+) -> Result<VaultValue, &'static str> {
+    match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        entry_name,
+    ) {
+        Ok(value) => Ok(value),
+        Err(VaultValueErr::PoisonErr(_)) => {
+            Err("There was a Poison Error, try again, or try to restart!")
+        }
+        Err(_) => Err("Failed to read the entry, try verifying the vault!"),
+    }
+}
@@ -0,0 +1,555 @@
+use super::audit;
+use super::content_stream::{self, ContentStream};
+use crate::encrypter::ecies::ECIES;
+use crate::hasher::blake3;
+use crate::utils::{self, Vault};
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+/// The name of the tree in the vault db holding the files installed on this machine,
+/// encrypted, under a keyed hash of their entry and path.
+pub const INSTALLS_TREE: &str = "installs";
+
+///
+/// How an entry is put at its install path.
+///
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallMode {
+    // the content is written to the install path
+    #[default]
+    Copy,
+    // the content is written to a file estash manages, the install path links to it
+    Symlink,
+}
+
+impl InstallMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstallMode::Copy => "copy",
+            InstallMode::Symlink => "symlink",
+        }
+    }
+}
+
+///
+/// A file installed on this machine, to tell later if it's still what was installed.
+///
+#[derive(Serialize, Deserialize)]
+pub struct InstalledFile {
+    pub entry: String,
+    pub path: String,
+    pub mode: InstallMode,
+    // the file the link points to, for the symlink mode
+    #[serde(default)]
+    pub managed_path: String,
+    // blake3 hash of the content as it was written
+    #[serde(with = "serde_bytes")]
+    pub hash: Vec<u8>,
+    // unix time, in seconds
+    pub time: u64,
+}
+
+impl Drop for InstalledFile {
+    fn drop(&mut self) {
+        self.hash.zeroize();
+    }
+}
+
+///
+/// What's at the path of an installed file now.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InstallState {
+    // still the link to the managed file, which wasn't changed
+    Linked,
+    // still the copy that was installed
+    Unchanged,
+    // the file (or the managed file the link points to) was changed since
+    Modified,
+    // the link was replaced by a file or a link to something else
+    Replaced,
+    Missing,
+}
+
+impl InstallState {
+    pub fn description(&self) -> &'static str {
+        match self {
+            InstallState::Linked => "our symlink",
+            InstallState::Unchanged => "unchanged copy",
+            InstallState::Modified => "modified copy",
+            InstallState::Replaced => "no longer our symlink",
+            InstallState::Missing => "missing",
+        }
+    }
+}
+
+///
+/// What uninstalling an installed file did.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UninstallOutcome {
+    Removed,
+    // it was already gone, it's just forgotten
+    Missing,
+    // it was changed since it was installed, so it's kept
+    Kept(InstallState),
+}
+
+///
+/// The content written on install, a big one is decrypted chunk by chunk.
+///
+pub enum InstallContent<'a> {
+    Bytes(&'a [u8]),
+    Stream(&'a ContentStream),
+}
+
+#[derive(Debug)]
+pub enum InstallsErr {
+    PoisonErr(u16),
+    DbInaccesible(u16),
+    Corrupted(u16),
+    InstallDirFailed(u16),
+    ManagedDirFailed(u16),
+    UnlinkFailed(u16),
+    WriteFailed(u16),
+    IsADirectory(u16),
+    LinkFailed(u16),
+    // the file was installed, but isn't remembered as installed
+    NotRecorded(u16),
+}
+
+///
+/// The file the content of the entry is written to in symlink mode, in the managed
+/// folder of the vault, named by the keyed hash of the entry so names don't leak.
+///
+fn managed_path(vault: &Vault, entry_name: &str) -> String {
+    let mut path = PathBuf::from(utils::get_managed_root_path(utils::is_windows()));
+    path.push(vault.id.to_string());
+    path.push(hex::encode(super::core::entry_key(vault, entry_name)));
+
+    path.to_string_lossy().into_owned()
+}
+
+///
+/// Writes the content of the entry for its install path, in copy mode to the path
+/// itself, in symlink mode once to the managed file the path then links to, and
+/// remembers the file as installed on this machine.
+/// The caller must not hold any of the arcs.
+///
+pub fn install_content(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    entry_name: &str,
+    install_path: &str,
+    mode: InstallMode,
+    content: InstallContent,
+) -> Result<(), InstallsErr> {
+    // create the folder or make sure there is one
+    if let Some(path_folder) = Path::new(install_path).parent() {
+        if let Err(err) = fs::create_dir_all(path_folder) {
+            eprintln!("ERROR: Failed to create the folder to install to!\n{err}");
+            return Err(InstallsErr::InstallDirFailed(0));
+        }
+    }
+
+    // a link left by the symlink mode is removed first in copy mode,
+    // so the copy doesn't end up written through it into the managed file
+    let written_path = match mode {
+        InstallMode::Symlink => {
+            let managed_path = match vault_arc.lock() {
+                Ok(vault) => managed_path(&vault, entry_name),
+                Err(err) => {
+                    eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+                    return Err(InstallsErr::PoisonErr(0));
+                }
+            };
+            prepare_managed_dir(&managed_path)?;
+            managed_path
+        }
+        InstallMode::Copy => {
+            unlink_managed(install_path)?;
+            install_path.to_owned()
+        }
+    };
+
+    let written_hash = match content {
+        // big contents are decrypted chunk by chunk straight into the file
+        InstallContent::Stream(content_stream) => {
+            let installed = match vault_db_arc.lock() {
+                Ok(vault_db) => {
+                    content_stream::install_to_file(&vault_db, content_stream, &written_path)
+                }
+                Err(err) => {
+                    eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                    return Err(InstallsErr::PoisonErr(0));
+                }
+            };
+            if installed.is_err() {
+                return Err(InstallsErr::WriteFailed(0));
+            }
+            match blake3::hash_file(&written_path) {
+                Ok(hash) => hash,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to read back the installed file!\n{err}");
+                    return Err(InstallsErr::WriteFailed(0));
+                }
+            }
+        }
+        InstallContent::Bytes(bytes) => {
+            if let Err(err) = fs::write(&written_path, bytes) {
+                eprintln!("ERROR: Failed to write the content to the file!\n{err}");
+                return Err(InstallsErr::WriteFailed(0));
+            }
+            blake3::hash_bytes(bytes)
+        }
+    };
+
+    if mode == InstallMode::Symlink {
+        link_to_managed(&written_path, install_path)?;
+    }
+
+    // remember what was installed here, so the status of the file can be checked later
+    let installed_file = new_installed_file(
+        entry_name,
+        install_path,
+        mode,
+        if mode == InstallMode::Symlink {
+            &written_path
+        } else {
+            ""
+        },
+        written_hash,
+    );
+    let recorded = match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => {
+            record_install(&vault_db, &mut ecies, &vault, &installed_file)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC!");
+            Err(InstallsErr::PoisonErr(0))
+        }
+    };
+    match recorded {
+        Ok(_) => Ok(()),
+        Err(_) => Err(InstallsErr::NotRecorded(0)),
+    }
+}
+
+///
+/// Makes sure the folder of the managed file exists, only readable by the user.
+///
+fn prepare_managed_dir(managed_path: &str) -> Result<(), InstallsErr> {
+    let dir = match Path::new(managed_path).parent() {
+        Some(dir) => dir,
+        None => return Err(InstallsErr::ManagedDirFailed(0)),
+    };
+    if let Err(err) = fs::create_dir_all(dir) {
+        eprintln!("ERROR: Failed to create the folder of the managed files!\n{err}");
+        return Err(InstallsErr::ManagedDirFailed(0));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(err) = fs::set_permissions(dir, fs::Permissions::from_mode(0o700)) {
+            eprintln!("ERROR: Failed to restrict the folder of the managed files!\n{err}");
+            return Err(InstallsErr::ManagedDirFailed(0));
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Points the install path to the managed file with a symlink, replacing a file
+/// or link already there. A folder is never replaced.
+///
+fn link_to_managed(managed_path: &str, install_path: &str) -> Result<(), InstallsErr> {
+    match fs::symlink_metadata(install_path) {
+        Ok(metadata) if metadata.is_dir() => {
+            eprintln!("ERROR: There is a folder where the link should be installed!");
+            return Err(InstallsErr::IsADirectory(0));
+        }
+        Ok(_) => {
+            if let Err(err) = fs::remove_file(install_path) {
+                eprintln!("ERROR: Failed to remove the file the link replaces!\n{err}");
+                return Err(InstallsErr::WriteFailed(0));
+            }
+        }
+        Err(_) => (),
+    };
+
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(managed_path, install_path);
+    #[cfg(windows)]
+    let linked = std::os::windows::fs::symlink_file(managed_path, install_path);
+    #[cfg(not(any(unix, windows)))]
+    let linked: io::Result<()> = Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symlinks are not supported here",
+    ));
+
+    match linked {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to create the link to the managed file!\n{err}");
+            Err(InstallsErr::LinkFailed(0))
+        }
+    }
+}
+
+///
+/// Removes a link estash made at the install path, so installing a copy doesn't
+/// write through it into the managed file. Other links are left alone.
+///
+fn unlink_managed(install_path: &str) -> Result<(), InstallsErr> {
+    let target = match fs::read_link(install_path) {
+        Ok(target) => target,
+        Err(_) => return Ok(()),
+    };
+    if !target.starts_with(utils::get_managed_root_path(utils::is_windows())) {
+        return Ok(());
+    }
+
+    match fs::remove_file(install_path) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to remove the link to the managed file!\n{err}");
+            Err(InstallsErr::UnlinkFailed(0))
+        }
+    }
+}
+
+///
+/// Remembers a file installed on this machine, replacing what was remembered for
+/// the same entry and path.
+///
+pub fn record_install(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    installed: &InstalledFile,
+) -> Result<(), InstallsErr> {
+    let mut installed_bytes = match rmp_serde::to_vec_named(installed) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the installed file!\n{err}");
+            return Err(InstallsErr::Corrupted(0));
+        }
+    };
+    let installed_encrypted =
+        ecies.encrypt_bytes_array(&installed_bytes, &vault.priv_key, &vault.pub_key);
+    installed_bytes.zeroize();
+    let installed_encrypted = match installed_encrypted {
+        Ok(cipher) => cipher,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encrypt the installed file!\n{err}");
+            return Err(InstallsErr::Corrupted(0));
+        }
+    };
+
+    let installs_tree = open_installs_tree(vault_db)?;
+    match installs_tree.insert(
+        install_key(vault, &installed.entry, &installed.path),
+        installed_encrypted,
+    ) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the installed file in the vault db!\n{err}");
+            Err(InstallsErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Lists the files installed on this machine, sorted by entry and path.
+///
+pub fn list_installs(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+) -> Result<Vec<InstalledFile>, InstallsErr> {
+    let installs_tree = open_installs_tree(vault_db)?;
+
+    let mut installs = Vec::new();
+    for installed in installs_tree.iter() {
+        let value = match installed {
+            Ok(kv) => kv.1,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read an installed file from the vault db!\n{err}");
+                return Err(InstallsErr::DbInaccesible(0));
+            }
+        };
+        let mut installed_bytes =
+            match ecies.decrypt_bytes_array(&value, &vault.priv_key, &vault.pub_key) {
+                Ok(plain) => plain,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to decrypt an installed file!\n{err}");
+                    return Err(InstallsErr::Corrupted(0));
+                }
+            };
+        let installed = rmp_serde::from_slice::<InstalledFile>(&installed_bytes);
+        installed_bytes.zeroize();
+        match installed {
+            Ok(installed) => installs.push(installed),
+            Err(err) => {
+                eprintln!("ERROR: An installed file is not stored in estash's format!\n{err}");
+                return Err(InstallsErr::Corrupted(0));
+            }
+        };
+    }
+
+    installs.sort_by(|a, b| (&a.entry, &a.path).cmp(&(&b.entry, &b.path)));
+
+    Ok(installs)
+}
+
+///
+/// Forgets a file installed on this machine.
+///
+pub fn remove_install(
+    vault_db: &Db,
+    vault: &Vault,
+    installed: &InstalledFile,
+) -> Result<(), InstallsErr> {
+    let installs_tree = open_installs_tree(vault_db)?;
+    match installs_tree.remove(install_key(vault, &installed.entry, &installed.path)) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to remove the installed file from the vault db!\n{err}");
+            Err(InstallsErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Deletes an installed file if it's still what was installed, with the managed
+/// file behind it in symlink mode. A file changed since is kept.
+/// The caller forgets the file unless it was kept.
+///
+pub fn uninstall(installed: &InstalledFile) -> Result<UninstallOutcome, InstallsErr> {
+    let state = check_install(installed);
+    let outcome = match state {
+        InstallState::Linked | InstallState::Unchanged => {
+            if let Err(err) = fs::remove_file(&installed.path) {
+                eprintln!("ERROR: Failed to remove the installed file!\n{err}");
+                return Err(InstallsErr::WriteFailed(0));
+            }
+            UninstallOutcome::Removed
+        }
+        InstallState::Missing => {
+            // a link to a managed file that's gone is ours to remove
+            let is_our_link = match fs::read_link(&installed.path) {
+                Ok(target) => target == Path::new(&installed.managed_path),
+                Err(_) => false,
+            };
+            if is_our_link {
+                if let Err(err) = fs::remove_file(&installed.path) {
+                    eprintln!(
+                        "ERROR: Failed to remove the link to the missing managed file!\n{err}"
+                    );
+                    return Err(InstallsErr::UnlinkFailed(0));
+                }
+            }
+            UninstallOutcome::Missing
+        }
+        InstallState::Modified | InstallState::Replaced => {
+            return Ok(UninstallOutcome::Kept(state));
+        }
+    };
+
+    // the managed file goes too, unless someone changed it
+    if installed.mode == InstallMode::Symlink {
+        let is_unchanged = match blake3::hash_file(&installed.managed_path) {
+            Ok(hash) => hash[..] == installed.hash[..],
+            Err(_) => false,
+        };
+        if is_unchanged {
+            if let Err(err) = fs::remove_file(&installed.managed_path) {
+                eprintln!("ERROR: Failed to remove the managed file, it's left behind!\n{err}");
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+///
+/// Checks what's at the path of an installed file now, compared to what was installed.
+///
+pub fn check_install(installed: &InstalledFile) -> InstallState {
+    let metadata = match fs::symlink_metadata(&installed.path) {
+        Ok(metadata) => metadata,
+        Err(_) => return InstallState::Missing,
+    };
+
+    let content_path = match installed.mode {
+        InstallMode::Symlink => {
+            let is_our_link = metadata.file_type().is_symlink()
+                && match fs::read_link(&installed.path) {
+                    Ok(target) => target == Path::new(&installed.managed_path),
+                    Err(_) => false,
+                };
+            if !is_our_link {
+                return InstallState::Replaced;
+            }
+            &installed.managed_path
+        }
+        InstallMode::Copy => &installed.path,
+    };
+
+    match blake3::hash_file(content_path) {
+        Ok(hash) if hash[..] == installed.hash[..] => match installed.mode {
+            InstallMode::Symlink => InstallState::Linked,
+            InstallMode::Copy => InstallState::Unchanged,
+        },
+        Ok(_) => InstallState::Modified,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => InstallState::Missing,
+        Err(_) => InstallState::Modified,
+    }
+}
+
+///
+/// An installed file for the entry installed at the path, hashed from what was written.
+///
+fn new_installed_file(
+    entry: &str,
+    path: &str,
+    mode: InstallMode,
+    managed_path: &str,
+    hash: [u8; 32],
+) -> InstalledFile {
+    InstalledFile {
+        entry: entry.to_owned(),
+        path: path.to_owned(),
+        mode,
+        managed_path: managed_path.to_owned(),
+        hash: hash.to_vec(),
+        time: audit::now(),
+    }
+}
+
+///
+/// The key an installed file is stored under, a keyed hash of its entry and path,
+/// so the same file installed again replaces what was remembered.
+///
+fn install_key(vault: &Vault, entry: &str, path: &str) -> Vec<u8> {
+    super::core::entry_key(vault, &format!("{entry}\0{path}"))
+}
+
+fn open_installs_tree(vault_db: &Db) -> Result<sled::Tree, InstallsErr> {
+    match vault_db.open_tree(INSTALLS_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the installs tree of the vault db!\n{err}");
+            Err(InstallsErr::DbInaccesible(0))
+        }
+    }
+}
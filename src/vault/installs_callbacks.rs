@@ -0,0 +1,414 @@
+use super::audit::{self, AuditAction};
+use super::core::{UpdateContentErr, VaultValueErr};
+use super::installs::{self, InstallMode, InstalledFile, InstallsErr, UninstallOutcome};
+use super::report_window;
+use crate::utils::{self, Vault};
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Installed Files/Toggle Symlink Mode menu item.
+/// Switches the selected entry between copying its content to the install path
+/// and linking the install path to a copy estash manages.
+///
+pub fn toggle_symlink_mode_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the entry to change the install mode of first!");
+        status_label.show();
+        return;
+    }
+
+    let mut value = match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &current_selected_entry,
+    ) {
+        Ok(value) => value,
+        Err(VaultValueErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the entry, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+
+    value.install_mode = match value.install_mode {
+        InstallMode::Copy => InstallMode::Symlink,
+        InstallMode::Symlink => InstallMode::Copy,
+    };
+    match super::core::set_entry_value(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &value,
+    ) {
+        Ok(_) => (),
+        Err(UpdateContentErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to save the entry, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    audit::record(
+        &vault_db_arc,
+        &ecies_arc,
+        &vault_arc,
+        AuditAction::Modified,
+        &current_selected_entry,
+        &format!("install mode {}", value.install_mode.as_str()),
+    );
+
+    match value.install_mode {
+        InstallMode::Symlink => status_label.set_label(&format!(
+            "{current_selected_entry} is linked to a managed copy on install now!"
+        )),
+        InstallMode::Copy => status_label.set_label(&format!(
+            "{current_selected_entry} is copied to its install path on install now!"
+        )),
+    };
+    status_label.show();
+}
+
+///
+/// Callback for the Installed Files/Status menu item.
+/// Lists the files installed on this machine and whether each one is still
+/// the symlink estash made, an unchanged or modified copy, or missing.
+///
+pub fn installed_files_status_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let installed_files = match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => {
+            installs::list_installs(&vault_db, &mut ecies, &vault)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let installed_files = match installed_files {
+        Ok(installed_files) => installed_files,
+        Err(_) => {
+            status_label.set_label("Failed to read the installed files, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let mut report = String::from("Files installed from this vault on this machine\n\n");
+    if installed_files.is_empty() {
+        report += "None yet, they're added here when an entry is installed.\n";
+    }
+    for installed_file in &installed_files {
+        let state = installs::check_install(installed_file);
+        report += &format!(
+            "{}\n  {} ({}, installed {})\n  {}\n",
+            installed_file.entry,
+            installed_file.path,
+            installed_file.mode.as_str(),
+            audit::format_time(installed_file.time),
+            state.description()
+        );
+        if installed_file.mode == InstallMode::Symlink {
+            report += &format!("  managed copy: {}\n", installed_file.managed_path);
+        }
+        report += "\n";
+    }
+
+    status_label.set_label("");
+    report_window::show("Installed Files", &report);
+}
+
+///
+/// Callback for the Installed Files/Uninstall Entry menu item.
+/// Removes the files the selected entry installed on this machine,
+/// keeping the ones changed since.
+///
+pub fn uninstall_entry_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the entry to uninstall first!");
+        status_label.show();
+        return;
+    }
+
+    uninstall_files(
+        &mut status_label,
+        &vault_db_arc,
+        &vault_arc,
+        &ecies_arc,
+        Some(&current_selected_entry),
+    );
+}
+
+///
+/// Callback for the Installed Files/Uninstall All menu item.
+/// Removes every file installed from the vault on this machine,
+/// keeping the ones changed since.
+///
+pub fn uninstall_all_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    uninstall_files(
+        &mut status_label,
+        &vault_db_arc,
+        &vault_arc,
+        &ecies_arc,
+        None,
+    );
+}
+
+///
+/// Displays the right message in the status label for an error
+/// returned when installing the content of an entry.
+///
+pub fn show_install_err(status_label: &mut frame::Frame, err: InstallsErr) {
+    match err {
+        InstallsErr::PoisonErr(_) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+        }
+        InstallsErr::InstallDirFailed(_) => {
+            status_label.set_label("There was an error creating/finding the dir where to install!");
+        }
+        InstallsErr::ManagedDirFailed(_) => {
+            status_label.set_label("There was an error creating the folder of the managed files!");
+        }
+        InstallsErr::UnlinkFailed(_) => {
+            status_label
+                .set_label("There was an error removing the link left by the symlink mode!");
+        }
+        InstallsErr::IsADirectory(_) => {
+            status_label.set_label("There is a folder at the install path, it's not replaced!");
+        }
+        InstallsErr::LinkFailed(_) if utils::is_windows() => {
+            status_label.set_label(
+                "Failed to create the link, turn on Developer Mode or run as administrator!",
+            );
+        }
+        InstallsErr::LinkFailed(_) => {
+            status_label.set_label("There was an error linking the file to the managed copy!");
+        }
+        InstallsErr::NotRecorded(_) => {
+            status_label
+                .set_label("The file was installed, but couldn't be added to the installed files!");
+        }
+        InstallsErr::WriteFailed(_) | InstallsErr::DbInaccesible(_) | InstallsErr::Corrupted(_) => {
+            status_label.set_label("There was an error writing the content to the file!");
+        }
+    };
+    status_label.show();
+}
+
+///
+/// Uninstalls the files of the entry installed on this machine, or every one when
+/// there's no entry, once confirmed, and reports what was removed and kept.
+///
+fn uninstall_files(
+    status_label: &mut frame::Frame,
+    vault_db_arc: &Arc<Mutex<Db>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    entry_name: Option<&str>,
+) {
+    let installed_files = match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => {
+            installs::list_installs(&vault_db, &mut ecies, &vault)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let installed_files: Vec<InstalledFile> = match installed_files {
+        Ok(installed_files) => installed_files
+            .into_iter()
+            .filter(|installed_file| entry_name.map_or(true, |name| installed_file.entry == name))
+            .collect(),
+        Err(_) => {
+            status_label.set_label("Failed to read the installed files, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let what = match entry_name {
+        Some(entry_name) => entry_name.to_owned(),
+        None => "this vault".to_string(),
+    };
+    if installed_files.is_empty() {
+        status_label.set_label(&format!(
+            "Nothing from {what} is installed on this machine!"
+        ));
+        status_label.show();
+        return;
+    }
+    match dialog::choice2_default(
+        &format!("Uninstall the {} files installed from {what} on this machine?\nFiles changed since they were installed are kept.", installed_files.len()),
+        "Cancel",
+        "Uninstall",
+        "",
+    ) {
+        Some(1) => (),
+        _ => return,
+    };
+
+    let mut removed = Vec::new();
+    let mut missing = Vec::new();
+    let mut kept = Vec::new();
+    let mut failed = Vec::new();
+    for installed_file in &installed_files {
+        let line = format!("  {}  ->  {}", installed_file.entry, installed_file.path);
+        match installs::uninstall(installed_file) {
+            Ok(UninstallOutcome::Removed) => removed.push(line),
+            Ok(UninstallOutcome::Missing) => missing.push(line),
+            Ok(UninstallOutcome::Kept(state)) => {
+                kept.push(format!("{line}  ({})", state.description()));
+                continue;
+            }
+            Err(_) => {
+                failed.push(line);
+                continue;
+            }
+        };
+
+        let forgotten = match (vault_db_arc.lock(), vault_arc.lock()) {
+            (Ok(vault_db), Ok(vault)) => {
+                installs::remove_install(&vault_db, &vault, installed_file)
+            }
+            _ => {
+                eprintln!("ERROR: Failed to get value under vault_db_arc or vault_arc ARC!");
+                Err(InstallsErr::PoisonErr(0))
+            }
+        };
+        if forgotten.is_err() {
+            eprintln!(
+                "ERROR: Failed to forget the uninstalled file {}!",
+                installed_file.path
+            );
+        }
+        audit::record(
+            vault_db_arc,
+            ecies_arc,
+            vault_arc,
+            AuditAction::Uninstalled,
+            &installed_file.entry,
+            &installed_file.path,
+        );
+    }
+
+    let mut report = format!("Uninstalled the files installed from {what} on this machine\n");
+    for (title, lines) in [
+        ("Removed", &removed),
+        ("Already missing, forgotten", &missing),
+        ("Kept, changed since they were installed", &kept),
+        ("Failed to remove", &failed),
+    ] {
+        if lines.is_empty() {
+            continue;
+        }
+        report += &format!("\n{title}:\n");
+        for line in lines {
+            report += line;
+            report += "\n";
+        }
+    }
+    report_window::show("Uninstall", &report);
+
+    if kept.is_empty() && failed.is_empty() {
+        status_label.set_label(&format!("Uninstalled {} files from {what}!", removed.len()));
+    } else {
+        status_label.set_label(&format!(
+            "Uninstalled {} files from {what}, {} were kept since they changed and {} failed!",
+            removed.len(),
+            kept.len(),
+            failed.len()
+        ));
+    }
+    status_label.show();
+}
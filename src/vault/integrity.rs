@@ -0,0 +1,354 @@
+use super::content_stream;
+use super::core::VaultValue;
+use crate::encrypter::ecies::ECIES;
+use crate::encrypter::key_encrypt::KeyEncrypt;
+use crate::signup::core::SingupError;
+use crate::utils::{self, db, Vault};
+use sled::Db;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+///
+/// The result of walking every key and value in a vault.
+/// The entries that could be fully read are kept so they can
+/// be exported into a fresh vault.
+///
+pub struct IntegrityReport {
+    pub healthy_entries: Vec<(String, VaultValue)>,
+    pub undecryptable_names: usize,
+    pub undecryptable_values: Vec<String>,
+    pub invalid_values: Vec<String>,
+    pub orphaned_vault_dirs: Vec<String>,
+}
+
+impl IntegrityReport {
+    ///
+    /// Whether there is anything wrong with the vault at all.
+    ///
+    pub fn is_healthy(&self) -> bool {
+        self.undecryptable_names == 0
+            && self.undecryptable_values.is_empty()
+            && self.invalid_values.is_empty()
+            && self.orphaned_vault_dirs.is_empty()
+    }
+
+    ///
+    /// Formats the report as text that can be displayed to the user.
+    ///
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str(&format!(
+            "Healthy entries: {}\n",
+            self.healthy_entries.len()
+        ));
+        text.push_str(&format!(
+            "Entries with undecryptable names: {}\n",
+            self.undecryptable_names
+        ));
+
+        text.push_str(&format!(
+            "\nEntries with undecryptable values: {}\n",
+            self.undecryptable_values.len()
+        ));
+        for name in &self.undecryptable_values {
+            text.push_str(&format!("    {name}\n"));
+        }
+
+        text.push_str(&format!(
+            "\nEntries with invalid values: {}\n",
+            self.invalid_values.len()
+        ));
+        for name in &self.invalid_values {
+            text.push_str(&format!("    {name}\n"));
+        }
+
+        text.push_str(&format!(
+            "\nOrphaned vault directories: {}\n",
+            self.orphaned_vault_dirs.len()
+        ));
+        for dir in &self.orphaned_vault_dirs {
+            text.push_str(&format!("    {dir}\n"));
+        }
+
+        text
+    }
+}
+
+#[derive(Debug)]
+pub enum VerifyErr {
+    DbInaccesible(u16),
+    FailedToAccessVaultsDb(u16),
+}
+
+#[derive(Debug)]
+pub enum RecoverErr {
+    FailedToCreateVault(u16),
+    FailedToStoreEntry(u16),
+}
+
+///
+/// Walks every key and value in the vault and reports what can't be read.
+/// Unlike load_entries this never exits, everything that fails is
+/// just counted in the report.
+///
+pub fn verify_vault(
+    vault: &Vault,
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    is_windows: bool,
+) -> Result<IntegrityReport, VerifyErr> {
+    let mut report = IntegrityReport {
+        healthy_entries: Vec::new(),
+        undecryptable_names: 0,
+        undecryptable_values: Vec::new(),
+        invalid_values: Vec::new(),
+        orphaned_vault_dirs: Vec::new(),
+    };
+
+    let entry_names_tree = match vault_db.open_tree(super::core::ENTRY_NAMES_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the entry names tree of the vault db!\n{err}");
+            return Err(VerifyErr::DbInaccesible(0));
+        }
+    };
+
+    for entry in vault_db.iter() {
+        let (key, value) = match entry {
+            Ok(kv) => (kv.0.to_vec(), kv.1.to_vec()),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read an entry from the vault db!\n{err}");
+                return Err(VerifyErr::DbInaccesible(0));
+            }
+        };
+
+        // check the entry name, from the names tree or, for entries that weren't moved
+        // to the new keys yet, from the key itself
+        let name_encrypted = match entry_names_tree.get(&key) {
+            Ok(Some(cipher)) => cipher.to_vec(),
+            _ => key.to_owned(),
+        };
+        let name = match ecies.decrypt_bytes(&name_encrypted, &vault.priv_key, &vault.pub_key) {
+            Ok(plain) => match String::from_utf8(plain) {
+                Ok(name) => name,
+                Err(_) => {
+                    report.undecryptable_names += 1;
+                    continue;
+                }
+            },
+            Err(_) => {
+                report.undecryptable_names += 1;
+                continue;
+            }
+        };
+
+        // check the entry value
+        let value_plain = match ecies.decrypt_bytes(&value, &vault.priv_key, &vault.pub_key) {
+            Ok(plain) => plain,
+            Err(_) => {
+                report.undecryptable_values.push(name);
+                continue;
+            }
+        };
+        let mut entry_value = match VaultValue::from_bytes(&value_plain) {
+            Ok(entry_value) => entry_value,
+            Err(_) => {
+                report.invalid_values.push(name);
+                continue;
+            }
+        };
+
+        // the name inside the value has to be the one the key was made from
+        if entry_value.name.is_empty() {
+            entry_value.name = name.to_owned();
+        } else if key.len() == super::core::ENTRY_KEY_SIZE
+            && super::core::entry_key(vault, &entry_value.name) != key
+        {
+            report.invalid_values.push(name);
+            continue;
+        }
+
+        // big contents live in separate chunks, every one of them has to decrypt
+        if let Some(stream) = &entry_value.content_stream {
+            if !content_stream::verify_stream(vault_db, stream) {
+                report.undecryptable_values.push(name);
+                continue;
+            }
+        }
+
+        report.healthy_entries.push((name, entry_value));
+    }
+
+    report.orphaned_vault_dirs = find_orphaned_vault_dirs(is_windows)?;
+
+    Ok(report)
+}
+
+///
+/// Lists the directories in the vaults root folder that don't belong
+/// to any vault registered in the vaults db.
+///
+pub fn find_orphaned_vault_dirs(is_windows: bool) -> Result<Vec<String>, VerifyErr> {
+    let estashdb = match db::EstashDb::new() {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            return Err(VerifyErr::FailedToAccessVaultsDb(0));
+        }
+    };
+
+    // collect the ids of every registered vault
+    let mut known_ids: Vec<String> = Vec::new();
+    for entry in estashdb.vault_db.iter() {
+        let value = match entry {
+            Ok(kv) => kv.1,
+            Err(err) => {
+                eprintln!("ERROR: There was an error accessing vaults db!\n{err}");
+                return Err(VerifyErr::FailedToAccessVaultsDb(0));
+            }
+        };
+        let parsed: serde_json::Value = match serde_json::from_slice(&value) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
+        match parsed.get("id").and_then(|id| id.as_u64()) {
+            Some(id) => known_ids.push(id.to_string()),
+            None => continue,
+        };
+    }
+
+    // drop the db so other windows can open it
+    drop(estashdb);
+
+    let vaults_root_dir = match fs::read_dir(utils::get_vaults_root_path(is_windows)) {
+        Ok(dir) => dir,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the vaults root folder!\n{err}");
+            return Ok(Vec::new());
+        }
+    };
+
+    let mut orphaned = Vec::new();
+    for dir in vaults_root_dir.flatten() {
+        let dir_name = dir.file_name().to_string_lossy().to_string();
+        if !known_ids.contains(&dir_name) {
+            orphaned.push(dir.path().to_string_lossy().to_string());
+        }
+    }
+
+    Ok(orphaned)
+}
+
+///
+/// Creates a new vault with the given credentials and copies into it
+/// every entry that was fully readable in the report.
+/// A keyfile hash is given when the original vault needs a keyfile, so the new one does too.
+/// Returns the number of entries that were exported.
+///
+pub fn export_recoverable(
+    report: &IntegrityReport,
+    vault_db: &Db,
+    new_vault_name: &str,
+    password: &str,
+    keyfile_hash: Option<&[u8; 32]>,
+    is_windows: bool,
+) -> Result<usize, RecoverErr> {
+    let mut estashdb = match db::EstashDb::new() {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            return Err(RecoverErr::FailedToCreateVault(0));
+        }
+    };
+    let mut ecies = ECIES::new();
+    let mut key_encrypt = match keyfile_hash {
+        Some(hash) => KeyEncrypt::new_with_keyfile(hash),
+        None => KeyEncrypt::new(),
+    };
+
+    let new_vault = match crate::signup::core::create_vault(
+        new_vault_name,
+        password,
+        &mut estashdb,
+        &mut ecies,
+        &mut key_encrypt,
+        is_windows,
+    ) {
+        Ok(vault) => vault,
+        Err(SingupError::AlreadyExists(_)) => {
+            eprintln!(
+                "ERROR: A vault with the name chosen for the recovered vault already exists!"
+            );
+            return Err(RecoverErr::FailedToCreateVault(0));
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to create the vault for the recovered entries!\n{err:?}");
+            return Err(RecoverErr::FailedToCreateVault(0));
+        }
+    };
+
+    // drop the db so other windows can open it
+    drop(estashdb);
+
+    let new_vault_db = super::core::load_vault(is_windows, &new_vault);
+    let new_vault_arc = Arc::new(Mutex::new(new_vault));
+    let ecies_arc = Arc::new(Mutex::new(ecies));
+
+    let mut exported = 0;
+    for (name, value) in &report.healthy_entries {
+        match super::core::add_new_entry(
+            new_vault_db.clone(),
+            new_vault_arc.clone(),
+            name,
+            ecies_arc.clone(),
+        ) {
+            Ok(key) => key,
+            Err(err) => {
+                eprintln!(
+                    "ERROR: Failed to add the recovered entry {name} to the new vault!\n{err:?}"
+                );
+                return Err(RecoverErr::FailedToStoreEntry(0));
+            }
+        };
+
+        match super::core::set_entry_value(
+            new_vault_db.clone(),
+            ecies_arc.clone(),
+            new_vault_arc.clone(),
+            value,
+        ) {
+            Ok(_) => (),
+            Err(_) => {
+                eprintln!("ERROR: Failed to store the value of the recovered entry {name} in the new vault!");
+                return Err(RecoverErr::FailedToStoreEntry(0));
+            }
+        };
+
+        // the chunks are encrypted with a key kept in the value, so they're copied as they are
+        if let Some(stream) = &value.content_stream {
+            let new_vault_db_locked = match new_vault_db.lock() {
+                Ok(db) => db,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to get value under new_vault_db ARC!\n{err}");
+                    return Err(RecoverErr::FailedToStoreEntry(0));
+                }
+            };
+            match content_stream::copy_stream(vault_db, &new_vault_db_locked, stream) {
+                Ok(_) => (),
+                Err(_) => {
+                    eprintln!("ERROR: Failed to copy the content chunks of the recovered entry {name} in the new vault!");
+                    return Err(RecoverErr::FailedToStoreEntry(0));
+                }
+            };
+        }
+
+        exported += 1;
+    }
+
+    Ok(exported)
+}
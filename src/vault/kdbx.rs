@@ -0,0 +1,771 @@
+use super::import::{self, ImportErr, ImportedEntry};
+use super::installs::InstallMode;
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use aes::Aes256;
+use argon2::{ThreadMode, Variant, Version};
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use chacha20::cipher::{NewCipher, StreamCipher};
+use chacha20::{ChaCha20, Key, Nonce};
+use flate2::read::GzDecoder;
+use hmac::{Hmac, Mac};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::io::Read;
+use zeroize::Zeroize;
+
+/// The two signatures every KeePass 2 database starts with.
+const SIGNATURE_1: u32 = 0x9AA2D903;
+const SIGNATURE_2: u32 = 0xB54BFB67;
+
+/// Only KDBX 4 is read, older databases have to be saved again with a recent KeePass.
+const SUPPORTED_MAJOR_VERSION: u16 = 4;
+
+// outer header fields
+const HEADER_END: u8 = 0;
+const HEADER_CIPHER_ID: u8 = 2;
+const HEADER_COMPRESSION: u8 = 3;
+const HEADER_MASTER_SEED: u8 = 4;
+const HEADER_ENCRYPTION_IV: u8 = 7;
+const HEADER_KDF_PARAMETERS: u8 = 11;
+
+// inner header fields
+const INNER_HEADER_END: u8 = 0;
+const INNER_HEADER_STREAM_ID: u8 = 1;
+const INNER_HEADER_STREAM_KEY: u8 = 2;
+const INNER_HEADER_BINARY: u8 = 3;
+
+/// The protected values are only read when hidden with ChaCha20, what KDBX 4 uses.
+const INNER_STREAM_CHACHA20: u32 = 3;
+
+const CIPHER_AES256: [u8; 16] = [
+    0x31, 0xc1, 0xf2, 0xe6, 0xbf, 0x71, 0x43, 0x50, 0xbe, 0x58, 0x05, 0x21, 0x6a, 0xfc, 0x5a, 0xff,
+];
+const CIPHER_CHACHA20: [u8; 16] = [
+    0xd6, 0x03, 0x8a, 0x2b, 0x8b, 0x6f, 0x4c, 0xb5, 0xa5, 0x24, 0x33, 0x9a, 0x31, 0xdb, 0xb5, 0x9a,
+];
+const KDF_AES: [u8; 16] = [
+    0xc9, 0xd9, 0xf3, 0x9a, 0x62, 0x8a, 0x44, 0x60, 0xbf, 0x74, 0x0d, 0x08, 0xc1, 0x8a, 0x4f, 0xea,
+];
+const KDF_ARGON2D: [u8; 16] = [
+    0xef, 0x63, 0x6d, 0xdf, 0x8c, 0x29, 0x44, 0x4b, 0x91, 0xf7, 0xa9, 0xa4, 0x03, 0xe3, 0x0a, 0x0c,
+];
+const KDF_ARGON2ID: [u8; 16] = [
+    0x9e, 0x29, 0x8b, 0x19, 0x56, 0xdb, 0x47, 0x73, 0xb2, 0x3d, 0xfc, 0x3e, 0xc6, 0xf0, 0xa1, 0xe6,
+];
+
+///
+/// Reads the bytes of the header and the blocks one after the other.
+///
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8], ImportErr> {
+        if self.bytes.len() - self.pos < len {
+            eprintln!("ERROR: The KeePass database ends too early!");
+            return Err(ImportErr::Corrupted(0));
+        }
+        let read = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+
+        Ok(read)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ImportErr> {
+        Ok(self.read(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ImportErr> {
+        Ok(u16::from_le_bytes([self.read_u8()?, self.read_u8()?]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ImportErr> {
+        Ok(u32::from_le_bytes(to_array(self.read(4)?)?))
+    }
+}
+
+///
+/// A group while the XML is read, the entries under the recycle bin are skipped.
+///
+#[derive(Default)]
+struct GroupState {
+    name: String,
+    uuid: String,
+}
+
+///
+/// An entry while the XML is read.
+///
+#[derive(Default)]
+struct EntryState {
+    strings: Vec<(String, String)>,
+    binaries: Vec<(String, usize)>,
+}
+
+impl Drop for EntryState {
+    fn drop(&mut self) {
+        for (_, value) in self.strings.iter_mut() {
+            value.zeroize();
+        }
+    }
+}
+
+///
+/// Reads a KeePass KDBX 4 database, unlocked with the password and the keyfile if
+/// it has one. Titles become the entry names, under the path of their group,
+/// passwords the content and notes the notes, along with the username, url and
+/// custom fields. Every attachment becomes an entry of its own, named after the
+/// entry it belongs to. History and the recycle bin are left out.
+///
+pub fn read_kdbx(
+    file: &[u8],
+    password: &str,
+    keyfile: Option<&[u8]>,
+) -> Result<Vec<ImportedEntry>, ImportErr> {
+    let mut reader = ByteReader {
+        bytes: file,
+        pos: 0,
+    };
+    if reader.read_u32()? != SIGNATURE_1 || reader.read_u32()? != SIGNATURE_2 {
+        eprintln!("ERROR: The file is not a KeePass database!");
+        return Err(ImportErr::Corrupted(0));
+    }
+    let _minor_version = reader.read_u16()?;
+    if reader.read_u16()? != SUPPORTED_MAJOR_VERSION {
+        eprintln!(
+            "ERROR: Only KDBX 4 databases can be imported, save it again with a recent KeePass!"
+        );
+        return Err(ImportErr::Unsupported(0));
+    }
+
+    // outer header
+    let mut cipher_id = Vec::new();
+    let mut compressed = false;
+    let mut master_seed = Vec::new();
+    let mut encryption_iv = Vec::new();
+    let mut kdf_parameters = HashMap::new();
+    loop {
+        let field_id = reader.read_u8()?;
+        let field_len = reader.read_u32()? as usize;
+        let field = reader.read(field_len)?;
+        match field_id {
+            HEADER_END => break,
+            HEADER_CIPHER_ID => cipher_id = field.to_vec(),
+            HEADER_COMPRESSION => compressed = u32::from_le_bytes(to_array(field)?) == 1,
+            HEADER_MASTER_SEED => master_seed = field.to_vec(),
+            HEADER_ENCRYPTION_IV => encryption_iv = field.to_vec(),
+            HEADER_KDF_PARAMETERS => kdf_parameters = read_variant_dictionary(field)?,
+            _ => (),
+        };
+    }
+    let header = &file[..reader.pos];
+    let header_hash = reader.read(32)?;
+    let header_hmac = reader.read(32)?;
+    if Sha256::digest(header).as_slice() != header_hash {
+        eprintln!("ERROR: The header of the KeePass database is corrupted!");
+        return Err(ImportErr::Corrupted(0));
+    }
+
+    // the keys, the password is left out when only a keyfile is used
+    let mut composite = Sha256::new();
+    if !password.is_empty() || keyfile.is_none() {
+        composite.update(Sha256::digest(password.as_bytes()));
+    }
+    if let Some(keyfile) = keyfile {
+        let mut keyfile_key = read_keyfile(keyfile)?;
+        composite.update(&keyfile_key);
+        keyfile_key.zeroize();
+    }
+    let mut composite_key = composite.finalize().to_vec();
+    let transformed_key = transform_key(&composite_key, &kdf_parameters);
+    composite_key.zeroize();
+    let mut transformed_key = transformed_key?;
+
+    let mut master_key = Sha256::new()
+        .chain_update(&master_seed)
+        .chain_update(&transformed_key)
+        .finalize()
+        .to_vec();
+    let mut hmac_key = Sha512::new()
+        .chain_update(&master_seed)
+        .chain_update(&transformed_key)
+        .chain_update([1])
+        .finalize()
+        .to_vec();
+    transformed_key.zeroize();
+
+    // a wrong password or keyfile shows up as a header that doesn't match its hmac
+    if !verify_hmac(&hmac_key, u64::MAX, &[header], header_hmac) {
+        master_key.zeroize();
+        hmac_key.zeroize();
+        eprintln!("ERROR: Wrong password or keyfile for the KeePass database!");
+        return Err(ImportErr::WrongCredentials(0));
+    }
+
+    // the payload comes in blocks, each with its own hmac
+    let mut payload = Vec::new();
+    let mut block_index = 0;
+    loop {
+        let block_hmac = reader.read(32)?;
+        let block_size = reader.read(4)?;
+        let block = reader.read(u32::from_le_bytes(to_array(block_size)?) as usize)?;
+        if !verify_hmac(&hmac_key, block_index, &[block_size, block], block_hmac) {
+            master_key.zeroize();
+            hmac_key.zeroize();
+            eprintln!("ERROR: The block {block_index} of the KeePass database is corrupted!");
+            return Err(ImportErr::Corrupted(0));
+        }
+        if block.is_empty() {
+            break;
+        }
+        payload.extend_from_slice(block);
+        block_index += 1;
+    }
+    hmac_key.zeroize();
+
+    let decrypted = decrypt_payload(&cipher_id, &master_key, &encryption_iv, &mut payload);
+    master_key.zeroize();
+    let mut decrypted = decrypted?;
+    if compressed {
+        let mut decompressed = Vec::new();
+        let read = GzDecoder::new(&decrypted[..]).read_to_end(&mut decompressed);
+        decrypted.zeroize();
+        if let Err(err) = read {
+            decompressed.zeroize();
+            eprintln!("ERROR: Failed to decompress the KeePass database!\n{err}");
+            return Err(ImportErr::Corrupted(0));
+        }
+        decrypted = decompressed;
+    }
+
+    let entries = read_inner(&decrypted);
+    decrypted.zeroize();
+
+    entries
+}
+
+///
+/// Reads the inner header (the key of the protected values and the attachments)
+/// and then the XML with the entries after it.
+///
+fn read_inner(decrypted: &[u8]) -> Result<Vec<ImportedEntry>, ImportErr> {
+    let mut reader = ByteReader {
+        bytes: decrypted,
+        pos: 0,
+    };
+    let mut stream_id = 0;
+    let mut stream_key = Vec::new();
+    let mut binaries = Vec::new();
+    loop {
+        let field_id = reader.read_u8()?;
+        let field_len = reader.read_u32()? as usize;
+        let field = reader.read(field_len)?;
+        match field_id {
+            INNER_HEADER_END => break,
+            INNER_HEADER_STREAM_ID => stream_id = u32::from_le_bytes(to_array(field)?),
+            INNER_HEADER_STREAM_KEY => stream_key = field.to_vec(),
+            // the first byte only holds flags
+            INNER_HEADER_BINARY if !field.is_empty() => binaries.push(field[1..].to_vec()),
+            _ => (),
+        };
+    }
+    if stream_id != INNER_STREAM_CHACHA20 {
+        eprintln!("ERROR: The protected values of the KeePass database use an unsupported cipher!");
+        return Err(ImportErr::Unsupported(0));
+    }
+
+    let xml = String::from_utf8_lossy(&decrypted[reader.pos..]);
+    let entries = read_xml(&xml, &binaries, &stream_key);
+    if let std::borrow::Cow::Owned(mut xml) = xml {
+        xml.zeroize();
+    }
+    stream_key.zeroize();
+    for binary in binaries.iter_mut() {
+        binary.zeroize();
+    }
+
+    entries
+}
+
+///
+/// Walks the XML of the database, turning every entry into imported ones.
+/// The protected values are unhidden in the order they come in, history included,
+/// since they all share one key stream.
+///
+fn read_xml(
+    xml: &str,
+    binaries: &[Vec<u8>],
+    stream_key: &[u8],
+) -> Result<Vec<ImportedEntry>, ImportErr> {
+    let mut stream_keys = Sha512::digest(stream_key);
+    let mut protected_stream = ChaCha20::new(
+        Key::from_slice(&stream_keys[..32]),
+        Nonce::from_slice(&stream_keys[32..44]),
+    );
+    stream_keys.zeroize();
+
+    let mut reader = Reader::from_str(xml);
+    reader.expand_empty_elements(true);
+
+    let mut entries = Vec::new();
+    let mut elements: Vec<String> = Vec::new();
+    let mut groups: Vec<GroupState> = Vec::new();
+    let mut recycle_bin_uuid = String::new();
+    let mut entry: Option<EntryState> = None;
+    let mut history_depth = 0;
+    let mut field_key = String::new();
+    let mut field_value = String::new();
+    let mut value_protected = false;
+    let mut value_ref = None;
+    let mut text = String::new();
+    loop {
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(err) => {
+                eprintln!("ERROR: The XML in the KeePass database is malformed!\n{err}");
+                return Err(ImportErr::Corrupted(0));
+            }
+        };
+
+        match event {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+                match name.as_str() {
+                    "Group" => groups.push(GroupState::default()),
+                    "Entry" if history_depth == 0 => entry = Some(EntryState::default()),
+                    "History" => history_depth += 1,
+                    "String" | "Binary" => {
+                        field_key.clear();
+                        field_value.zeroize();
+                        value_ref = None;
+                    }
+                    "Value" => {
+                        value_protected = matches!(
+                            element.try_get_attribute("Protected"),
+                            Ok(Some(attribute)) if attribute.value.as_ref() == b"True"
+                        );
+                        value_ref = match element.try_get_attribute("Ref") {
+                            Ok(Some(attribute)) => String::from_utf8_lossy(&attribute.value)
+                                .parse::<usize>()
+                                .ok(),
+                            _ => None,
+                        };
+                    }
+                    _ => (),
+                };
+                text.zeroize();
+                elements.push(name);
+            }
+            Event::Text(element_text) => match element_text.unescape() {
+                Ok(unescaped) => text += &unescaped,
+                Err(err) => {
+                    eprintln!("ERROR: The XML in the KeePass database is malformed!\n{err}");
+                    return Err(ImportErr::Corrupted(0));
+                }
+            },
+            Event::CData(cdata) => text += &String::from_utf8_lossy(&cdata),
+            Event::End(_) => {
+                let name = elements.pop().unwrap_or_default();
+                let parent = elements.last().map(|parent| parent.as_str());
+                match (name.as_str(), parent) {
+                    ("RecycleBinUUID", Some("Meta")) => recycle_bin_uuid = text.trim().to_owned(),
+                    ("UUID", Some("Group")) => {
+                        if let Some(group) = groups.last_mut() {
+                            group.uuid = text.trim().to_owned();
+                        }
+                    }
+                    ("Name", Some("Group")) => {
+                        if let Some(group) = groups.last_mut() {
+                            group.name = text.to_owned();
+                        }
+                    }
+                    ("Group", _) => {
+                        groups.pop();
+                    }
+                    ("Key", Some("String" | "Binary")) => field_key = text.to_owned(),
+                    ("Value", Some("String")) => {
+                        field_value.zeroize();
+                        field_value = if value_protected {
+                            unhide_value(&mut protected_stream, &text)?
+                        } else {
+                            text.to_owned()
+                        };
+                    }
+                    ("String", _) if history_depth == 0 => {
+                        if let Some(entry) = entry.as_mut() {
+                            entry
+                                .strings
+                                .push((field_key.to_owned(), std::mem::take(&mut field_value)));
+                        }
+                    }
+                    ("Binary", _) if history_depth == 0 => {
+                        if let (Some(entry), Some(value_ref)) = (entry.as_mut(), value_ref) {
+                            entry.binaries.push((field_key.to_owned(), value_ref));
+                        }
+                    }
+                    ("History", _) => history_depth -= 1,
+                    ("Entry", _) if history_depth == 0 => {
+                        let in_recycle_bin = !recycle_bin_uuid.is_empty()
+                            && groups.iter().any(|group| group.uuid == recycle_bin_uuid);
+                        if let Some(finished) = entry.take() {
+                            if !in_recycle_bin {
+                                add_entry(&mut entries, &groups, finished, binaries);
+                            }
+                        }
+                    }
+                    _ => (),
+                };
+                text.zeroize();
+            }
+            Event::Eof => break,
+            _ => (),
+        };
+    }
+    field_value.zeroize();
+
+    Ok(entries)
+}
+
+///
+/// Turns a finished entry, and its attachments, into imported entries.
+///
+fn add_entry(
+    entries: &mut Vec<ImportedEntry>,
+    groups: &[GroupState],
+    mut finished: EntryState,
+    binaries: &[Vec<u8>],
+) {
+    let mut title = String::new();
+    let mut imported = ImportedEntry::default();
+    let mut notes = String::new();
+    let mut details = Vec::new();
+    for (key, value) in finished.strings.iter_mut() {
+        match key.as_str() {
+            "Title" => title = value.to_owned(),
+            "Password" => imported.content = std::mem::take(value).into_bytes(),
+            "Notes" => notes = std::mem::take(value),
+            _ if value.is_empty() => (),
+            _ => details.push(format!("{key}: {value}")),
+        };
+    }
+    if title.trim().is_empty() {
+        title = "Untitled".to_string();
+    }
+
+    // the root group is the database itself, it's left out of the names
+    let mut name_parts: Vec<&str> = groups
+        .iter()
+        .skip(1)
+        .map(|group| group.name.as_str())
+        .collect();
+    name_parts.push(&title);
+    imported.name = name_parts.join("/");
+    imported.notes = import::join_notes(&notes, &details);
+    notes.zeroize();
+    for detail in details.iter_mut() {
+        detail.zeroize();
+    }
+
+    let entry_name = imported.name.to_owned();
+    entries.push(imported);
+    for (key, value_ref) in &finished.binaries {
+        match binaries.get(*value_ref) {
+            Some(binary) => entries.push(ImportedEntry {
+                name: format!("{entry_name}/{key}"),
+                install_path: String::new(),
+                content: binary.to_owned(),
+                content_file: None,
+                notes: format!("Attachment of {entry_name}"),
+                install_targets: Vec::new(),
+                is_template: false,
+                install_mode: InstallMode::Copy,
+                post_install: String::new(),
+            }),
+            None => eprintln!("ERROR: The attachment {key} of {entry_name} is missing!"),
+        };
+    }
+}
+
+///
+/// Unhides a protected value with the next bytes of the key stream.
+///
+fn unhide_value(protected_stream: &mut ChaCha20, text: &str) -> Result<String, ImportErr> {
+    let mut bytes = match base64::decode(text.trim()) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: A protected value in the KeePass database is malformed!\n{err}");
+            return Err(ImportErr::Corrupted(0));
+        }
+    };
+    protected_stream.apply_keystream(&mut bytes);
+    let value = String::from_utf8_lossy(&bytes).to_string();
+    bytes.zeroize();
+
+    Ok(value)
+}
+
+///
+/// Runs the key derivation the database was saved with on the composite key.
+///
+fn transform_key(
+    composite_key: &[u8],
+    kdf_parameters: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<u8>, ImportErr> {
+    let kdf_id = get_parameter(kdf_parameters, "$UUID")?;
+
+    if kdf_id == KDF_AES {
+        let seed = get_parameter(kdf_parameters, "S")?;
+        let rounds = u64::from_le_bytes(to_array(get_parameter(kdf_parameters, "R")?)?);
+        if seed.len() != 32 {
+            eprintln!("ERROR: The key derivation of the KeePass database has a malformed seed!");
+            return Err(ImportErr::Corrupted(0));
+        }
+
+        let cipher = Aes256::new(GenericArray::from_slice(seed));
+        let mut key = composite_key.to_vec();
+        for _ in 0..rounds {
+            for block in key.chunks_mut(16) {
+                cipher.encrypt_block(GenericArray::from_mut_slice(block));
+            }
+        }
+        let transformed = Sha256::digest(&key).to_vec();
+        key.zeroize();
+
+        return Ok(transformed);
+    }
+
+    let variant = if kdf_id == KDF_ARGON2D {
+        Variant::Argon2d
+    } else if kdf_id == KDF_ARGON2ID {
+        Variant::Argon2id
+    } else {
+        eprintln!("ERROR: The KeePass database uses an unsupported key derivation!");
+        return Err(ImportErr::Unsupported(0));
+    };
+    let version = match u32::from_le_bytes(to_array(get_parameter(kdf_parameters, "V")?)?) {
+        0x10 => Version::Version10,
+        0x13 => Version::Version13,
+        _ => {
+            eprintln!("ERROR: The KeePass database uses an unsupported version of Argon2!");
+            return Err(ImportErr::Unsupported(0));
+        }
+    };
+    let salt = get_parameter(kdf_parameters, "S")?;
+    let memory = u64::from_le_bytes(to_array(get_parameter(kdf_parameters, "M")?)?);
+    let iterations = u64::from_le_bytes(to_array(get_parameter(kdf_parameters, "I")?)?);
+    let parallelism = u32::from_le_bytes(to_array(get_parameter(kdf_parameters, "P")?)?);
+
+    // the memory is stored in bytes, argon2 takes it in KiB
+    let config = argon2::Config {
+        ad: &[],
+        hash_length: 32,
+        lanes: parallelism,
+        mem_cost: (memory / 1024) as u32,
+        secret: &[],
+        thread_mode: ThreadMode::Parallel,
+        time_cost: iterations as u32,
+        variant,
+        version,
+    };
+    match argon2::hash_raw(composite_key, salt, &config) {
+        Ok(transformed) => Ok(transformed),
+        Err(err) => {
+            eprintln!("ERROR: Failed to derive the key of the KeePass database!\n{err}");
+            Err(ImportErr::Corrupted(0))
+        }
+    }
+}
+
+///
+/// Decrypts the payload with the cipher the database was saved with.
+///
+fn decrypt_payload(
+    cipher_id: &[u8],
+    master_key: &[u8],
+    encryption_iv: &[u8],
+    payload: &mut [u8],
+) -> Result<Vec<u8>, ImportErr> {
+    if cipher_id == CIPHER_AES256 {
+        if master_key.len() != 32 || encryption_iv.len() != 16 {
+            eprintln!("ERROR: The KeePass database has a malformed encryption iv!");
+            return Err(ImportErr::Corrupted(0));
+        }
+        let decryptor = cbc::Decryptor::<Aes256>::new(master_key.into(), encryption_iv.into());
+        return match decryptor.decrypt_padded_mut::<Pkcs7>(payload) {
+            Ok(decrypted) => Ok(decrypted.to_vec()),
+            Err(err) => {
+                eprintln!("ERROR: Failed to decrypt the KeePass database!\n{err}");
+                Err(ImportErr::Corrupted(0))
+            }
+        };
+    }
+
+    if cipher_id == CIPHER_CHACHA20 {
+        if master_key.len() != 32 || encryption_iv.len() != 12 {
+            eprintln!("ERROR: The KeePass database has a malformed encryption iv!");
+            return Err(ImportErr::Corrupted(0));
+        }
+        let mut cipher = ChaCha20::new(
+            Key::from_slice(master_key),
+            Nonce::from_slice(encryption_iv),
+        );
+        cipher.apply_keystream(payload);
+        return Ok(payload.to_vec());
+    }
+
+    eprintln!("ERROR: The KeePass database uses an unsupported cipher!");
+    Err(ImportErr::Unsupported(0))
+}
+
+///
+/// Checks the hmac of a header or block, keyed with its index.
+///
+fn verify_hmac(hmac_key: &[u8], index: u64, parts: &[&[u8]], expected: &[u8]) -> bool {
+    let mut block_key = Sha512::new()
+        .chain_update(index.to_le_bytes())
+        .chain_update(hmac_key)
+        .finalize();
+    let mut mac = match <Hmac<Sha256> as Mac>::new_from_slice(&block_key) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    block_key.zeroize();
+
+    // blocks are authenticated along with their index, the header isn't
+    if index != u64::MAX {
+        mac.update(&index.to_le_bytes());
+    }
+    for part in parts {
+        mac.update(part);
+    }
+
+    mac.verify_slice(expected).is_ok()
+}
+
+///
+/// The key a keyfile adds to the composite key, in any of the formats KeePass writes.
+///
+fn read_keyfile(keyfile: &[u8]) -> Result<Vec<u8>, ImportErr> {
+    if keyfile.len() == 32 {
+        return Ok(keyfile.to_vec());
+    }
+    if keyfile.len() == 64 {
+        if let Some(key) = decode_hex(&String::from_utf8_lossy(keyfile)) {
+            return Ok(key);
+        }
+    }
+
+    let text = String::from_utf8_lossy(keyfile);
+    if text.contains("<KeyFile>") {
+        let mut reader = Reader::from_str(&text);
+        let mut element = String::new();
+        let mut version = String::new();
+        let mut data = String::new();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(start)) => {
+                    element = String::from_utf8_lossy(start.local_name().as_ref()).to_string();
+                }
+                Ok(Event::Text(element_text)) => {
+                    let unescaped = element_text.unescape().unwrap_or_default();
+                    match element.as_str() {
+                        "Version" => version += &unescaped,
+                        "Data" => data += &unescaped,
+                        _ => (),
+                    };
+                }
+                Ok(Event::End(_)) => element.clear(),
+                Ok(Event::Eof) => break,
+                Ok(_) => (),
+                Err(err) => {
+                    eprintln!("ERROR: The keyfile is malformed XML!\n{err}");
+                    return Err(ImportErr::Corrupted(0));
+                }
+            };
+        }
+
+        // version 2 keyfiles hold the key as hex, version 1 as base64
+        let key = if version.trim().starts_with('2') {
+            decode_hex(&data)
+        } else {
+            base64::decode(data.trim()).ok()
+        };
+        data.zeroize();
+        return match key {
+            Some(key) => Ok(key),
+            None => {
+                eprintln!("ERROR: The key in the keyfile is malformed!");
+                Err(ImportErr::Corrupted(0))
+            }
+        };
+    }
+
+    // any other file is used as a whole
+    Ok(Sha256::digest(keyfile).to_vec())
+}
+
+///
+/// Reads the variant dictionary holding the key derivation parameters.
+///
+fn read_variant_dictionary(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>, ImportErr> {
+    let mut reader = ByteReader { bytes, pos: 0 };
+    let _version = reader.read_u16()?;
+
+    let mut dictionary = HashMap::new();
+    loop {
+        let value_type = reader.read_u8()?;
+        if value_type == 0 {
+            break;
+        }
+        let key_len = reader.read_u32()? as usize;
+        let key = String::from_utf8_lossy(reader.read(key_len)?).to_string();
+        let value_len = reader.read_u32()? as usize;
+        dictionary.insert(key, reader.read(value_len)?.to_vec());
+    }
+
+    Ok(dictionary)
+}
+
+fn get_parameter<'a>(
+    kdf_parameters: &'a HashMap<String, Vec<u8>>,
+    name: &str,
+) -> Result<&'a [u8], ImportErr> {
+    match kdf_parameters.get(name) {
+        Some(value) => Ok(value),
+        None => {
+            eprintln!(
+                "ERROR: The KeePass database is missing the key derivation parameter {name}!"
+            );
+            Err(ImportErr::Corrupted(0))
+        }
+    }
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> Result<[u8; N], ImportErr> {
+    match bytes.try_into() {
+        Ok(array) => Ok(array),
+        Err(_) => {
+            eprintln!("ERROR: A field of the KeePass database has the wrong size!");
+            Err(ImportErr::Corrupted(0))
+        }
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,289 @@
+use crate::encrypter::ecies::ECIES;
+use crate::hasher::blake3;
+use crate::utils::Vault;
+use serde::{Deserialize, Serialize};
+use sled::Db;
+
+/// The name of the tree in the vault db holding the contacts, encrypted,
+/// under a keyed hash of their public key.
+pub const CONTACTS_TREE: &str = "contacts";
+
+/// The lines around an exported public key.
+const PUBLIC_KEY_BEGIN: &str = "-----BEGIN ESTASH PUBLIC KEY-----";
+const PUBLIC_KEY_END: &str = "-----END ESTASH PUBLIC KEY-----";
+
+/// The file extension used for exported public keys.
+pub const PUBLIC_KEY_FILE_EXTENSION: &str = "estash-pub";
+
+///
+/// The public key of another vault, under a name chosen by the owner of this vault.
+///
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Contact {
+    pub name: String,
+    #[serde(with = "serde_bytes")]
+    pub pub_key: Vec<u8>,
+}
+
+impl Contact {
+    ///
+    /// The public key as an array, contacts are only stored with 32 byte keys.
+    ///
+    pub fn pub_key_array(&self) -> Option<[u8; 32]> {
+        self.pub_key.to_owned().try_into().ok()
+    }
+}
+
+#[derive(Debug)]
+pub enum KeyringErr {
+    DbInaccesible(u16),
+    Corrupted(u16),
+    InvalidKey(u16),
+    FingerprintMismatch(u16),
+    AlreadyExists(u16),
+    NotFound(u16),
+}
+
+///
+/// A short, human comparable digest of a public key, the first 20 bytes of its
+/// blake3 hash in hex, grouped by 4 characters.
+/// Compare it over another channel (in person, on the phone) before trusting a key.
+///
+pub fn fingerprint(pub_key: &[u8; 32]) -> String {
+    let hash = blake3::hash_bytes(pub_key);
+    let hash_hex = hex::encode_upper(&hash[..20]);
+
+    let mut fingerprint = String::new();
+    for (index, c) in hash_hex.chars().enumerate() {
+        if index > 0 && index % 4 == 0 {
+            fingerprint.push(' ');
+        }
+        fingerprint.push(c);
+    }
+
+    fingerprint
+}
+
+///
+/// The public key of the vault as a text blob that can be pasted anywhere,
+/// with the vault name and the fingerprint of the key.
+///
+pub fn export_public_key(vault: &Vault) -> String {
+    format!(
+        "{PUBLIC_KEY_BEGIN}\nVault: {}\nFingerprint: {}\n{}\n{PUBLIC_KEY_END}\n",
+        vault.vault_name,
+        fingerprint(&vault.pub_key),
+        hex::encode(vault.pub_key)
+    )
+}
+
+///
+/// Reads a public key exported by export_public_key, or just the key in hex.
+/// If there is a fingerprint in the blob it has to match the key.
+/// Returns the key and the vault name from the blob, if there is one.
+///
+pub fn parse_public_key(text: &str) -> Result<([u8; 32], Option<String>), KeyringErr> {
+    let mut pub_key: Option<[u8; 32]> = None;
+    let mut vault_name: Option<String> = None;
+    let mut stated_fingerprint: Option<String> = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line == PUBLIC_KEY_BEGIN || line == PUBLIC_KEY_END {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("Vault:") {
+            vault_name = Some(name.trim().to_owned());
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Fingerprint:") {
+            stated_fingerprint = Some(value.trim().to_owned());
+            continue;
+        }
+
+        let mut key_bytes: [u8; 32] = [0; 32];
+        match hex::decode_to_slice(line, &mut key_bytes) {
+            Ok(_) if pub_key.is_none() => pub_key = Some(key_bytes),
+            _ => {
+                eprintln!("ERROR: The public key has an unexpected line in it!");
+                return Err(KeyringErr::InvalidKey(0));
+            }
+        };
+    }
+
+    let pub_key = match pub_key {
+        Some(key) => key,
+        None => {
+            eprintln!("ERROR: There is no public key in the given text!");
+            return Err(KeyringErr::InvalidKey(0));
+        }
+    };
+
+    if let Some(stated_fingerprint) = stated_fingerprint {
+        if stated_fingerprint.to_uppercase() != fingerprint(&pub_key) {
+            eprintln!("ERROR: The fingerprint in the blob doesn't match the public key!");
+            return Err(KeyringErr::FingerprintMismatch(0));
+        }
+    }
+
+    Ok((pub_key, vault_name))
+}
+
+///
+/// Lists the contacts of the vault, sorted by their name.
+/// Contacts stored by older versions under their public key are moved to its keyed hash.
+///
+pub fn list_contacts(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+) -> Result<Vec<Contact>, KeyringErr> {
+    let contacts_tree = open_contacts_tree(vault_db)?;
+
+    // read them all first, moving a contact while iterating could list it twice
+    let mut records = Vec::new();
+    for contact in contacts_tree.iter() {
+        match contact {
+            Ok(kv) => records.push(kv),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a contact from the vault db!\n{err}");
+                return Err(KeyringErr::DbInaccesible(0));
+            }
+        };
+    }
+
+    let mut contacts = Vec::new();
+    for (key, value) in records {
+        let contact_bytes = match ecies.decrypt_bytes_array(&value, &vault.priv_key, &vault.pub_key)
+        {
+            Ok(plain) => plain,
+            Err(err) => {
+                eprintln!(
+                    "ERROR: Failed to decrypt a contact, the vault db may be corrupted!\n{err}"
+                );
+                return Err(KeyringErr::Corrupted(0));
+            }
+        };
+        let contact = match rmp_serde::from_slice::<Contact>(&contact_bytes) {
+            Ok(contact) => contact,
+            Err(err) => {
+                eprintln!("ERROR: A contact is not stored in estash's format!\n{err}");
+                return Err(KeyringErr::Corrupted(0));
+            }
+        };
+
+        let contact_key = contact_key(vault, &contact.pub_key);
+        if key != contact_key[..] {
+            let moved = contacts_tree.insert(&contact_key, value).is_ok()
+                && contacts_tree.remove(key).is_ok();
+            if !moved {
+                eprintln!("ERROR: Failed to move a contact to its keyed hash, it will be tried again the next time the contacts are read!");
+            }
+        }
+        contacts.push(contact);
+    }
+
+    contacts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(contacts)
+}
+
+///
+/// Finds a contact by the name it was given in this vault.
+///
+pub fn find_contact(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    name: &str,
+) -> Result<Contact, KeyringErr> {
+    let contacts = list_contacts(vault_db, ecies, vault)?;
+
+    match contacts.into_iter().find(|contact| contact.name == name) {
+        Some(contact) => Ok(contact),
+        None => Err(KeyringErr::NotFound(0)),
+    }
+}
+
+///
+/// Stores a contact, encrypted with the vault keys so the names don't leak.
+/// Neither the name nor the key can already be in the keyring.
+///
+pub fn add_contact(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    name: &str,
+    pub_key: &[u8; 32],
+) -> Result<(), KeyringErr> {
+    let contacts = list_contacts(vault_db, ecies, vault)?;
+    if contacts
+        .iter()
+        .any(|contact| contact.name == name || contact.pub_key == pub_key)
+    {
+        return Err(KeyringErr::AlreadyExists(0));
+    }
+
+    let contact = Contact {
+        name: name.to_owned(),
+        pub_key: pub_key.to_vec(),
+    };
+    let contact_bytes = match rmp_serde::to_vec_named(&contact) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the contact!\n{err}");
+            return Err(KeyringErr::Corrupted(0));
+        }
+    };
+    let contact_encrypted =
+        match ecies.encrypt_bytes_array(&contact_bytes, &vault.priv_key, &vault.pub_key) {
+            Ok(cipher) => cipher,
+            Err(err) => {
+                eprintln!("ERROR: Failed to encrypt the contact!\n{err}");
+                return Err(KeyringErr::Corrupted(0));
+            }
+        };
+
+    let contacts_tree = open_contacts_tree(vault_db)?;
+    match contacts_tree.insert(contact_key(vault, pub_key), contact_encrypted) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the contact in the vault db!\n{err}");
+            Err(KeyringErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Removes the contact with the given public key.
+///
+pub fn remove_contact(vault_db: &Db, vault: &Vault, pub_key: &[u8; 32]) -> Result<(), KeyringErr> {
+    let contacts_tree = open_contacts_tree(vault_db)?;
+
+    match contacts_tree.remove(contact_key(vault, pub_key)) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(KeyringErr::NotFound(0)),
+        Err(err) => {
+            eprintln!("ERROR: Failed to remove the contact from the vault db!\n{err}");
+            Err(KeyringErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// The key a contact is stored under, a keyed hash of its public key, so the
+/// vault db doesn't show who the contacts are.
+///
+fn contact_key(vault: &Vault, pub_key: &[u8]) -> Vec<u8> {
+    super::core::entry_key(vault, &format!("contact\0{}", hex::encode(pub_key)))
+}
+
+fn open_contacts_tree(vault_db: &Db) -> Result<sled::Tree, KeyringErr> {
+    match vault_db.open_tree(CONTACTS_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the contacts tree of the vault db!\n{err}");
+            Err(KeyringErr::DbInaccesible(0))
+        }
+    }
+}
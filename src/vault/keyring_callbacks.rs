@@ -0,0 +1,329 @@
+use super::keyring::{self, KeyringErr};
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Keyring/Export Public Key menu item.
+/// Shows the public key of the vault with its fingerprint,
+/// and offers to save it to a file for sending it to others.
+///
+pub fn export_public_key_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let (vault_name, public_key_text) = match vault_arc.lock() {
+        Ok(vault) => (
+            vault.vault_name.to_owned(),
+            keyring::export_public_key(&vault),
+        ),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    super::report_window::show("Public Key", &public_key_text);
+
+    match dialog::choice2_default("Save the public key to a file?", "No", "Save", "") {
+        Some(1) => (),
+        _ => return,
+    };
+
+    let mut file_dialog =
+        dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+    file_dialog.set_title("Save the public key");
+    file_dialog.set_preset_file(&format!(
+        "{vault_name}.{}",
+        keyring::PUBLIC_KEY_FILE_EXTENSION
+    ));
+    file_dialog.show();
+    let path = file_dialog.filename().to_string_lossy().to_string();
+    if path.is_empty() {
+        return;
+    }
+
+    match fs::write(&path, public_key_text) {
+        Ok(_) => {
+            status_label.set_label("The public key has been saved!");
+            status_label.show();
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to write the public key to the file!\n{err}");
+            status_label.set_label("Failed to write the public key to the file!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Keyring/Import Contact menu item.
+/// Reads a public key, from a file or pasted in, shows its fingerprint
+/// for checking and stores it under the given name.
+///
+pub fn import_contact_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let public_key_text = match dialog::choice2_default(
+        "Import the public key from a file, or paste it in hex?",
+        "Cancel",
+        "From File",
+        "Paste",
+    ) {
+        Some(1) => {
+            let mut file_dialog =
+                dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+            file_dialog.set_title("Select the public key");
+            file_dialog.show();
+            let path = file_dialog.filename().to_string_lossy().to_string();
+            if path.is_empty() {
+                return;
+            }
+            match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to read the public key file!\n{err}");
+                    status_label.set_label("Failed to read the selected file!");
+                    status_label.show();
+                    return;
+                }
+            }
+        }
+        Some(2) => match dialog::input_default("Public key (hex):", "") {
+            Some(text) => text,
+            None => return,
+        },
+        _ => return,
+    };
+
+    let (pub_key, vault_name) = match keyring::parse_public_key(&public_key_text) {
+        Ok(parsed) => parsed,
+        Err(KeyringErr::FingerprintMismatch(_)) => {
+            status_label.set_label("The fingerprint doesn't match the key, it was tampered with!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("That's not a valid public key!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let name = match dialog::input_default(
+        &format!(
+            "Fingerprint: {}\nCheck it with the owner of the key before trusting it.\n\nName for the contact:",
+            keyring::fingerprint(&pub_key)
+        ),
+        &vault_name.unwrap_or_default(),
+    ) {
+        Some(name) => name.trim().to_owned(),
+        None => return,
+    };
+    if name.is_empty() {
+        status_label.set_label("The contact needs a name!");
+        status_label.show();
+        return;
+    }
+
+    // get references from arcs
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut ecies = match ecies_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    match keyring::add_contact(&vault_db, &mut ecies, &vault, &name, &pub_key) {
+        Ok(_) => {
+            status_label.set_label(&format!("Added {name} to the keyring!"));
+            status_label.show();
+        }
+        Err(KeyringErr::AlreadyExists(_)) => {
+            status_label.set_label("A contact with this name or key is already in the keyring!");
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to store the contact, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Keyring/List Contacts menu item.
+/// Shows every contact with the fingerprint of its key.
+///
+pub fn list_contacts_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let contacts = match (vault_db_arc.lock(), vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault_db), Ok(vault), Ok(mut ecies)) => {
+            keyring::list_contacts(&vault_db, &mut ecies, &vault)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let contacts = match contacts {
+        Ok(contacts) => contacts,
+        Err(_) => {
+            status_label.set_label("Failed to read the keyring, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let mut text = String::from("Contacts in the keyring:\n\n");
+    if contacts.is_empty() {
+        text += "None yet, import a public key with Keyring > Import Contact.\n";
+    }
+    for contact in &contacts {
+        let fingerprint = match contact.pub_key_array() {
+            Some(pub_key) => keyring::fingerprint(&pub_key),
+            None => "invalid key".to_string(),
+        };
+        text += &format!("{}\n  Fingerprint: {fingerprint}\n", contact.name);
+    }
+
+    super::report_window::show("Keyring", &text);
+}
+
+///
+/// Callback for the Keyring/Remove Contact menu item.
+/// Removes the contact with the given name from the keyring.
+///
+pub fn remove_contact_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let name = match dialog::input_default("Name of the contact to remove:", "") {
+        Some(name) => name.trim().to_owned(),
+        None => return,
+    };
+
+    // get references from arcs
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let contact = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => keyring::find_contact(&vault_db, &mut ecies, &vault, &name),
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let pub_key = match contact.map(|contact| contact.pub_key_array()) {
+        Ok(Some(pub_key)) => pub_key,
+        Ok(None) | Err(KeyringErr::NotFound(_)) => {
+            status_label.set_label("There is no contact with that name!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the keyring, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let removed = match vault_arc.lock() {
+        Ok(vault) => keyring::remove_contact(&vault_db, &vault, &pub_key),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    match removed {
+        Ok(_) => {
+            status_label.set_label(&format!("Removed {name} from the keyring!"));
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to remove the contact, try again or restart!");
+            status_label.show();
+        }
+    };
+}
@@ -1,5 +1,40 @@
+mod audit;
+mod audit_callbacks;
+mod content_stream;
 mod core;
+mod credentials;
+mod dir_store;
+mod dir_store_callbacks;
+mod drift;
+mod drift_callbacks;
 mod dry_callbacks;
 mod entries_callbacks;
+mod export;
+mod export_callbacks;
+mod hooks;
+mod hooks_callbacks;
+mod import;
+mod import_callbacks;
+mod install_targets;
+mod install_targets_callbacks;
+mod installs;
+mod installs_callbacks;
+mod integrity;
+mod kdbx;
+mod keyring;
+mod keyring_callbacks;
+mod path_vars;
+mod path_vars_callbacks;
+mod report_window;
+mod settings;
+mod share_callbacks;
+mod sharing;
+mod sync;
+mod sync_callbacks;
+mod team;
+mod team_callbacks;
+mod templates;
+mod templates_callbacks;
+mod tools_callbacks;
 mod value_callbacks;
 pub mod window;
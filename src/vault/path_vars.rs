@@ -0,0 +1,164 @@
+use super::settings;
+use crate::utils;
+use sled::Db;
+use std::collections::BTreeMap;
+
+/// The variables every machine knows, with where their value comes from when not set.
+pub const BUILTIN_PATH_VARIABLES: [(&str, &str); 7] = [
+    ("HOME", "the home folder of the user"),
+    ("XDG_CONFIG_HOME", "$XDG_CONFIG_HOME, else ~/.config"),
+    ("XDG_DATA_HOME", "$XDG_DATA_HOME, else ~/.local/share"),
+    ("XDG_CACHE_HOME", "$XDG_CACHE_HOME, else ~/.cache"),
+    ("XDG_STATE_HOME", "$XDG_STATE_HOME, else ~/.local/state"),
+    ("USER", "the name of the user"),
+    ("HOSTNAME", "the name of this machine"),
+];
+
+///
+/// An install path with its variables replaced by their value on this machine.
+///
+pub struct ExpandedPath {
+    pub path: String,
+    // names of the variables with no value here, left as they were in the path
+    pub unknown: Vec<String>,
+}
+
+///
+/// Replaces a leading ~, $NAME and ${NAME} in the path by their value on this
+/// machine, $$ gives a literal $.
+/// A variable is looked up in the custom ones first, then in the environment,
+/// then in the defaults of the builtin ones.
+///
+pub fn expand(path: &str, custom: &BTreeMap<String, String>) -> ExpandedPath {
+    let mut expanded = ExpandedPath {
+        path: String::new(),
+        unknown: Vec::new(),
+    };
+
+    let mut rest = path;
+    if let Some(after) = path.strip_prefix('~') {
+        if after.is_empty() || after.starts_with('/') || after.starts_with('\\') {
+            push_variable(&mut expanded, "HOME", "~", custom);
+            rest = after;
+        }
+    }
+
+    let mut chars = rest.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c != '$' {
+            expanded.path.push(c);
+            continue;
+        }
+
+        match chars.peek() {
+            Some((_, '$')) => {
+                chars.next();
+                expanded.path.push('$');
+            }
+            Some((_, '{')) => {
+                let start = index + 2;
+                match rest[start..].find('}') {
+                    Some(length) if is_variable_name(&rest[start..start + length]) => {
+                        let name = &rest[start..start + length];
+                        push_variable(&mut expanded, name, &rest[index..=start + length], custom);
+                        for (next_index, _) in chars.by_ref() {
+                            if next_index == start + length {
+                                break;
+                            }
+                        }
+                    }
+                    _ => expanded.path.push('$'),
+                }
+            }
+            _ => {
+                let start = index + 1;
+                let length = rest[start..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len() - start);
+                let name = &rest[start..start + length];
+                if is_variable_name(name) {
+                    push_variable(&mut expanded, name, &rest[index..start + length], custom);
+                    for _ in 0..length {
+                        chars.next();
+                    }
+                } else {
+                    expanded.path.push('$');
+                }
+            }
+        };
+    }
+
+    expanded
+}
+
+///
+/// Expands the path with the custom variables set on this machine.
+///
+pub fn expand_with_settings(vault_db: &Db, path: &str) -> ExpandedPath {
+    expand(path, &settings::get_path_variables(vault_db))
+}
+
+///
+/// Checks the name can be used as a variable, letters, digits and _,
+/// not starting with a digit.
+///
+pub fn is_variable_name(name: &str) -> bool {
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+///
+/// The value of the variable on this machine, if it has one.
+///
+pub fn lookup(name: &str, custom: &BTreeMap<String, String>) -> Option<String> {
+    if let Some(value) = custom.get(name) {
+        return Some(value.to_owned());
+    }
+    if let Ok(value) = std::env::var(name) {
+        if !value.is_empty() {
+            return Some(value);
+        }
+    }
+
+    let home = || dirs::home_dir().map(|home_dir| home_dir.to_string_lossy().to_string());
+    let in_home = |rest: &str| {
+        home().map(|home| {
+            let mut path = std::path::PathBuf::from(home);
+            path.extend(rest.split('/'));
+            path.to_string_lossy().to_string()
+        })
+    };
+    match name {
+        "HOME" => home(),
+        "XDG_CONFIG_HOME" => in_home(".config"),
+        "XDG_DATA_HOME" => in_home(".local/share"),
+        "XDG_CACHE_HOME" => in_home(".cache"),
+        "XDG_STATE_HOME" => in_home(".local/state"),
+        "USER" => std::env::var("USERNAME")
+            .ok()
+            .filter(|name| !name.is_empty()),
+        "HOSTNAME" => Some(utils::hostname()).filter(|name| !name.is_empty()),
+        _ => None,
+    }
+}
+
+fn push_variable(
+    expanded: &mut ExpandedPath,
+    name: &str,
+    written: &str,
+    custom: &BTreeMap<String, String>,
+) {
+    match lookup(name, custom) {
+        Some(value) => expanded.path.push_str(&value),
+        None => {
+            expanded.path.push_str(written);
+            if !expanded.unknown.iter().any(|unknown| unknown == name) {
+                expanded.unknown.push(name.to_owned());
+            }
+        }
+    };
+}
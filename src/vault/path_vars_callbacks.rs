@@ -0,0 +1,167 @@
+use super::path_vars::{self, BUILTIN_PATH_VARIABLES};
+use super::report_window;
+use super::settings;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+
+///
+/// Shows where the install path resolves to on this machine in its label,
+/// or which of its variables aren't set here.
+///
+pub fn update_path_preview(
+    install_path_label_arc: Arc<Mutex<frame::Frame>>,
+    install_path_arc: Arc<Mutex<input::Input>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+) {
+    // get references from arcs
+    let (path, active) = match install_path_arc.lock() {
+        Ok(install_path) => (install_path.value(), install_path.active()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under install_path_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let expanded = match vault_db_arc.lock() {
+        Ok(vault_db) => path_vars::expand_with_settings(&vault_db, &path),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let mut install_path_label = match install_path_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under install_path_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    if !active || expanded.path == path {
+        install_path_label.set_label("Install Path");
+    } else if !expanded.unknown.is_empty() {
+        install_path_label.set_label(&format!(
+            "Install Path (not set here: {})",
+            expanded.unknown.join(", ")
+        ));
+    } else {
+        install_path_label.set_label(&format!("Install Path -> {}", expanded.path));
+    }
+}
+
+///
+/// Callback for the Path Variables/Set Variable menu item.
+/// Sets a variable for the install paths on this machine, NAME=value,
+/// an empty value removes it.
+///
+pub fn set_path_variable_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    install_path_label_arc: Arc<Mutex<frame::Frame>>,
+    install_path_arc: Arc<Mutex<input::Input>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let input = match dialog::input_default(
+        "Set a variable of the install paths for this machine, as NAME=value.\nUse it in a path as $NAME or ${NAME}, leave the value empty to remove it.",
+        "",
+    ) {
+        Some(input) => input,
+        None => return,
+    };
+    let (name, value) = match input.split_once('=') {
+        Some((name, value)) => (name.trim().to_owned(), value.trim().to_owned()),
+        None => {
+            status_label.set_label("Write the variable as NAME=value!");
+            status_label.show();
+            return;
+        }
+    };
+    if !path_vars::is_variable_name(&name) {
+        status_label
+            .set_label("Variable names are letters, digits and _, not starting with a digit!");
+        status_label.show();
+        return;
+    }
+
+    let saved = match vault_db_arc.lock() {
+        Ok(vault_db) => {
+            let mut variables = settings::get_path_variables(&vault_db);
+            if value.is_empty() {
+                variables.remove(&name);
+            } else {
+                variables.insert(name.to_owned(), value.to_owned());
+            }
+            settings::set_path_variables(&vault_db, &variables)
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    if saved.is_err() {
+        status_label.set_label("Failed to save the variable, try again or restart!");
+        status_label.show();
+        return;
+    }
+
+    update_path_preview(install_path_label_arc, install_path_arc, vault_db_arc);
+    if value.is_empty() {
+        status_label.set_label(&format!("Removed ${name} from this machine!"));
+    } else {
+        status_label.set_label(&format!("Set ${name} to {value} on this machine!"));
+    }
+    status_label.show();
+}
+
+///
+/// Callback for the Path Variables/List Variables menu item.
+/// Shows the builtin and custom variables with their value on this machine.
+///
+pub fn list_path_variables_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let custom = match vault_db_arc.lock() {
+        Ok(vault_db) => settings::get_path_variables(&vault_db),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let mut report = String::from("Builtin variables, set on every machine:\n");
+    for (name, source) in BUILTIN_PATH_VARIABLES {
+        let value = path_vars::lookup(name, &custom).unwrap_or_else(|| "(not set)".to_owned());
+        report += &format!("  ${name} = {value}\n      {source}\n");
+    }
+    report += "\nVariables set on this machine:\n";
+    if custom.is_empty() {
+        report += "  none, add them with Path Variables/Set Variable\n";
+    }
+    for (name, value) in &custom {
+        report += &format!("  ${name} = {value}\n");
+    }
+    report += "\nA leading ~ is the same as $HOME, write $$ for a literal $.\n";
+
+    report_window::show("Path Variables", &report);
+}
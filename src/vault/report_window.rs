@@ -0,0 +1,21 @@
+use fltk::{enums::Color, prelude::*, text, window::Window};
+
+///
+/// Opens a separate read only window displaying the given text.
+/// Used for reports that are too long to fit in a message dialog.
+///
+pub fn show(title: &str, report: &str) {
+    let mut wind = Window::default().with_size(600, 400).with_label(title);
+    wind.set_xclass("estash");
+
+    let mut text_display = text::TextDisplay::default().with_size(600, 400);
+    text_display.set_color(Color::from_rgb(23, 23, 23));
+    text_display.set_text_color(Color::from_rgb(140, 140, 140));
+    let mut text_buf = text::TextBuffer::default();
+    text_buf.set_text(report);
+    text_display.set_buffer(text_buf);
+
+    wind.end();
+    wind.resizable(&text_display);
+    wind.show();
+}
@@ -0,0 +1,197 @@
+use sled::Db;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The name of the tree in the vault db holding the vault settings.
+pub const SETTINGS_TREE: &str = "settings";
+
+const COMPRESS_CONTENT_KEY: &str = "compress_content";
+
+const STORE_DIRECTORY_KEY: &str = "store_directory";
+
+const SYNC_DIRECTORY_KEY: &str = "sync_directory";
+
+const PATH_VARIABLES_KEY: &str = "path_variables";
+
+const APPROVED_HOOKS_KEY: &str = "approved_hooks";
+
+#[derive(Debug)]
+pub enum SettingsErr {
+    DbInaccesible(u16),
+}
+
+///
+/// Whether the entry values should be compressed before they get encrypted.
+/// Off by default, and also if the setting can't be read.
+///
+pub fn is_compression_enabled(vault_db: &Db) -> bool {
+    let settings_tree = match vault_db.open_tree(SETTINGS_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the settings tree of the vault db!\n{err}");
+            return false;
+        }
+    };
+
+    match settings_tree.get(COMPRESS_CONTENT_KEY) {
+        Ok(Some(value)) => value.first() == Some(&1),
+        Ok(None) => false,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the compression setting of the vault!\n{err}");
+            false
+        }
+    }
+}
+
+///
+/// Turn on or off the compression of entry values, only the values
+/// saved from now on are affected.
+///
+pub fn set_compression_enabled(vault_db: &Db, enabled: bool) -> Result<(), SettingsErr> {
+    let settings_tree = match vault_db.open_tree(SETTINGS_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the settings tree of the vault db!\n{err}");
+            return Err(SettingsErr::DbInaccesible(0));
+        }
+    };
+
+    match settings_tree.insert(COMPRESS_CONTENT_KEY, vec![enabled as u8]) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the compression setting of the vault!\n{err}");
+            Err(SettingsErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// The directory snapshot last used on this machine, it's not saved into the store itself
+/// since the path is different on every machine.
+///
+pub fn get_store_directory(vault_db: &Db) -> Option<String> {
+    get_text(vault_db, STORE_DIRECTORY_KEY)
+}
+
+///
+/// Remember the directory snapshot used, so it's the default the next time.
+///
+pub fn set_store_directory(vault_db: &Db, dir: &str) -> Result<(), SettingsErr> {
+    set_text(vault_db, STORE_DIRECTORY_KEY, dir)
+}
+
+///
+/// The sync directory last used on this machine.
+///
+pub fn get_sync_directory(vault_db: &Db) -> Option<String> {
+    get_text(vault_db, SYNC_DIRECTORY_KEY)
+}
+
+///
+/// Remember the sync directory used, so it's the default the next time.
+///
+pub fn set_sync_directory(vault_db: &Db, dir: &str) -> Result<(), SettingsErr> {
+    set_text(vault_db, SYNC_DIRECTORY_KEY, dir)
+}
+
+///
+/// The variables set on this machine for the install paths, by name.
+/// They're not synced or stored into a directory snapshot, since they're what
+/// differs from one machine to another.
+///
+pub fn get_path_variables(vault_db: &Db) -> BTreeMap<String, String> {
+    match get_text(vault_db, PATH_VARIABLES_KEY) {
+        Some(text) => match serde_json::from_str(&text) {
+            Ok(variables) => variables,
+            Err(err) => {
+                eprintln!("ERROR: The path variables of the vault are malformed!\n{err}");
+                BTreeMap::new()
+            }
+        },
+        None => BTreeMap::new(),
+    }
+}
+
+///
+/// Replace the variables set on this machine for the install paths.
+///
+pub fn set_path_variables(
+    vault_db: &Db,
+    variables: &BTreeMap<String, String>,
+) -> Result<(), SettingsErr> {
+    match serde_json::to_string(variables) {
+        Ok(text) => set_text(vault_db, PATH_VARIABLES_KEY, &text),
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the path variables of the vault!\n{err}");
+            Err(SettingsErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// The keyed hashes of the post-install hooks allowed to run on this machine.
+///
+pub fn get_approved_hooks(vault_db: &Db) -> BTreeSet<String> {
+    match get_text(vault_db, APPROVED_HOOKS_KEY) {
+        Some(text) => match serde_json::from_str(&text) {
+            Ok(approved_hooks) => approved_hooks,
+            Err(err) => {
+                eprintln!("ERROR: The approved hooks of the vault are malformed!\n{err}");
+                BTreeSet::new()
+            }
+        },
+        None => BTreeSet::new(),
+    }
+}
+
+///
+/// Replace the post-install hooks allowed to run on this machine.
+///
+pub fn set_approved_hooks(
+    vault_db: &Db,
+    approved_hooks: &BTreeSet<String>,
+) -> Result<(), SettingsErr> {
+    match serde_json::to_string(approved_hooks) {
+        Ok(text) => set_text(vault_db, APPROVED_HOOKS_KEY, &text),
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the approved hooks of the vault!\n{err}");
+            Err(SettingsErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn get_text(vault_db: &Db, key: &str) -> Option<String> {
+    let settings_tree = match vault_db.open_tree(SETTINGS_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the settings tree of the vault db!\n{err}");
+            return None;
+        }
+    };
+
+    match settings_tree.get(key) {
+        Ok(Some(value)) => String::from_utf8(value.to_vec()).ok(),
+        Ok(None) => None,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the {key} setting of the vault!\n{err}");
+            None
+        }
+    }
+}
+
+fn set_text(vault_db: &Db, key: &str, text: &str) -> Result<(), SettingsErr> {
+    let settings_tree = match vault_db.open_tree(SETTINGS_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the settings tree of the vault db!\n{err}");
+            return Err(SettingsErr::DbInaccesible(0));
+        }
+    };
+
+    match settings_tree.insert(key, text.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the {key} setting of the vault!\n{err}");
+            Err(SettingsErr::DbInaccesible(0))
+        }
+    }
+}
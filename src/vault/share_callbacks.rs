@@ -0,0 +1,331 @@
+use super::core::{NewEntryErr, VaultValueErr};
+use super::keyring;
+use super::sharing::{self, ShareErr};
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Share/Share Entry menu item.
+/// Seals the selected entry to the public key of another vault
+/// and saves it as a file that only that vault can import.
+///
+pub fn share_entry_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the entry to share first!");
+        status_label.show();
+        return;
+    }
+
+    let recipient = match dialog::input_default(
+        "Share with (a contact in the keyring, the name of a vault on this machine, or a public key in hex):",
+        "",
+    ) {
+        Some(recipient) => recipient.trim().to_owned(),
+        None => return,
+    };
+
+    let recipient_pub_key = match (vault_db_arc.lock(), vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault_db), Ok(vault), Ok(mut ecies)) => {
+            sharing::resolve_recipient(&vault_db, &mut ecies, &vault, &recipient)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let recipient_pub_key = match recipient_pub_key {
+        Ok(key) => key,
+        Err(ShareErr::RecipientNotFound(_)) => {
+            status_label.set_label(
+                "There is no contact or vault with that name, and it's not a public key!",
+            );
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to access the vaults db, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let value = match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &current_selected_entry,
+    ) {
+        Ok(value) => value,
+        Err(VaultValueErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the entry, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // get references from arcs
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut ecies = match ecies_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // sealed before asking where to save it, so a refused entry doesn't ask for a file
+    let sealed = match sharing::seal_entry(&mut ecies, &vault, value, &recipient_pub_key) {
+        Ok(sealed) => sealed,
+        Err(ShareErr::TooBig(_)) => {
+            status_label.set_label(
+                "Entries with content over 1 MiB can't be shared, send the file another way!",
+            );
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to seal the entry for the recipient!");
+            status_label.show();
+            return;
+        }
+    };
+
+    drop(ecies);
+    drop(vault);
+
+    let mut file_dialog =
+        dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+    file_dialog.set_title("Save the shared entry");
+    file_dialog.set_preset_file(&format!(
+        "{current_selected_entry}.{}",
+        sharing::SHARE_FILE_EXTENSION
+    ));
+    file_dialog.show();
+    let path = file_dialog.filename().to_string_lossy().to_string();
+    if path.is_empty() {
+        return;
+    }
+
+    match fs::write(&path, sealed) {
+        Ok(_) => {
+            status_label.set_label("The entry has been sealed, only the recipient can import it!");
+            status_label.show();
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to write the shared entry to the file!\n{err}");
+            status_label.set_label("Failed to write the shared entry to the file!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Share/Import Shared Entry menu item.
+/// Opens a file sealed to this vault and adds the entry in it,
+/// asking for another name if one with the same name exists.
+///
+pub fn import_shared_entry_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let mut file_dialog = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+    file_dialog.set_title("Select the shared entry");
+    file_dialog.show();
+    let path = file_dialog.filename().to_string_lossy().to_string();
+    if path.is_empty() {
+        return;
+    }
+
+    let sealed = match fs::read(&path) {
+        Ok(sealed) => sealed,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the shared entry file!\n{err}");
+            status_label.set_label("Failed to read the selected file!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let opened = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => sharing::open_sealed(&mut ecies, &vault, &sealed),
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let (mut value, sender_pub_key) = match opened {
+        Ok(opened) => opened,
+        Err(ShareErr::NotASharedEntry(_)) => {
+            status_label.set_label("The selected file is not a shared entry!");
+            status_label.show();
+            return;
+        }
+        Err(ShareErr::WrongRecipient(_)) => {
+            status_label.set_label("The entry wasn't shared with this vault, or it's corrupted!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("The shared entry can't be read!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // name the sender if it's in the keyring, otherwise show its fingerprint
+    let sender_contact = match (vault_db_arc.lock(), vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault_db), Ok(vault), Ok(mut ecies)) => {
+            match keyring::list_contacts(&vault_db, &mut ecies, &vault) {
+                Ok(contacts) => contacts
+                    .into_iter()
+                    .find(|contact| contact.pub_key == sender_pub_key),
+                Err(_) => None,
+            }
+        }
+        _ => None,
+    };
+    let sender = match sender_contact {
+        Some(contact) => format!("your contact {}", contact.name),
+        None => format!(
+            "an unknown vault with the fingerprint\n{}",
+            keyring::fingerprint(&sender_pub_key)
+        ),
+    };
+
+    match dialog::choice2_default(
+        &format!("Import the entry \"{}\" shared by {sender}?", value.name),
+        "No",
+        "Import",
+        "",
+    ) {
+        Some(1) => (),
+        _ => return,
+    };
+
+    let mut entries = match entries_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under entries_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // add the entry, under another name if the one it came with is taken
+    let mut name = value.name.to_owned();
+    loop {
+        if name.is_empty() || name == "ROOT" {
+            name = match dialog::input_default("Name for the imported entry:", "") {
+                Some(name) => name,
+                None => return,
+            };
+            continue;
+        }
+
+        match super::core::add_new_entry(
+            vault_db_arc.clone(),
+            vault_arc.clone(),
+            &name,
+            ecies_arc.clone(),
+        ) {
+            Ok(_) => break,
+            Err(NewEntryErr::AlreadyExists(_)) => {
+                name = match dialog::input_default(
+                    "An entry with this name already exists, name for the imported entry:",
+                    &(name + " (shared)"),
+                ) {
+                    Some(name) => name,
+                    None => return,
+                };
+            }
+            Err(_) => {
+                status_label.set_label("Failed to add the entry, try again or restart!");
+                status_label.show();
+                return;
+            }
+        };
+    }
+    value.name = name.to_owned();
+
+    // big contents are stored as a stream, like when they're selected from a file
+    let streamed = match vault_db_arc.lock() {
+        Ok(vault_db) => sharing::stream_big_content(&vault_db, &mut value),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            Err(ShareErr::DbInaccesible(0))
+        }
+    };
+
+    let stored = streamed.is_ok()
+        && super::core::set_entry_value(vault_db_arc.clone(), ecies_arc, vault_arc, &value).is_ok();
+
+    entries.add(&name);
+    entries.redraw();
+
+    if stored {
+        status_label.set_label(&format!("Imported the shared entry as {name}!"));
+    } else {
+        status_label.set_label("The entry was added, but failed to store its value!");
+    }
+    status_label.show();
+}
@@ -0,0 +1,192 @@
+use super::content_stream::{self, STREAM_THRESHOLD};
+use super::core::VaultValue;
+use super::keyring;
+use crate::encrypter::ecies::ECIES;
+use crate::hasher::blake3;
+use crate::utils::{db, Vault};
+use sled::Db;
+use zeroize::Zeroize;
+
+/// Marks a file holding an entry sealed to another vault, followed by the
+/// public key of the sending vault and the encrypted value.
+pub const SHARE_FILE_HEADER: &[u8] = b"ESSHARE\x01";
+
+/// The file extension used for sealed entries.
+pub const SHARE_FILE_EXTENSION: &str = "estash-share";
+
+#[derive(Debug)]
+pub enum ShareErr {
+    RecipientNotFound(u16),
+    DbInaccesible(u16),
+    EntryUnreadable(u16),
+    EncryptFailed(u16),
+    NotASharedEntry(u16),
+    WrongRecipient(u16),
+    TooBig(u16),
+}
+
+///
+/// Finds the public key of a recipient, a contact in the keyring of the vault
+/// comes first, then a vault on this machine or a key given directly as hex.
+///
+pub fn resolve_recipient(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    recipient: &str,
+) -> Result<[u8; 32], ShareErr> {
+    let recipient = recipient.trim();
+
+    match keyring::find_contact(vault_db, ecies, vault, recipient) {
+        Ok(contact) => match contact.pub_key_array() {
+            Some(pub_key) => Ok(pub_key),
+            None => Err(ShareErr::RecipientNotFound(0)),
+        },
+        Err(_) => find_recipient_key(recipient),
+    }
+}
+
+///
+/// Finds the public key to seal an entry to, either given directly as hex
+/// or as the name of a vault on this machine.
+///
+fn find_recipient_key(recipient: &str) -> Result<[u8; 32], ShareErr> {
+    let recipient = recipient.trim();
+
+    let mut key_bytes: [u8; 32] = [0; 32];
+    if hex::decode_to_slice(recipient, &mut key_bytes).is_ok() {
+        return Ok(key_bytes);
+    }
+
+    let estashdb = match db::EstashDb::new() {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            return Err(ShareErr::DbInaccesible(0));
+        }
+    };
+
+    match estashdb.vault_pub_key_db.get(blake3::hash_str(recipient)) {
+        Ok(Some(pub_key)) => match pub_key.to_vec().try_into() {
+            Ok(pub_key) => Ok(pub_key),
+            Err(_) => {
+                eprintln!("ERROR: The public key stored for the vault is not 32 bytes, the pub key db is corrupted!");
+                Err(ShareErr::RecipientNotFound(0))
+            }
+        },
+        Ok(None) => Err(ShareErr::RecipientNotFound(0)),
+        Err(err) => {
+            eprintln!("ERROR: There was an error trying to access the pub key db!\n{err}");
+            Err(ShareErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Encrypts the given entry value to the recipient's public key, with the vault's own
+/// private key, so the recipient can also tell which vault it came from.
+/// The sealed file is made whole in memory, so content kept as a stream, bigger
+/// than STREAM_THRESHOLD, is refused instead of being read back into memory.
+///
+pub fn seal_entry(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    mut value: VaultValue,
+    recipient_pub_key: &[u8; 32],
+) -> Result<Vec<u8>, ShareErr> {
+    if value.content_stream.is_some() {
+        eprintln!("ERROR: The content of the entry is too big to be shared!");
+        return Err(ShareErr::TooBig(0));
+    }
+
+    let mut value_bytes = match value.to_bytes(true) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            value.content.zeroize();
+            return Err(ShareErr::EntryUnreadable(0));
+        }
+    };
+    value.content.zeroize();
+
+    let cipher = ecies.encrypt_bytes_array(&value_bytes, &vault.priv_key, recipient_pub_key);
+    value_bytes.zeroize();
+    let cipher = match cipher {
+        Ok(cipher) => cipher,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encrypt the entry to the recipient's public key!\n{err}");
+            return Err(ShareErr::EncryptFailed(0));
+        }
+    };
+
+    let mut sealed = Vec::with_capacity(SHARE_FILE_HEADER.len() + 32 + cipher.len());
+    sealed.extend_from_slice(SHARE_FILE_HEADER);
+    sealed.extend_from_slice(&vault.pub_key);
+    sealed.extend_from_slice(&cipher);
+
+    Ok(sealed)
+}
+
+///
+/// Decrypts an entry sealed to this vault, returns the value and
+/// the public key of the vault that sealed it.
+///
+pub fn open_sealed(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    sealed: &[u8],
+) -> Result<(VaultValue, [u8; 32]), ShareErr> {
+    let body = match sealed.strip_prefix(SHARE_FILE_HEADER) {
+        Some(body) if body.len() > 32 => body,
+        _ => {
+            eprintln!("ERROR: The file is not an entry shared by estash!");
+            return Err(ShareErr::NotASharedEntry(0));
+        }
+    };
+    let (sender_pub_key, cipher) = body.split_at(32);
+    let sender_pub_key: [u8; 32] = match sender_pub_key.try_into() {
+        Ok(key) => key,
+        Err(_) => return Err(ShareErr::NotASharedEntry(0)),
+    };
+
+    let mut value_bytes = match ecies.decrypt_bytes_array(cipher, &vault.priv_key, &sender_pub_key)
+    {
+        Ok(plain) => plain,
+        Err(err) => {
+            eprintln!("ERROR: Failed to decrypt the shared entry, it wasn't sealed to this vault or it's corrupted!\n{err}");
+            return Err(ShareErr::WrongRecipient(0));
+        }
+    };
+
+    let value = VaultValue::from_bytes(&value_bytes);
+    value_bytes.zeroize();
+
+    match value {
+        Ok(value) => Ok((value, sender_pub_key)),
+        Err(_) => Err(ShareErr::EntryUnreadable(0)),
+    }
+}
+
+///
+/// Moves a big shared content out of the value into a content stream of this vault,
+/// the same way a big file selected from disk is stored.
+///
+pub fn stream_big_content(vault_db: &Db, value: &mut VaultValue) -> Result<(), ShareErr> {
+    if (value.content.len() as u64) <= STREAM_THRESHOLD {
+        return Ok(());
+    }
+
+    match content_stream::import_bytes(vault_db, &value.content) {
+        Ok(content_stream) => {
+            value.content.zeroize();
+            value.content = Vec::new();
+            value.content_stream = Some(content_stream);
+            Ok(())
+        }
+        Err(_) => {
+            eprintln!("ERROR: Failed to store the content of the shared entry as a stream!");
+            Err(ShareErr::DbInaccesible(0))
+        }
+    }
+}
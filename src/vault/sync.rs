@@ -0,0 +1,856 @@
+use super::content_stream::{self, ContentStream, CONTENT_CHUNKS_TREE};
+use super::core::{self, VaultValue, ENTRY_KEY_SIZE, ENTRY_NAMES_TREE};
+use super::dir_store::{self, DirStoreErr};
+use crate::encrypter::ecies::ECIES;
+use crate::hasher::blake3;
+use crate::utils::{db, Vault};
+use rand::prelude::*;
+use rand_hc::Hc128Rng;
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// The file marking a directory as a sync directory, with the public key of the vault.
+pub const SYNC_MARKER_FILE: &str = "estash-sync";
+const SYNC_MARKER_HEADER: &str = "EStash sync directory v1";
+
+/// A folder per entry, named by the hex of its keyed hash, with a file per machine
+/// named by its replica id. Every machine only writes its own files, so tools
+/// like Syncthing never see two machines writing the same file.
+const ENTRIES_DIR: &str = "entries";
+
+/// One file per encrypted chunk of the content streams, they never change once written.
+const CHUNKS_DIR: &str = "chunks";
+
+/// The name of the tree in the vaults db of this machine holding the replica id of
+/// every vault, under the hash of the vault name.
+const REPLICA_IDS_TREE: &str = "replica_ids";
+
+/// The name of the tree in the vault db holding, for every entry, the vector clock
+/// and the hash of the value as they were at the last sync.
+pub const SYNC_STATE_TREE: &str = "sync_state";
+
+/// The name of the tree in the vault db holding the entries edited on more than
+/// one machine at once, with every concurrent version, until one is picked.
+pub const SYNC_CONFLICTS_TREE: &str = "sync_conflicts";
+
+/// A counter of edits for every machine (replica id) that edited the entry.
+pub type VectorClock = BTreeMap<u64, u64>;
+
+#[derive(Serialize, Deserialize)]
+struct EntryState {
+    clock: VectorClock,
+    // empty when the entry was deleted
+    #[serde(with = "serde_bytes")]
+    value_hash: Vec<u8>,
+}
+
+///
+/// A version of an entry as a machine last saw it. The value is encrypted
+/// the same way it's stored in the vault db.
+///
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EntryVersion {
+    pub replica_id: u64,
+    pub clock: VectorClock,
+    pub deleted: bool,
+    #[serde(with = "serde_bytes")]
+    pub value: Vec<u8>,
+}
+
+///
+/// An entry edited on more than one machine without one seeing the others' edit.
+///
+pub struct Conflict {
+    pub key: Vec<u8>,
+    pub versions: Vec<EntryVersion>,
+}
+
+///
+/// What a sync did, pending entries are waiting for chunks that haven't arrived yet.
+///
+pub struct SyncSummary {
+    pub sent: usize,
+    pub received: usize,
+    pub conflicts: usize,
+    pub pending: usize,
+}
+
+#[derive(Debug)]
+pub enum SyncErr {
+    DbInaccesible(u16),
+    DirInaccesible(u16),
+    NotASyncDir(u16),
+    OtherVault(u16),
+    Corrupted(u16),
+    EncryptFailed(u16),
+    Pending(u16),
+}
+
+///
+/// Exchanges the entry changes with the sync directory. Local changes since the last sync
+/// get a new tick of this machine's clock and are written for the other machines, then
+/// every entry takes the newest version. Versions that happened concurrently (neither
+/// clock includes the other) and differ are never overwritten, they're kept as a conflict
+/// until one of them is picked with resolve_conflict.
+///
+pub fn sync_with_directory(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    dir: &str,
+) -> Result<SyncSummary, SyncErr> {
+    let dir = Path::new(dir);
+    dir_store::claim_directory(dir, SYNC_MARKER_FILE, SYNC_MARKER_HEADER, vault)
+        .map_err(store_err)?;
+
+    let replica_id = own_replica_id(vault)?;
+    let state_tree = open_tree(vault_db, SYNC_STATE_TREE)?;
+    let conflicts_tree = open_tree(vault_db, SYNC_CONFLICTS_TREE)?;
+    let entries_dir = dir.join(ENTRIES_DIR);
+    let chunks_dir = dir.join(CHUNKS_DIR);
+
+    let mut summary = SyncSummary {
+        sent: 0,
+        received: 0,
+        conflicts: 0,
+        pending: 0,
+    };
+
+    // the chunks go first, a version is only usable once its chunks are there
+    send_chunks(vault_db, &chunks_dir)?;
+
+    // every entry known here, at the last sync or by another machine
+    let mut keys: BTreeSet<Vec<u8>> = BTreeSet::new();
+    for tree in [vault_db as &sled::Tree, &state_tree] {
+        for key in tree.iter().keys() {
+            match key {
+                Ok(key) if key.len() == ENTRY_KEY_SIZE => {
+                    keys.insert(key.to_vec());
+                }
+                Ok(_) => (),
+                Err(err) => {
+                    eprintln!("ERROR: Failed to read from the vault db!\n{err}");
+                    return Err(SyncErr::DbInaccesible(0));
+                }
+            };
+        }
+    }
+    for dir_name in dir_store::list_files(&entries_dir).map_err(store_err)? {
+        match hex::decode(&dir_name) {
+            Ok(key) if key.len() == ENTRY_KEY_SIZE => {
+                keys.insert(key);
+            }
+            _ => (),
+        };
+    }
+
+    for key in keys {
+        let key_dir = entries_dir.join(hex::encode(&key));
+        let local = local_version(vault_db, &state_tree, replica_id, &key)?;
+
+        let mut versions: Vec<EntryVersion> = local.iter().cloned().collect();
+        versions.extend(read_other_versions(ecies, vault, &key_dir, replica_id)?);
+        let mut latest = latest_versions(ecies, vault, versions);
+
+        let chosen = if latest.len() == 1 {
+            let winner = latest.remove(0);
+            if winner.replica_id != replica_id {
+                if !apply_version(vault_db, ecies, vault, &chunks_dir, &key, &winner)? {
+                    summary.pending += 1;
+                    continue;
+                }
+                summary.received += 1;
+            }
+            if let Err(err) = conflicts_tree.remove(&key) {
+                eprintln!("ERROR: Failed to remove a solved conflict from the vault db!\n{err}");
+                return Err(SyncErr::DbInaccesible(0));
+            }
+            winner
+        } else {
+            // the chunks of every version have to be here for picking it later
+            let mut chunks_received = true;
+            for version in &latest {
+                if !receive_version_chunks(vault_db, ecies, vault, &chunks_dir, version)? {
+                    chunks_received = false;
+                }
+            }
+            if !chunks_received {
+                summary.pending += 1;
+                continue;
+            }
+            store_conflict(&conflicts_tree, &key, &latest)?;
+            summary.conflicts += 1;
+
+            // the local version stays as it is until the conflict is resolved
+            match local {
+                Some(local) => local,
+                None => continue,
+            }
+        };
+
+        let value_hash = match vault_db.get(&key) {
+            Ok(Some(value)) => blake3::hash_bytes(&value).to_vec(),
+            Ok(None) => Vec::new(),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read an entry from the vault db!\n{err}");
+                return Err(SyncErr::DbInaccesible(0));
+            }
+        };
+        store_state(&state_tree, &key, &chosen.clock, value_hash)?;
+
+        // let the other machines know what this one has now
+        let own_version = EntryVersion {
+            replica_id,
+            clock: chosen.clock.to_owned(),
+            deleted: chosen.deleted,
+            value: chosen.value.to_owned(),
+        };
+        let own_path = key_dir.join(replica_file_name(replica_id));
+        let already_sent = match read_version(ecies, vault, &own_path) {
+            Some(sent) => sent.clock == own_version.clock,
+            None => false,
+        };
+        if !already_sent {
+            write_version(ecies, vault, &key_dir, &own_path, &own_version)?;
+            if chosen.replica_id == replica_id {
+                summary.sent += 1;
+            }
+        }
+    }
+
+    match vault_db.flush() {
+        Ok(_) => Ok(summary),
+        Err(err) => {
+            eprintln!("ERROR: Failed to flush the vault db after syncing!\n{err}");
+            Err(SyncErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Lists the entries in conflict, with every concurrent version of them.
+///
+pub fn list_conflicts(vault_db: &Db) -> Result<Vec<Conflict>, SyncErr> {
+    let conflicts_tree = open_tree(vault_db, SYNC_CONFLICTS_TREE)?;
+
+    let mut conflicts = Vec::new();
+    for conflict in conflicts_tree.iter() {
+        let (key, value) = match conflict {
+            Ok(kv) => kv,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a conflict from the vault db!\n{err}");
+                return Err(SyncErr::DbInaccesible(0));
+            }
+        };
+        match rmp_serde::from_slice(&value) {
+            Ok(versions) => conflicts.push(Conflict {
+                key: key.to_vec(),
+                versions,
+            }),
+            Err(err) => {
+                eprintln!("ERROR: A conflict is not stored in estash's format!\n{err}");
+                return Err(SyncErr::Corrupted(0));
+            }
+        };
+    }
+
+    Ok(conflicts)
+}
+
+///
+/// A short description of a version for picking between them.
+///
+pub fn describe_version(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    version: &EntryVersion,
+    replica_id: u64,
+) -> String {
+    let origin = if version.replica_id == replica_id {
+        String::from("this machine")
+    } else {
+        format!("machine {}", replica_file_name(version.replica_id))
+    };
+    if version.deleted {
+        return format!("Deleted on {origin}");
+    }
+
+    let mut value = match dir_store::decrypt_entry(ecies, vault, &version.value) {
+        Ok(value) => value,
+        Err(_) => return format!("Unreadable version from {origin}"),
+    };
+    let size = match &value.content_stream {
+        Some(content_stream) => content_stream.size,
+        None => value.content.len() as u64,
+    };
+    let preview: String = String::from_utf8_lossy(&value.content)
+        .chars()
+        .take(200)
+        .collect();
+    value.content.zeroize();
+
+    format!(
+        "Edited on {origin}\n  Name: {}\n  Install path: {}\n  Notes: {}\n  Content ({size} bytes): {preview}",
+        value.name, value.install_path, value.notes
+    )
+}
+
+///
+/// Keeps the picked version of an entry in conflict. Its clock becomes newer than every
+/// version in the conflict, so the next sync sends it to the other machines instead of
+/// bringing the conflict back.
+///
+pub fn resolve_conflict(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    conflict: &Conflict,
+    picked: usize,
+) -> Result<(), SyncErr> {
+    let replica_id = own_replica_id(vault)?;
+    let state_tree = open_tree(vault_db, SYNC_STATE_TREE)?;
+    let conflicts_tree = open_tree(vault_db, SYNC_CONFLICTS_TREE)?;
+    let picked_version = match conflict.versions.get(picked) {
+        Some(version) => version,
+        None => return Err(SyncErr::Corrupted(0)),
+    };
+
+    // the chunks were already brought into the vault db when the conflict was found
+    if picked_version.replica_id != replica_id
+        && !apply_version(
+            vault_db,
+            ecies,
+            vault,
+            Path::new(""),
+            &conflict.key,
+            picked_version,
+        )?
+    {
+        return Err(SyncErr::Pending(0));
+    }
+
+    let mut clock = VectorClock::new();
+    if let Some(local) = local_version(vault_db, &state_tree, replica_id, &conflict.key)? {
+        clock = merge_clocks(&clock, &local.clock);
+    }
+    for version in &conflict.versions {
+        clock = merge_clocks(&clock, &version.clock);
+    }
+    *clock.entry(replica_id).or_insert(0) += 1;
+
+    let value_hash = match vault_db.get(&conflict.key) {
+        Ok(Some(value)) => blake3::hash_bytes(&value).to_vec(),
+        Ok(None) => Vec::new(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read an entry from the vault db!\n{err}");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    };
+    store_state(&state_tree, &conflict.key, &clock, value_hash)?;
+
+    match conflicts_tree.remove(&conflict.key) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to remove the resolved conflict from the vault db!\n{err}");
+            Err(SyncErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// The replica id of this machine for the vault, used in the vector clocks and for
+/// telling its versions apart. It's random, made on first use, and kept in the vaults
+/// db of this machine instead of the vault, so a copy of the vault db on another
+/// machine always gets an id of its own there.
+///
+pub fn own_replica_id(vault: &Vault) -> Result<u64, SyncErr> {
+    let estashdb = match db::EstashDb::new() {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    };
+    let replica_ids_tree = match estashdb.vault_db.open_tree(REPLICA_IDS_TREE) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the replica ids tree of the vaults db!\n{err}");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    };
+    let vault_name_hashed = blake3::hash_str(&vault.vault_name);
+
+    match replica_ids_tree.get(vault_name_hashed) {
+        Ok(Some(value)) if value.len() == 8 => {
+            let mut id_bytes: [u8; 8] = [0; 8];
+            id_bytes.copy_from_slice(&value);
+            return Ok(u64::from_be_bytes(id_bytes));
+        }
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the replica id of the vault!\n{err}");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    };
+
+    let replica_id = Hc128Rng::from_entropy().next_u64();
+    match replica_ids_tree
+        .insert(vault_name_hashed, &replica_id.to_be_bytes())
+        .and_then(|_| replica_ids_tree.flush())
+    {
+        Ok(_) => Ok(replica_id),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the replica id of the vault!\n{err}");
+            Err(SyncErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// The version of the entry in this vault. If it changed since the last sync
+/// this machine's counter in the clock goes up.
+///
+fn local_version(
+    vault_db: &Db,
+    state_tree: &sled::Tree,
+    replica_id: u64,
+    key: &[u8],
+) -> Result<Option<EntryVersion>, SyncErr> {
+    let value = match vault_db.get(key) {
+        Ok(value) => value.map(|value| value.to_vec()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read an entry from the vault db!\n{err}");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    };
+    let state: Option<EntryState> = match state_tree.get(key) {
+        Ok(Some(state)) => {
+            match rmp_serde::from_slice(&state) {
+                Ok(state) => Some(state),
+                Err(err) => {
+                    eprintln!("ERROR: The sync state of an entry is not stored in estash's format!\n{err}");
+                    return Err(SyncErr::Corrupted(0));
+                }
+            }
+        }
+        Ok(None) => None,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the sync state of an entry!\n{err}");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    };
+    if value.is_none() && state.is_none() {
+        return Ok(None);
+    }
+
+    let value_hash = match &value {
+        Some(value) => blake3::hash_bytes(value).to_vec(),
+        None => Vec::new(),
+    };
+    let (mut clock, changed) = match state {
+        Some(state) => (state.clock, state.value_hash != value_hash),
+        None => (VectorClock::new(), true),
+    };
+    if changed {
+        *clock.entry(replica_id).or_insert(0) += 1;
+    }
+
+    Ok(Some(EntryVersion {
+        replica_id,
+        clock,
+        deleted: value.is_none(),
+        value: value.unwrap_or_default(),
+    }))
+}
+
+///
+/// Drops the versions some other version already includes, and merges
+/// the concurrent ones that ended up with the same value.
+/// The local version comes first, so its value is kept on ties.
+///
+fn latest_versions(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    versions: Vec<EntryVersion>,
+) -> Vec<EntryVersion> {
+    let mut latest: Vec<EntryVersion> = Vec::new();
+    for (index, version) in versions.iter().enumerate() {
+        let outdated = versions.iter().enumerate().any(|(other_index, other)| {
+            other_index != index
+                && includes(&other.clock, &version.clock)
+                && (other.clock != version.clock || other_index < index)
+        });
+        if outdated {
+            continue;
+        }
+
+        match latest
+            .iter_mut()
+            .find(|kept| same_value(ecies, vault, kept, version))
+        {
+            Some(kept) => kept.clock = merge_clocks(&kept.clock, &version.clock),
+            None => latest.push(version.to_owned()),
+        };
+    }
+
+    latest
+}
+
+///
+/// Whether clock a has seen everything clock b has.
+///
+fn includes(a: &VectorClock, b: &VectorClock) -> bool {
+    b.iter()
+        .all(|(replica_id, count)| a.get(replica_id).copied().unwrap_or(0) >= *count)
+}
+
+fn merge_clocks(a: &VectorClock, b: &VectorClock) -> VectorClock {
+    let mut merged = a.to_owned();
+    for (replica_id, count) in b {
+        let merged_count = merged.entry(*replica_id).or_insert(0);
+        *merged_count = (*merged_count).max(*count);
+    }
+
+    merged
+}
+
+///
+/// Whether two versions hold the same entry, the values are encrypted with
+/// different nonces so they are compared decrypted.
+///
+fn same_value(ecies: &mut ECIES, vault: &Vault, a: &EntryVersion, b: &EntryVersion) -> bool {
+    if a.deleted || b.deleted {
+        return a.deleted == b.deleted;
+    }
+    if a.value == b.value {
+        return true;
+    }
+
+    let (mut a_value, mut b_value) = match (
+        dir_store::decrypt_entry(ecies, vault, &a.value),
+        dir_store::decrypt_entry(ecies, vault, &b.value),
+    ) {
+        (Ok(a_value), Ok(b_value)) => (a_value, b_value),
+        _ => return false,
+    };
+    let stream_id = |value: &VaultValue| value.content_stream.as_ref().map(|s| s.id.to_owned());
+    let same = a_value.name == b_value.name
+        && a_value.install_path == b_value.install_path
+        && a_value.notes == b_value.notes
+        && a_value.content == b_value.content
+        && stream_id(&a_value) == stream_id(&b_value);
+    a_value.content.zeroize();
+    b_value.content.zeroize();
+
+    same
+}
+
+///
+/// Puts the version in the vault db. Returns false, without changing anything,
+/// if the chunks of its content haven't arrived in the sync directory yet.
+///
+fn apply_version(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    chunks_dir: &Path,
+    key: &[u8],
+    version: &EntryVersion,
+) -> Result<bool, SyncErr> {
+    // the stream of the value being replaced, removed once nothing points to it
+    let old_stream = match vault_db.get(key) {
+        Ok(Some(old_value)) => match dir_store::decrypt_entry(ecies, vault, &old_value) {
+            Ok(mut old_value) => old_value.content_stream.take(),
+            Err(_) => None,
+        },
+        Ok(None) => None,
+        Err(err) => {
+            eprintln!("ERROR: Failed to read an entry from the vault db!\n{err}");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    };
+
+    let mut new_stream_id = None;
+    if version.deleted {
+        if core::remove_entry(vault_db, key).is_err() {
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    } else {
+        let mut value =
+            dir_store::decrypt_entry(ecies, vault, &version.value).map_err(store_err)?;
+        value.content.zeroize();
+        if core::entry_key(vault, &value.name) != key {
+            eprintln!("ERROR: A synced entry is not stored under the hash of its name, the sync directory was tampered with!");
+            return Err(SyncErr::Corrupted(0));
+        }
+        if let Some(content_stream) = &value.content_stream {
+            if !receive_chunks(vault_db, chunks_dir, content_stream)? {
+                return Ok(false);
+            }
+            new_stream_id = Some(content_stream.id.to_owned());
+        }
+
+        let name_encrypted =
+            match ecies.encrypt_bytes_array(value.name.as_bytes(), &vault.priv_key, &vault.pub_key)
+            {
+                Ok(cipher) => cipher,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to encrypt the name of a synced entry!\n{err}");
+                    return Err(SyncErr::EncryptFailed(0));
+                }
+            };
+        let entry_names_tree = open_tree(vault_db, ENTRY_NAMES_TREE)?;
+        let stored = vault_db.insert(key, version.value.to_owned()).is_ok()
+            && entry_names_tree.insert(key, name_encrypted).is_ok();
+        if !stored {
+            eprintln!("ERROR: Failed to store a synced entry in the vault db!");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    }
+
+    if let Some(old_stream) = old_stream {
+        if new_stream_id.as_ref() != Some(&old_stream.id)
+            && content_stream::remove_stream(vault_db, &old_stream).is_err()
+        {
+            eprintln!(
+                "ERROR: Failed to remove the old content chunks, they will stay unused in the db!"
+            );
+        }
+    }
+
+    Ok(true)
+}
+
+///
+/// Brings the chunks of the version's content into the vault db, if it has any.
+///
+fn receive_version_chunks(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    chunks_dir: &Path,
+    version: &EntryVersion,
+) -> Result<bool, SyncErr> {
+    if version.deleted {
+        return Ok(true);
+    }
+
+    let mut value = dir_store::decrypt_entry(ecies, vault, &version.value).map_err(store_err)?;
+    value.content.zeroize();
+    match &value.content_stream {
+        Some(content_stream) => receive_chunks(vault_db, chunks_dir, content_stream),
+        None => Ok(true),
+    }
+}
+
+///
+/// Copies the chunks of the stream missing from the vault db from the sync directory.
+/// Returns false if some of them aren't in the sync directory yet.
+///
+fn receive_chunks(
+    vault_db: &Db,
+    chunks_dir: &Path,
+    content_stream: &ContentStream,
+) -> Result<bool, SyncErr> {
+    let chunks_tree = open_tree(vault_db, CONTENT_CHUNKS_TREE)?;
+
+    for index in 0..content_stream.chunks {
+        let chunk_key = content_stream.chunk_key(index);
+        match chunks_tree.contains_key(&chunk_key) {
+            Ok(true) => continue,
+            Ok(false) => (),
+            Err(err) => {
+                eprintln!("ERROR: Failed to read from the vault db!\n{err}");
+                return Err(SyncErr::DbInaccesible(0));
+            }
+        };
+
+        let chunk = match fs::read(chunks_dir.join(hex::encode(&chunk_key))) {
+            Ok(chunk) => chunk,
+            Err(_) => return Ok(false),
+        };
+        if let Err(err) = chunks_tree.insert(chunk_key, chunk) {
+            eprintln!("ERROR: Failed to store a synced chunk in the vault db!\n{err}");
+            return Err(SyncErr::DbInaccesible(0));
+        }
+    }
+
+    Ok(true)
+}
+
+///
+/// Writes the chunks of the vault missing from the sync directory.
+///
+fn send_chunks(vault_db: &Db, chunks_dir: &Path) -> Result<(), SyncErr> {
+    if let Err(err) = fs::create_dir_all(chunks_dir) {
+        eprintln!("ERROR: Failed to create the chunks folder of the sync directory!\n{err}");
+        return Err(SyncErr::DirInaccesible(0));
+    }
+    let chunks_tree = open_tree(vault_db, CONTENT_CHUNKS_TREE)?;
+
+    for chunk in chunks_tree.iter() {
+        let (key, value) = match chunk {
+            Ok(kv) => kv,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a chunk from the vault db!\n{err}");
+                return Err(SyncErr::DbInaccesible(0));
+            }
+        };
+        let path = chunks_dir.join(hex::encode(&key));
+        if !path.exists() {
+            dir_store::write_if_changed(&path, &value).map_err(store_err)?;
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Reads the versions other machines wrote for an entry. Files that can't be
+/// read are skipped, they may still be being copied by the sync tool.
+///
+fn read_other_versions(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    key_dir: &Path,
+    replica_id: u64,
+) -> Result<Vec<EntryVersion>, SyncErr> {
+    let mut versions = Vec::new();
+    for file_name in dir_store::list_files(key_dir).map_err(store_err)? {
+        if file_name.len() != 16 || file_name == replica_file_name(replica_id) {
+            continue;
+        }
+        match read_version(ecies, vault, &key_dir.join(&file_name)) {
+            Some(version) if replica_file_name(version.replica_id) == file_name => {
+                versions.push(version)
+            }
+            _ => eprintln!("ERROR: Skipping the unreadable synced version {file_name}!"),
+        };
+    }
+
+    Ok(versions)
+}
+
+fn read_version(ecies: &mut ECIES, vault: &Vault, path: &Path) -> Option<EntryVersion> {
+    let version_encrypted = fs::read(path).ok()?;
+    let mut version_bytes = ecies
+        .decrypt_bytes_array(&version_encrypted, &vault.priv_key, &vault.pub_key)
+        .ok()?;
+    let version = rmp_serde::from_slice(&version_bytes).ok();
+    version_bytes.zeroize();
+
+    version
+}
+
+fn write_version(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    key_dir: &Path,
+    path: &Path,
+    version: &EntryVersion,
+) -> Result<(), SyncErr> {
+    if let Err(err) = fs::create_dir_all(key_dir) {
+        eprintln!("ERROR: Failed to create an entry folder of the sync directory!\n{err}");
+        return Err(SyncErr::DirInaccesible(0));
+    }
+
+    let version_bytes = match rmp_serde::to_vec_named(version) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode a synced version!\n{err}");
+            return Err(SyncErr::EncryptFailed(0));
+        }
+    };
+    let version_encrypted =
+        match ecies.encrypt_bytes_array(&version_bytes, &vault.priv_key, &vault.pub_key) {
+            Ok(cipher) => cipher,
+            Err(err) => {
+                eprintln!("ERROR: Failed to encrypt a synced version!\n{err}");
+                return Err(SyncErr::EncryptFailed(0));
+            }
+        };
+
+    dir_store::write_if_changed(path, &version_encrypted).map_err(store_err)?;
+
+    Ok(())
+}
+
+fn store_state(
+    state_tree: &sled::Tree,
+    key: &[u8],
+    clock: &VectorClock,
+    value_hash: Vec<u8>,
+) -> Result<(), SyncErr> {
+    let state = EntryState {
+        clock: clock.to_owned(),
+        value_hash,
+    };
+    let state_bytes = match rmp_serde::to_vec_named(&state) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the sync state of an entry!\n{err}");
+            return Err(SyncErr::Corrupted(0));
+        }
+    };
+
+    match state_tree.insert(key, state_bytes) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the sync state of an entry!\n{err}");
+            Err(SyncErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn store_conflict(
+    conflicts_tree: &sled::Tree,
+    key: &[u8],
+    versions: &[EntryVersion],
+) -> Result<(), SyncErr> {
+    let versions_bytes = match rmp_serde::to_vec_named(versions) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode a conflict!\n{err}");
+            return Err(SyncErr::Corrupted(0));
+        }
+    };
+
+    match conflicts_tree.insert(key, versions_bytes) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store a conflict in the vault db!\n{err}");
+            Err(SyncErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn replica_file_name(replica_id: u64) -> String {
+    format!("{replica_id:016x}")
+}
+
+fn store_err(err: DirStoreErr) -> SyncErr {
+    match err {
+        DirStoreErr::DbInaccesible(_) => SyncErr::DbInaccesible(0),
+        DirStoreErr::DirInaccesible(_) => SyncErr::DirInaccesible(0),
+        DirStoreErr::NotAStore(_) => SyncErr::NotASyncDir(0),
+        DirStoreErr::OtherVault(_) => SyncErr::OtherVault(0),
+        DirStoreErr::Corrupted(_) | DirStoreErr::Incomplete(_) => SyncErr::Corrupted(0),
+        DirStoreErr::EncryptFailed(_) => SyncErr::EncryptFailed(0),
+    }
+}
+
+fn open_tree(vault_db: &Db, name: &str) -> Result<sled::Tree, SyncErr> {
+    match vault_db.open_tree(name) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the {name} tree of the vault db!\n{err}");
+            Err(SyncErr::DbInaccesible(0))
+        }
+    }
+}
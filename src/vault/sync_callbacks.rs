@@ -0,0 +1,236 @@
+use super::dir_store_callbacks::choose_directory;
+use super::settings;
+use super::sync::{self, SyncErr};
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Sync/Sync with Directory menu item.
+/// Exchanges the entry changes with a directory shared between machines,
+/// and lists the entries again with the ones received.
+///
+pub fn sync_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let dir = match choose_directory(
+        "Select the sync directory",
+        settings::get_sync_directory(&vault_db),
+    ) {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    let synced = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => {
+            sync::sync_with_directory(&vault_db, &mut ecies, &vault, &dir)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let summary = match synced {
+        Ok(summary) => summary,
+        Err(SyncErr::NotASyncDir(_)) => {
+            status_label.set_label("Pick an empty directory or a sync directory of this vault!");
+            status_label.show();
+            return;
+        }
+        Err(SyncErr::OtherVault(_)) => {
+            status_label.set_label("That directory is used by another vault!");
+            status_label.show();
+            return;
+        }
+        Err(SyncErr::Corrupted(_)) => {
+            status_label.set_label("The sync directory has files that don't belong to this vault!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to sync, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let _ = settings::set_sync_directory(&vault_db, &dir);
+
+    // drop arc ref
+    drop(vault_db);
+
+    if summary.received > 0 {
+        match (entries_arc.lock(), vault_arc.lock()) {
+            (Ok(mut entries), Ok(vault)) => {
+                super::core::reload_entries(&vault, vault_db_arc, ecies_arc, &mut entries);
+            }
+            _ => {
+                eprintln!("ERROR: Failed to get value under entries_arc or vault_arc ARC!");
+            }
+        };
+    }
+
+    let mut status = format!(
+        "Synced, {} entries sent, {} received",
+        summary.sent, summary.received
+    );
+    if summary.pending > 0 {
+        status += &format!(", {} waiting for files", summary.pending);
+    }
+    if summary.conflicts > 0 {
+        status += &format!(
+            ", {} in conflict, see Sync > Resolve Conflicts",
+            summary.conflicts
+        );
+    }
+    status_label.set_label(&(status + "!"));
+    status_label.show();
+}
+
+///
+/// Callback for the Sync/Resolve Conflicts menu item.
+/// Shows every version of each entry in conflict and keeps the one picked.
+///
+pub fn resolve_conflicts_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    entries_arc: Arc<Mutex<tree::Tree>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut ecies = match ecies_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let (conflicts, replica_id) = match (
+        sync::list_conflicts(&vault_db),
+        sync::own_replica_id(&vault),
+    ) {
+        (Ok(conflicts), Ok(replica_id)) => (conflicts, replica_id),
+        _ => {
+            status_label.set_label("Failed to read the conflicts, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    if conflicts.is_empty() {
+        status_label.set_label("There are no conflicts to resolve!");
+        status_label.show();
+        return;
+    }
+
+    let mut resolved = 0;
+    for conflict in &conflicts {
+        let mut text = String::from("This entry was changed on more than one machine:\n\n");
+        for (index, version) in conflict.versions.iter().enumerate() {
+            text += &format!(
+                "Version {}: {}\n\n",
+                index + 1,
+                sync::describe_version(&mut ecies, &vault, version, replica_id)
+            );
+        }
+        super::report_window::show("Sync Conflict", &text);
+
+        let picked = match dialog::input_default(
+            &format!(
+                "Keep which version (1-{})? Leave it empty to skip this entry.",
+                conflict.versions.len()
+            ),
+            "",
+        ) {
+            Some(picked) => picked,
+            None => break,
+        };
+        let picked = match picked.trim().parse::<usize>() {
+            Ok(picked) if picked >= 1 && picked <= conflict.versions.len() => picked - 1,
+            _ => continue,
+        };
+
+        match sync::resolve_conflict(&vault_db, &mut ecies, &vault, conflict, picked) {
+            Ok(_) => resolved += 1,
+            Err(_) => {
+                status_label.set_label("Failed to keep the picked version, sync and try again!");
+                status_label.show();
+                return;
+            }
+        };
+    }
+
+    // drop arc ref
+    drop(vault_db);
+    drop(ecies);
+
+    if resolved > 0 {
+        match entries_arc.lock() {
+            Ok(mut entries) => {
+                super::core::reload_entries(&vault, vault_db_arc, ecies_arc, &mut entries);
+            }
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under entries_arc ARC!\n{err}");
+            }
+        };
+    }
+
+    status_label.set_label(&format!(
+        "Resolved {resolved} of {} conflicts, sync again to send them!",
+        conflicts.len()
+    ));
+    status_label.show();
+}
@@ -0,0 +1,926 @@
+use super::audit;
+use super::content_stream;
+use super::installs;
+use super::integrity;
+use super::keyring;
+use super::settings;
+use super::templates;
+use crate::encrypter::ecies::ECIES;
+use crate::hasher::blake3;
+use crate::utils::{self, db, secure::SecretKey, Vault};
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use zeroize::{Zeroize, Zeroizing};
+
+/// The name of the tree in the vault db of a team vault holding the members,
+/// each with the private key of the team vault wrapped to their public key.
+pub const MEMBERS_TREE: &str = "members";
+
+/// Context for deriving the key used to hash the member public keys from the team private key.
+const MEMBER_KEY_CONTEXT: &str = "estash 2022-10 team member keys v1";
+
+///
+/// The value stored in the vaults db for a team vault,
+/// it has no password so nothing is stored in the priv key db for it.
+///
+#[derive(Serialize, Deserialize)]
+struct TeamVaultDbValue {
+    id: u64,
+    #[serde(default)]
+    keyfile: bool,
+    #[serde(default)]
+    team: bool,
+}
+
+///
+/// A member of a team vault, stored encrypted with the team keys.
+///
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Member {
+    pub name: String,
+    #[serde(with = "serde_bytes")]
+    pub pub_key: Vec<u8>,
+}
+
+///
+/// How a member is stored. The private key of the team vault is encrypted
+/// to the member's public key with a one time key pair, so only the member's
+/// own vault can unwrap it, and that's all that can be read before the team
+/// vault is unlocked. The member itself is encrypted with the team keys.
+///
+#[derive(Serialize, Deserialize)]
+struct MemberRecord {
+    #[serde(with = "serde_bytes")]
+    ephemeral_pub_key: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    wrapped_key: Vec<u8>,
+    #[serde(default, with = "serde_bytes")]
+    member_encrypted: Vec<u8>,
+    // older versions stored the member unencrypted, under its public key
+    #[serde(default, skip_serializing)]
+    name: String,
+    #[serde(default, skip_serializing, with = "serde_bytes")]
+    pub_key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum TeamErr {
+    DbInaccesible(u16),
+    AlreadyExists(u16),
+    NotFound(u16),
+    NotAMember(u16),
+    LastMember(u16),
+    Corrupted(u16),
+    EncryptFailed(u16),
+}
+
+///
+/// Creates a new team vault with its own key pair and the given vault as its first member.
+///
+pub fn create_team_vault(
+    team_name: &str,
+    member_name: &str,
+    member_pub_key: &[u8; 32],
+    ecies: &mut ECIES,
+    is_windows: bool,
+) -> Result<(), TeamErr> {
+    let estashdb = open_estashdb()?;
+    let team_name_hashed = blake3::hash_str(team_name);
+
+    match estashdb.vault_db.contains_key(team_name_hashed) {
+        Ok(false) => (),
+        Ok(true) => return Err(TeamErr::AlreadyExists(0)),
+        Err(err) => {
+            eprintln!("ERROR: Failed to access vaults db!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+
+    let new_id = next_vault_id(&estashdb)?;
+    let (pub_key, mut priv_key) = ecies.gen_key_pair();
+
+    // the members are stored first, a team vault without them could never be opened
+    let team_vault_path = utils::get_vaults_root_path(is_windows) + &new_id.to_string();
+    let stored = match sled::open(&team_vault_path) {
+        Ok(team_vault_db) => {
+            let result = store_member(
+                &team_vault_db,
+                ecies,
+                member_name,
+                member_pub_key,
+                &priv_key,
+                &pub_key,
+            );
+            let _ = team_vault_db.flush();
+            result
+        }
+        Err(err) => {
+            eprintln!("ERROR: There was an error creating the team vault!\n{err}");
+            Err(TeamErr::DbInaccesible(0))
+        }
+    };
+    priv_key.zeroize();
+    if let Err(err) = stored {
+        let _ = fs::remove_dir_all(&team_vault_path);
+        return Err(err);
+    }
+
+    match register_team_vault(&estashdb, &team_name_hashed, new_id, &pub_key) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_dir_all(&team_vault_path);
+            Err(err)
+        }
+    }
+}
+
+///
+/// Opens a team vault with the key of one of its members,
+/// returns the team vault unlocked.
+///
+pub fn open_team_vault(
+    team_name: &str,
+    member_vault: &Vault,
+    ecies: &mut ECIES,
+    is_windows: bool,
+) -> Result<Vault, TeamErr> {
+    let estashdb = open_estashdb()?;
+    let team_name_hashed = blake3::hash_str(team_name);
+
+    let team_value: TeamVaultDbValue = match estashdb.vault_db.get(team_name_hashed) {
+        Ok(Some(value)) => match serde_json::from_slice(&value) {
+            Ok(value) => value,
+            Err(err) => {
+                eprintln!("ERROR: Value under vault is not stored in estash's format, meaning vaults db is corrupted!\n{err}");
+                return Err(TeamErr::Corrupted(0));
+            }
+        },
+        Ok(None) => return Err(TeamErr::NotFound(0)),
+        Err(err) => {
+            eprintln!("ERROR: There was an error accessing vaults db!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+    if !team_value.team {
+        return Err(TeamErr::NotFound(0));
+    }
+
+    let pub_key: [u8; 32] = match estashdb.vault_pub_key_db.get(team_name_hashed) {
+        Ok(Some(pub_key)) => match pub_key.to_vec().try_into() {
+            Ok(pub_key) => pub_key,
+            Err(_) => {
+                eprintln!("ERROR: The team vault public key is not 32 bytes, the pub key db is corrupted!");
+                return Err(TeamErr::Corrupted(0));
+            }
+        },
+        Ok(None) => {
+            eprintln!("ERROR: There is no public key stored for the team vault, the pub key db is corrupted!");
+            return Err(TeamErr::Corrupted(0));
+        }
+        Err(err) => {
+            eprintln!("ERROR: There was an error trying to access the pub key db!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+
+    // drop the db so other windows can open it
+    drop(estashdb);
+
+    let team_vault_db =
+        match sled::open(utils::get_vaults_root_path(is_windows) + &team_value.id.to_string()) {
+            Ok(db) => db,
+            Err(err) => {
+                eprintln!("ERROR: Failed to open the team vault, maybe it's already open!\n{err}");
+                return Err(TeamErr::DbInaccesible(0));
+            }
+        };
+    let priv_key = match unwrap_team_key(&team_vault_db, ecies, member_vault)? {
+        Some(priv_key) => priv_key,
+        None => return Err(TeamErr::NotAMember(0)),
+    };
+
+    let team_vault = Vault {
+        vault_name: team_name.to_string(),
+        id: team_value.id,
+        priv_key,
+        pub_key,
+        has_keyfile: false,
+        is_team: true,
+    };
+    seal_legacy_members(&team_vault_db, ecies, &team_vault);
+
+    Ok(team_vault)
+}
+
+///
+/// Lists the members of the team vault, sorted by their name.
+///
+pub fn list_members(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+) -> Result<Vec<Member>, TeamErr> {
+    let mut members = Vec::new();
+    for (_, record) in read_member_records(vault_db)? {
+        members.push(open_member_record(ecies, vault, record)?);
+    }
+
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(members)
+}
+
+///
+/// Gives another vault access to the team vault by wrapping the team key to its public key.
+///
+pub fn add_member(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    name: &str,
+    pub_key: &[u8; 32],
+) -> Result<(), TeamErr> {
+    let members = list_members(vault_db, ecies, vault)?;
+    if members
+        .iter()
+        .any(|member| member.name == name || member.pub_key == pub_key)
+    {
+        return Err(TeamErr::AlreadyExists(0));
+    }
+
+    store_member(
+        vault_db,
+        ecies,
+        name,
+        pub_key,
+        &vault.priv_key,
+        &vault.pub_key,
+    )
+}
+
+///
+/// Removes a member and re-keys the team vault, so the removed member's copy of the
+/// old key opens nothing new. Everything is re-encrypted with a new key pair into a
+/// new vault directory, which replaces the old one only once it's complete.
+/// Returns the team vault with its new keys, the old one can't be used anymore.
+///
+pub fn remove_member(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    member_pub_key: &[u8; 32],
+    is_windows: bool,
+) -> Result<Vault, TeamErr> {
+    let members = list_members(vault_db, ecies, vault)?;
+    if !members
+        .iter()
+        .any(|member| member.pub_key == member_pub_key)
+    {
+        return Err(TeamErr::NotFound(0));
+    }
+    if members.len() == 1 {
+        return Err(TeamErr::LastMember(0));
+    }
+
+    // everything has to be readable, or it would be lost with the old key
+    let report = match integrity::verify_vault(vault, vault_db, ecies, is_windows) {
+        Ok(report) => report,
+        Err(_) => return Err(TeamErr::DbInaccesible(0)),
+    };
+    if report.undecryptable_names > 0
+        || !report.undecryptable_values.is_empty()
+        || !report.invalid_values.is_empty()
+    {
+        eprintln!("ERROR: The team vault has unreadable entries, verify it before re-keying!");
+        return Err(TeamErr::Corrupted(0));
+    }
+    let contacts = match keyring::list_contacts(vault_db, ecies, vault) {
+        Ok(contacts) => contacts,
+        Err(_) => return Err(TeamErr::Corrupted(0)),
+    };
+
+    let estashdb = open_estashdb()?;
+    let new_id = next_vault_id(&estashdb)?;
+    let (new_pub_key, mut new_priv_key) = ecies.gen_key_pair();
+    let new_vault = Vault {
+        vault_name: vault.vault_name.to_owned(),
+        id: new_id,
+        priv_key: SecretKey::new(&mut new_priv_key),
+        pub_key: new_pub_key,
+        has_keyfile: false,
+        is_team: true,
+    };
+    let vaults_root_path = utils::get_vaults_root_path(is_windows);
+    let new_vault_path = vaults_root_path.to_owned() + &new_id.to_string();
+
+    let new_vault = match copy_rekeyed(
+        vault_db,
+        &new_vault_path,
+        new_vault,
+        report.healthy_entries,
+        contacts,
+        &members,
+        member_pub_key,
+    ) {
+        Ok(new_vault) => new_vault,
+        Err(err) => {
+            let _ = fs::remove_dir_all(&new_vault_path);
+            return Err(err);
+        }
+    };
+    if let Err(err) = copy_audit_log(
+        vault_db,
+        &estashdb,
+        ecies,
+        vault,
+        &new_vault_path,
+        &new_vault,
+    ) {
+        let _ = fs::remove_dir_all(&new_vault_path);
+        return Err(err);
+    }
+    if let Err(err) = copy_template_variables(vault_db, ecies, vault, &new_vault_path, &new_vault) {
+        let _ = fs::remove_dir_all(&new_vault_path);
+        return Err(err);
+    }
+    if let Err(err) = copy_installs(vault_db, ecies, vault, &new_vault_path, &new_vault) {
+        let _ = fs::remove_dir_all(&new_vault_path);
+        return Err(err);
+    }
+
+    // switch the team vault over to the new directory and keys
+    let team_name_hashed = blake3::hash_str(&vault.vault_name);
+    if let Err(err) = register_team_vault(&estashdb, &team_name_hashed, new_id, &new_pub_key) {
+        let _ = fs::remove_dir_all(&new_vault_path);
+        return Err(err);
+    }
+
+    // the old directory is still open in this window, a failure just leaves it orphaned
+    if let Err(err) = fs::remove_dir_all(vaults_root_path + &vault.id.to_string()) {
+        eprintln!("ERROR: Failed to remove the directory of the old team vault, it's left orphaned!\n{err}");
+    }
+
+    Ok(new_vault)
+}
+
+///
+/// Copies every entry, contact and setting into a new vault db, encrypted with the keys
+/// of the new vault, and wraps the new key to every member except the removed one.
+/// Content streams are re-encrypted too, their keys were readable with the old key.
+/// Gives the new vault back once it's done, it can't be copied.
+///
+fn copy_rekeyed(
+    vault_db: &Db,
+    new_vault_path: &str,
+    new_vault: Vault,
+    entries: Vec<(String, super::core::VaultValue)>,
+    contacts: Vec<keyring::Contact>,
+    members: &[Member],
+    removed_pub_key: &[u8; 32],
+) -> Result<Vault, TeamErr> {
+    let new_vault_db = match sled::open(new_vault_path) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("ERROR: There was an error creating the re-keyed team vault!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+    let mut ecies = ECIES::new();
+
+    if settings::set_compression_enabled(&new_vault_db, settings::is_compression_enabled(vault_db))
+        .is_err()
+    {
+        return Err(TeamErr::DbInaccesible(0));
+    }
+    if settings::set_path_variables(&new_vault_db, &settings::get_path_variables(vault_db)).is_err()
+    {
+        return Err(TeamErr::DbInaccesible(0));
+    }
+
+    for contact in contacts {
+        let pub_key = match contact.pub_key_array() {
+            Some(pub_key) => pub_key,
+            None => continue,
+        };
+        if keyring::add_contact(
+            &new_vault_db,
+            &mut ecies,
+            &new_vault,
+            &contact.name,
+            &pub_key,
+        )
+        .is_err()
+        {
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    }
+
+    for member in members {
+        if member.pub_key == removed_pub_key {
+            continue;
+        }
+        let pub_key: [u8; 32] = match member.pub_key.to_owned().try_into() {
+            Ok(pub_key) => pub_key,
+            Err(_) => return Err(TeamErr::Corrupted(0)),
+        };
+        store_member(
+            &new_vault_db,
+            &mut ecies,
+            &member.name,
+            &pub_key,
+            &new_vault.priv_key,
+            &new_vault.pub_key,
+        )?;
+    }
+
+    let new_vault_db_arc = Arc::new(Mutex::new(new_vault_db));
+    let new_vault_arc = Arc::new(Mutex::new(new_vault));
+    let ecies_arc = Arc::new(Mutex::new(ecies));
+
+    for (name, mut value) in entries {
+        if let Some(stream) = value.content_stream.take() {
+            let new_vault_db = match new_vault_db_arc.lock() {
+                Ok(db) => db,
+                Err(err) => {
+                    eprintln!("ERROR: Failed to get value under new_vault_db ARC!\n{err}");
+                    return Err(TeamErr::DbInaccesible(0));
+                }
+            };
+            let mut content = match content_stream::read_to_vec(vault_db, &stream) {
+                Ok(content) => content,
+                Err(_) => return Err(TeamErr::Corrupted(0)),
+            };
+            let new_stream = content_stream::import_bytes(&new_vault_db, &content);
+            content.zeroize();
+            value.content_stream = match new_stream {
+                Ok(new_stream) => Some(new_stream),
+                Err(_) => return Err(TeamErr::DbInaccesible(0)),
+            };
+        }
+
+        if super::core::add_new_entry(
+            new_vault_db_arc.clone(),
+            new_vault_arc.clone(),
+            &name,
+            ecies_arc.clone(),
+        )
+        .is_err()
+        {
+            eprintln!("ERROR: Failed to add the entry {name} to the re-keyed team vault!");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+        value.name = name;
+        if super::core::set_entry_value(
+            new_vault_db_arc.clone(),
+            ecies_arc.clone(),
+            new_vault_arc.clone(),
+            &value,
+        )
+        .is_err()
+        {
+            eprintln!(
+                "ERROR: Failed to store the entry {} in the re-keyed team vault!",
+                value.name
+            );
+            return Err(TeamErr::DbInaccesible(0));
+        }
+        value.content.zeroize();
+    }
+
+    let new_vault_db = match new_vault_db_arc.lock() {
+        Ok(new_vault_db) => new_vault_db,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under new_vault_db ARC!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+    if let Err(err) = new_vault_db.flush() {
+        eprintln!("ERROR: Failed to flush the re-keyed team vault!\n{err}");
+        return Err(TeamErr::DbInaccesible(0));
+    }
+
+    match Arc::try_unwrap(new_vault_arc) {
+        Ok(new_vault) => match new_vault.into_inner() {
+            Ok(new_vault) => Ok(new_vault),
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under new_vault ARC!\n{err}");
+                Err(TeamErr::DbInaccesible(0))
+            }
+        },
+        Err(_) => {
+            eprintln!("ERROR: The re-keyed team vault is still in use!");
+            Err(TeamErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Copies the audit log into the re-keyed team vault, encrypted with its keys.
+///
+fn copy_audit_log(
+    vault_db: &Db,
+    estashdb: &db::EstashDb,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    new_vault_path: &str,
+    new_vault: &Vault,
+) -> Result<(), TeamErr> {
+    let log = match audit::read_log(vault_db, estashdb, ecies, vault) {
+        Ok(log) => log,
+        Err(_) => return Err(TeamErr::DbInaccesible(0)),
+    };
+    let new_vault_db = match sled::open(new_vault_path) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("ERROR: There was an error opening the re-keyed team vault!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+
+    match audit::copy_log(&new_vault_db, estashdb, ecies, new_vault, &log) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(TeamErr::DbInaccesible(0)),
+    }
+}
+
+///
+/// Copies the template variables of this machine into the re-keyed team vault,
+/// encrypted with its keys.
+///
+fn copy_template_variables(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    new_vault_path: &str,
+    new_vault: &Vault,
+) -> Result<(), TeamErr> {
+    let mut variables = match templates::get_variables(vault_db, ecies, vault) {
+        Ok(variables) => variables,
+        Err(_) => return Err(TeamErr::Corrupted(0)),
+    };
+    if variables.is_empty() {
+        return Ok(());
+    }
+    let new_vault_db = match sled::open(new_vault_path) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("ERROR: There was an error opening the re-keyed team vault!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+
+    let copied = templates::set_variables(&new_vault_db, ecies, new_vault, &variables);
+    for value in variables.values_mut() {
+        value.zeroize();
+    }
+    match copied {
+        Ok(_) => Ok(()),
+        Err(_) => Err(TeamErr::DbInaccesible(0)),
+    }
+}
+
+///
+/// Copies the files installed on this machine into the re-keyed team vault,
+/// encrypted with its keys.
+///
+fn copy_installs(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    new_vault_path: &str,
+    new_vault: &Vault,
+) -> Result<(), TeamErr> {
+    let installed_files = match installs::list_installs(vault_db, ecies, vault) {
+        Ok(installed_files) => installed_files,
+        Err(_) => return Err(TeamErr::Corrupted(0)),
+    };
+    if installed_files.is_empty() {
+        return Ok(());
+    }
+    let new_vault_db = match sled::open(new_vault_path) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("ERROR: There was an error opening the re-keyed team vault!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+
+    for installed_file in &installed_files {
+        if installs::record_install(&new_vault_db, ecies, new_vault, installed_file).is_err() {
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Wraps the team key to the member's public key and stores the member,
+/// encrypted with the team keys, under a keyed hash of its public key.
+///
+fn store_member(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    name: &str,
+    member_pub_key: &[u8; 32],
+    team_priv_key: &[u8; 32],
+    team_pub_key: &[u8; 32],
+) -> Result<(), TeamErr> {
+    // a one time key pair, so wrapping doesn't need any member's private key
+    let (ephemeral_pub_key, mut ephemeral_priv_key) = ecies.gen_key_pair();
+    let wrapped_key = ecies.encrypt_bytes_array(team_priv_key, &ephemeral_priv_key, member_pub_key);
+    ephemeral_priv_key.zeroize();
+    let wrapped_key = match wrapped_key {
+        Ok(cipher) => cipher,
+        Err(err) => {
+            eprintln!("ERROR: Failed to wrap the team key to the member's public key!\n{err}");
+            return Err(TeamErr::EncryptFailed(0));
+        }
+    };
+
+    let member = Member {
+        name: name.to_owned(),
+        pub_key: member_pub_key.to_vec(),
+    };
+    let member_encrypted = encrypt_member(ecies, &member, team_priv_key, team_pub_key)?;
+    let record = MemberRecord {
+        ephemeral_pub_key: ephemeral_pub_key.to_vec(),
+        wrapped_key,
+        member_encrypted,
+        name: String::new(),
+        pub_key: Vec::new(),
+    };
+
+    insert_member_record(
+        vault_db,
+        &member_key(team_priv_key, member_pub_key),
+        &record,
+    )
+}
+
+///
+/// Finds the member record the vault can unwrap and returns the team private key in it, in its locked page,
+/// None if the vault is not a member. The records don't say whose they are until the
+/// team vault is unlocked, so each one is tried.
+///
+fn unwrap_team_key(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    member_vault: &Vault,
+) -> Result<Option<SecretKey>, TeamErr> {
+    for (_, record) in read_member_records(vault_db)? {
+        let ephemeral_pub_key: [u8; 32] = match record.ephemeral_pub_key.try_into() {
+            Ok(key) => key,
+            Err(_) => return Err(TeamErr::Corrupted(0)),
+        };
+        let priv_key = match ecies.decrypt_bytes_array(
+            &record.wrapped_key,
+            &member_vault.priv_key,
+            &ephemeral_pub_key,
+        ) {
+            Ok(key) => Zeroizing::new(key),
+            Err(_) => continue,
+        };
+
+        return match SecretKey::from_slice(&priv_key) {
+            Some(key) => Ok(Some(key)),
+            None => {
+                eprintln!(
+                    "ERROR: The team vault private key is not 32 bytes, the team vault is corrupted!"
+                );
+                Err(TeamErr::Corrupted(0))
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+///
+/// Encrypts the members stored by older versions, unencrypted under their public key.
+/// A member that fails is left as it is and tried again the next time.
+///
+fn seal_legacy_members(vault_db: &Db, ecies: &mut ECIES, vault: &Vault) {
+    let records = match read_member_records(vault_db) {
+        Ok(records) => records,
+        Err(_) => return,
+    };
+    let members_tree = match open_members_tree(vault_db) {
+        Ok(tree) => tree,
+        Err(_) => return,
+    };
+
+    for (legacy_key, mut record) in records {
+        if !record.member_encrypted.is_empty() {
+            continue;
+        }
+        let member = Member {
+            name: std::mem::take(&mut record.name),
+            pub_key: std::mem::take(&mut record.pub_key),
+        };
+        record.member_encrypted =
+            match encrypt_member(ecies, &member, &vault.priv_key, &vault.pub_key) {
+                Ok(member_encrypted) => member_encrypted,
+                Err(_) => continue,
+            };
+
+        let moved = insert_member_record(
+            vault_db,
+            &member_key(&vault.priv_key, &member.pub_key),
+            &record,
+        )
+        .is_ok()
+            && members_tree.remove(legacy_key).is_ok();
+        if !moved {
+            eprintln!("ERROR: Failed to encrypt the member {}, it will be tried again the next time the team vault is opened!", member.name);
+        }
+    }
+}
+
+fn encrypt_member(
+    ecies: &mut ECIES,
+    member: &Member,
+    team_priv_key: &[u8; 32],
+    team_pub_key: &[u8; 32],
+) -> Result<Vec<u8>, TeamErr> {
+    let member_bytes = match rmp_serde::to_vec_named(member) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the member!\n{err}");
+            return Err(TeamErr::Corrupted(0));
+        }
+    };
+
+    match ecies.encrypt_bytes_array(&member_bytes, team_priv_key, team_pub_key) {
+        Ok(cipher) => Ok(cipher),
+        Err(err) => {
+            eprintln!("ERROR: Failed to encrypt the member!\n{err}");
+            Err(TeamErr::EncryptFailed(0))
+        }
+    }
+}
+
+///
+/// The member in a record, decrypted with the team keys.
+///
+fn open_member_record(
+    ecies: &mut ECIES,
+    vault: &Vault,
+    record: MemberRecord,
+) -> Result<Member, TeamErr> {
+    if record.member_encrypted.is_empty() {
+        return Ok(Member {
+            name: record.name,
+            pub_key: record.pub_key,
+        });
+    }
+
+    let member_bytes = match ecies.decrypt_bytes_array(
+        &record.member_encrypted,
+        &vault.priv_key,
+        &vault.pub_key,
+    ) {
+        Ok(plain) => plain,
+        Err(err) => {
+            eprintln!("ERROR: Failed to decrypt a member, the vault db may be corrupted!\n{err}");
+            return Err(TeamErr::Corrupted(0));
+        }
+    };
+    match rmp_serde::from_slice::<Member>(&member_bytes) {
+        Ok(member) => Ok(member),
+        Err(err) => {
+            eprintln!("ERROR: A member is not stored in estash's format!\n{err}");
+            Err(TeamErr::Corrupted(0))
+        }
+    }
+}
+
+fn read_member_records(vault_db: &Db) -> Result<Vec<(sled::IVec, MemberRecord)>, TeamErr> {
+    let members_tree = open_members_tree(vault_db)?;
+
+    let mut records = Vec::new();
+    for member in members_tree.iter() {
+        let (key, value) = match member {
+            Ok(kv) => kv,
+            Err(err) => {
+                eprintln!("ERROR: Failed to read a member from the vault db!\n{err}");
+                return Err(TeamErr::DbInaccesible(0));
+            }
+        };
+        match rmp_serde::from_slice::<MemberRecord>(&value) {
+            Ok(record) => records.push((key, record)),
+            Err(err) => {
+                eprintln!("ERROR: A member is not stored in estash's format!\n{err}");
+                return Err(TeamErr::Corrupted(0));
+            }
+        };
+    }
+
+    Ok(records)
+}
+
+fn insert_member_record(vault_db: &Db, key: &[u8], record: &MemberRecord) -> Result<(), TeamErr> {
+    let record_bytes = match rmp_serde::to_vec_named(record) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the member!\n{err}");
+            return Err(TeamErr::Corrupted(0));
+        }
+    };
+
+    let members_tree = open_members_tree(vault_db)?;
+    match members_tree.insert(key, record_bytes) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the member in the vault db!\n{err}");
+            Err(TeamErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// The key a member is stored under, a keyed hash of its public key with a key derived
+/// from the team private key, so the vault db doesn't show who the members are.
+///
+fn member_key(team_priv_key: &[u8; 32], member_pub_key: &[u8]) -> Vec<u8> {
+    let mut hash_key = ::blake3::derive_key(MEMBER_KEY_CONTEXT, team_priv_key);
+    let key = ::blake3::keyed_hash(&hash_key, member_pub_key);
+    hash_key.zeroize();
+
+    key.as_bytes().to_vec()
+}
+
+///
+/// Stores (or replaces) the team vault in the vaults db and its public key in the pub key db.
+///
+fn register_team_vault(
+    estashdb: &db::EstashDb,
+    team_name_hashed: &[u8; 32],
+    id: u64,
+    pub_key: &[u8; 32],
+) -> Result<(), TeamErr> {
+    let team_value = TeamVaultDbValue {
+        id,
+        keyfile: false,
+        team: true,
+    };
+    let team_value_string = match serde_json::to_string(&team_value) {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("ERROR: Failed to convert the team vault value to json!\n{err}");
+            return Err(TeamErr::Corrupted(0));
+        }
+    };
+
+    match estashdb
+        .vault_db
+        .insert(team_name_hashed, team_value_string.as_bytes())
+    {
+        Ok(_) => (),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the team vault!\n{err}");
+            return Err(TeamErr::DbInaccesible(0));
+        }
+    };
+    match estashdb.vault_pub_key_db.insert(team_name_hashed, pub_key) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the public key of the team vault!\n{err}");
+            Err(TeamErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn next_vault_id(estashdb: &db::EstashDb) -> Result<u64, TeamErr> {
+    match estashdb.next_vault_id() {
+        Ok(id) => Ok(id),
+        Err(err) => {
+            eprintln!("ERROR: There was an error accessing vaults db!\n{err}");
+            Err(TeamErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn open_estashdb() -> Result<db::EstashDb, TeamErr> {
+    match db::EstashDb::new() {
+        Ok(db) => Ok(db),
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            Err(TeamErr::DbInaccesible(0))
+        }
+    }
+}
+
+fn open_members_tree(vault_db: &Db) -> Result<sled::Tree, TeamErr> {
+    match vault_db.open_tree(MEMBERS_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the members tree of the vault db!\n{err}");
+            Err(TeamErr::DbInaccesible(0))
+        }
+    }
+}
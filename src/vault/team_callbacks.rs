@@ -0,0 +1,416 @@
+use super::sharing::{self, ShareErr};
+use super::team::{self, TeamErr};
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, window::DoubleWindow, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+
+///
+/// Callback for the Team/Create Team Vault menu item.
+/// Creates a team vault with the opened vault as its first member.
+///
+pub fn create_team_vault_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    is_windows: bool,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut ecies = match ecies_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let team_name = match dialog::input_default("Name for the team vault:", "") {
+        Some(name) => name.trim().to_owned(),
+        None => return,
+    };
+    if team_name.is_empty() {
+        status_label.set_label("The team vault needs a name!");
+        status_label.show();
+        return;
+    }
+    let member_name = match dialog::input_default("Your name in the team:", &vault.vault_name) {
+        Some(name) => name.trim().to_owned(),
+        None => return,
+    };
+
+    match team::create_team_vault(
+        &team_name,
+        &member_name,
+        &vault.pub_key,
+        &mut ecies,
+        is_windows,
+    ) {
+        Ok(_) => {
+            status_label.set_label(&format!(
+                "Created the team vault {team_name}, open it from Team > Open Team Vault!"
+            ));
+            status_label.show();
+        }
+        Err(TeamErr::AlreadyExists(_)) => {
+            status_label.set_label("A vault with this name already exists!");
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to create the team vault, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Team/Open Team Vault menu item.
+/// Unwraps the key of a team vault the opened vault is a member of,
+/// and opens the team vault in its own window.
+///
+pub fn open_team_vault_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    wind: &mut DoubleWindow,
+    is_windows: bool,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let team_name = match dialog::input_default("Name of the team vault:", "") {
+        Some(name) => name.trim().to_owned(),
+        None => return,
+    };
+
+    let team_vault = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => {
+            team::open_team_vault(&team_name, &vault, &mut ecies, is_windows)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let team_vault = match team_vault {
+        Ok(team_vault) => team_vault,
+        Err(TeamErr::NotFound(_)) => {
+            status_label.set_label("There is no team vault with that name!");
+            status_label.show();
+            return;
+        }
+        Err(TeamErr::NotAMember(_)) => {
+            status_label.set_label("This vault is not a member of that team vault!");
+            status_label.show();
+            return;
+        }
+        Err(TeamErr::DbInaccesible(_)) => {
+            status_label.set_label("Failed to open the team vault, maybe it's already open!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("The team vault is corrupted!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let mut team_wind = super::window::create(is_windows, team_vault, 0, wind);
+    team_wind.set_label(&format!("Team Vault - {team_name}"));
+    team_wind.show();
+}
+
+///
+/// Callback for the Team/List Members menu item.
+/// Shows every member of the team vault with the fingerprint of their key.
+///
+pub fn list_members_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let members = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => team::list_members(&vault_db, &mut ecies, &vault),
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let members = match members {
+        Ok(members) => members,
+        Err(_) => {
+            status_label.set_label("Failed to read the members, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let mut text = String::from("Members of the team vault:\n\n");
+    for member in &members {
+        let fingerprint = match member.pub_key.to_owned().try_into() {
+            Ok(pub_key) => super::keyring::fingerprint(&pub_key),
+            Err(_) => "invalid key".to_string(),
+        };
+        text += &format!("{}\n  Fingerprint: {fingerprint}\n", member.name);
+    }
+
+    super::report_window::show("Team Members", &text);
+}
+
+///
+/// Callback for the Team/Add Member menu item.
+/// Wraps the key of the team vault to the public key of another vault.
+///
+pub fn add_member_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let new_member = match dialog::input_default(
+        "New member (a contact in the keyring, the name of a vault on this machine, or a public key in hex):",
+        "",
+    ) {
+        Some(new_member) => new_member.trim().to_owned(),
+        None => return,
+    };
+    let name = match dialog::input_default("Name of the member in the team:", &new_member) {
+        Some(name) => name.trim().to_owned(),
+        None => return,
+    };
+
+    // get references from arcs
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut ecies = match ecies_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let pub_key = match sharing::resolve_recipient(&vault_db, &mut ecies, &vault, &new_member) {
+        Ok(pub_key) => pub_key,
+        Err(ShareErr::RecipientNotFound(_)) => {
+            status_label.set_label(
+                "There is no contact or vault with that name, and it's not a public key!",
+            );
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to access the vaults db, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    match team::add_member(&vault_db, &mut ecies, &vault, &name, &pub_key) {
+        Ok(_) => {
+            status_label.set_label(&format!("Added {name} to the team!"));
+            status_label.show();
+        }
+        Err(TeamErr::AlreadyExists(_)) => {
+            status_label.set_label("A member with this name or key is already in the team!");
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to add the member, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Team/Remove Member menu item.
+/// Removes a member and re-keys the team vault, the window is then
+/// replaced by one using the new keys.
+///
+pub fn remove_member_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    wind: &mut DoubleWindow,
+    is_windows: bool,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let name = match dialog::input_default("Name of the member to remove:", "") {
+        Some(name) => name.trim().to_owned(),
+        None => return,
+    };
+
+    // get references from arcs
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let member = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => team::list_members(&vault_db, &mut ecies, &vault),
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let member = match member {
+        Ok(members) => members.into_iter().find(|member| member.name == name),
+        Err(_) => {
+            status_label.set_label("Failed to read the members, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+    let member_pub_key: [u8; 32] = match member.map(|member| member.pub_key.try_into()) {
+        Some(Ok(pub_key)) => pub_key,
+        _ => {
+            status_label.set_label("There is no member with that name!");
+            status_label.show();
+            return;
+        }
+    };
+
+    match dialog::choice2_default(
+        &format!("Remove {name} from the team? Every entry will be re-encrypted with a new key."),
+        "No",
+        "Remove",
+        "",
+    ) {
+        Some(1) => (),
+        _ => return,
+    };
+
+    let new_vault = match (vault_arc.lock(), ecies_arc.lock()) {
+        (Ok(vault), Ok(mut ecies)) => {
+            team::remove_member(&vault_db, &mut ecies, &vault, &member_pub_key, is_windows)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_arc or ecies_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let new_vault = match new_vault {
+        Ok(new_vault) => new_vault,
+        Err(TeamErr::LastMember(_)) => {
+            status_label.set_label("The last member of the team can't be removed!");
+            status_label.show();
+            return;
+        }
+        Err(TeamErr::Corrupted(_)) => {
+            status_label.set_label("The team vault has unreadable entries, verify it first!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to re-key the team vault, nothing was changed!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // drop arc ref
+    drop(vault_db);
+    drop(vault_db_arc);
+    drop(status_label);
+    drop(status_label_arc);
+
+    // this window still has the old keys, replace it
+    let team_name = new_vault.vault_name.to_owned();
+    let mut team_wind = super::window::create(is_windows, new_vault, 0, wind);
+    team_wind.set_label(&format!("Team Vault - {team_name}"));
+    team_wind.show();
+    wind.hide();
+}
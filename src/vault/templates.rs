@@ -0,0 +1,235 @@
+use super::path_vars;
+use crate::encrypter::ecies::ECIES;
+use crate::utils::{self, Vault};
+use sled::Db;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+/// The name of the tree in the vault db holding the template variables of this machine.
+pub const TEMPLATE_VARIABLES_TREE: &str = "template_variables";
+
+/// The key the variables are stored under, encrypted, as one map.
+const TEMPLATE_VARIABLES_KEY: &str = "variables";
+
+/// The variables every machine has, with what they hold.
+pub const BUILTIN_TEMPLATE_VARIABLES: [(&str, &str); 4] = [
+    ("hostname", "the name of this machine"),
+    ("user", "the name of the user"),
+    ("os", "the operating system, like linux, macos or windows"),
+    ("home", "the home folder of the user"),
+];
+
+///
+/// A template with its placeholders replaced by the variables of this machine.
+///
+pub struct Rendered {
+    pub text: String,
+    // names of the placeholders with no variable here, left as they were
+    pub unknown: Vec<String>,
+}
+
+impl Drop for Rendered {
+    fn drop(&mut self) {
+        self.text.zeroize();
+    }
+}
+
+#[derive(Debug)]
+pub enum TemplateErr {
+    PoisonErr(u16),
+    DbInaccesible(u16),
+    Corrupted(u16),
+    NotText(u16),
+}
+
+///
+/// Replaces every {{ name }} in the template by the variable with that name,
+/// the spaces around the name are optional. {{{{ gives a literal {{.
+/// Anything else between braces is left as it is.
+///
+pub fn render(template: &str, variables: &BTreeMap<String, String>) -> Rendered {
+    let mut rendered = Rendered {
+        text: String::with_capacity(template.len()),
+        unknown: Vec::new(),
+    };
+
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.text.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        if let Some(after_escape) = after.strip_prefix("{{") {
+            rendered.text.push_str("{{");
+            rest = after_escape;
+            continue;
+        }
+
+        let placeholder = after
+            .find("}}")
+            .map(|end| (after[..end].trim(), end))
+            .filter(|(name, _)| path_vars::is_variable_name(name));
+        match placeholder {
+            Some((name, end)) => {
+                match variables.get(name) {
+                    Some(value) => rendered.text.push_str(value),
+                    None => {
+                        rendered.text.push_str(&rest[start..start + 2 + end + 2]);
+                        if !rendered.unknown.iter().any(|unknown| unknown == name) {
+                            rendered.unknown.push(name.to_owned());
+                        }
+                    }
+                };
+                rest = &after[end + 2..];
+            }
+            None => {
+                rendered.text.push_str("{{");
+                rest = after;
+            }
+        };
+    }
+    rendered.text.push_str(rest);
+
+    rendered
+}
+
+///
+/// The variables every machine has, see BUILTIN_TEMPLATE_VARIABLES.
+///
+pub fn builtin_variables() -> BTreeMap<String, String> {
+    let empty = BTreeMap::new();
+    let mut variables = BTreeMap::new();
+    variables.insert("hostname".to_string(), utils::hostname());
+    variables.insert(
+        "user".to_string(),
+        path_vars::lookup("USER", &empty).unwrap_or_default(),
+    );
+    variables.insert("os".to_string(), std::env::consts::OS.to_string());
+    variables.insert(
+        "home".to_string(),
+        path_vars::lookup("HOME", &empty).unwrap_or_default(),
+    );
+
+    variables
+}
+
+///
+/// The variables set on this machine, decrypted. They aren't synced or stored
+/// into a directory snapshot, since they're what differs between machines.
+///
+pub fn get_variables(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+) -> Result<BTreeMap<String, String>, TemplateErr> {
+    let variables_tree = open_variables_tree(vault_db)?;
+    let variables_encrypted = match variables_tree.get(TEMPLATE_VARIABLES_KEY) {
+        Ok(Some(value)) => value,
+        Ok(None) => return Ok(BTreeMap::new()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to read the template variables from the vault db!\n{err}");
+            return Err(TemplateErr::DbInaccesible(0));
+        }
+    };
+
+    let mut variables_bytes =
+        match ecies.decrypt_bytes_array(&variables_encrypted, &vault.priv_key, &vault.pub_key) {
+            Ok(plain) => plain,
+            Err(err) => {
+                eprintln!("ERROR: Failed to decrypt the template variables!\n{err}");
+                return Err(TemplateErr::Corrupted(0));
+            }
+        };
+    let variables = rmp_serde::from_slice(&variables_bytes);
+    variables_bytes.zeroize();
+    match variables {
+        Ok(variables) => Ok(variables),
+        Err(err) => {
+            eprintln!("ERROR: The template variables are not stored in estash's format!\n{err}");
+            Err(TemplateErr::Corrupted(0))
+        }
+    }
+}
+
+///
+/// Replaces the variables set on this machine, encrypted with the vault keys.
+///
+pub fn set_variables(
+    vault_db: &Db,
+    ecies: &mut ECIES,
+    vault: &Vault,
+    variables: &BTreeMap<String, String>,
+) -> Result<(), TemplateErr> {
+    let mut variables_bytes = match rmp_serde::to_vec_named(variables) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encode the template variables!\n{err}");
+            return Err(TemplateErr::Corrupted(0));
+        }
+    };
+    let variables_encrypted =
+        ecies.encrypt_bytes_array(&variables_bytes, &vault.priv_key, &vault.pub_key);
+    variables_bytes.zeroize();
+    let variables_encrypted = match variables_encrypted {
+        Ok(cipher) => cipher,
+        Err(err) => {
+            eprintln!("ERROR: Failed to encrypt the template variables!\n{err}");
+            return Err(TemplateErr::Corrupted(0));
+        }
+    };
+
+    let variables_tree = open_variables_tree(vault_db)?;
+    match variables_tree.insert(TEMPLATE_VARIABLES_KEY, variables_encrypted) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            eprintln!("ERROR: Failed to store the template variables in the vault db!\n{err}");
+            Err(TemplateErr::DbInaccesible(0))
+        }
+    }
+}
+
+///
+/// Renders the template with the variables of this machine, the ones set in the
+/// vault winning over the builtin ones.
+///
+pub fn render_with_vault(
+    vault_db_arc: &Arc<Mutex<Db>>,
+    ecies_arc: &Arc<Mutex<ECIES>>,
+    vault_arc: &Arc<Mutex<Vault>>,
+    template: &[u8],
+) -> Result<Rendered, TemplateErr> {
+    let template = match std::str::from_utf8(template) {
+        Ok(template) => template,
+        Err(_) => {
+            eprintln!("ERROR: The template is not utf8 text!");
+            return Err(TemplateErr::NotText(0));
+        }
+    };
+
+    let custom = match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => get_variables(&vault_db, &mut ecies, &vault)?,
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC!");
+            return Err(TemplateErr::PoisonErr(0));
+        }
+    };
+    let mut variables = builtin_variables();
+    variables.extend(custom);
+
+    let rendered = render(template, &variables);
+    for value in variables.values_mut() {
+        value.zeroize();
+    }
+
+    Ok(rendered)
+}
+
+fn open_variables_tree(vault_db: &Db) -> Result<sled::Tree, TemplateErr> {
+    match vault_db.open_tree(TEMPLATE_VARIABLES_TREE) {
+        Ok(tree) => Ok(tree),
+        Err(err) => {
+            eprintln!("ERROR: Failed to open the template variables tree of the vault db!\n{err}");
+            Err(TemplateErr::DbInaccesible(0))
+        }
+    }
+}
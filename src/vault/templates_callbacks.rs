@@ -0,0 +1,383 @@
+use super::audit::{self, AuditAction};
+use super::core::{UpdateContentErr, VaultValueErr};
+use super::path_vars;
+use super::report_window;
+use super::templates::{self, TemplateErr, BUILTIN_TEMPLATE_VARIABLES};
+use crate::utils::Vault;
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+///
+/// Callback for the Templates/Toggle Template menu item.
+/// Marks the selected entry as a template, rendered on install, or back as plain content.
+///
+pub fn toggle_template_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    content_label_arc: Arc<Mutex<frame::Frame>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the entry to make a template first!");
+        status_label.show();
+        return;
+    }
+
+    let mut value = match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &current_selected_entry,
+    ) {
+        Ok(value) => value,
+        Err(VaultValueErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the entry, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+    if !value.is_template && value.content_stream.is_some() {
+        status_label
+            .set_label("The content is too big to be a template, keep templates under 1 MiB!");
+        status_label.show();
+        return;
+    }
+
+    value.is_template = !value.is_template;
+    match super::core::set_entry_value(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &value,
+    ) {
+        Ok(_) => (),
+        Err(UpdateContentErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to save the entry, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    audit::record(
+        &vault_db_arc,
+        &ecies_arc,
+        &vault_arc,
+        AuditAction::Modified,
+        &current_selected_entry,
+        if value.is_template {
+            "made a template"
+        } else {
+            "no longer a template"
+        },
+    );
+
+    match content_label_arc.lock() {
+        Ok(mut content_label) => {
+            if value.is_template {
+                content_label.set_label("Content (template)");
+            } else {
+                content_label.set_label("Content");
+            }
+        }
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under content_label_arc ARC!\n{err}");
+        }
+    };
+    if value.is_template {
+        status_label.set_label(&format!(
+            "{current_selected_entry} is a template now, it's rendered on install!"
+        ));
+    } else {
+        status_label.set_label(&format!(
+            "{current_selected_entry} is installed as it is now!"
+        ));
+    }
+    status_label.show();
+}
+
+///
+/// Callback for the Templates/Preview Rendered menu item.
+/// Shows the content of the selected template rendered with the variables of this
+/// machine, what Install would write, without writing anything.
+///
+pub fn preview_template_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    content_arc: Arc<Mutex<input::MultilineInput>>,
+    current_selected_entry_arc: Arc<Mutex<String>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let current_selected_entry = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if current_selected_entry.is_empty() {
+        status_label.set_label("Select the template to preview first!");
+        status_label.show();
+        return;
+    }
+
+    let mut value = match super::core::get_entry_value_plain(
+        vault_db_arc.clone(),
+        ecies_arc.clone(),
+        vault_arc.clone(),
+        &current_selected_entry,
+    ) {
+        Ok(value) => value,
+        Err(VaultValueErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the entry, try verifying the vault!");
+            status_label.show();
+            return;
+        }
+    };
+    if !value.is_template {
+        status_label.set_label("This entry is not a template, mark it with Toggle Template!");
+        status_label.show();
+        return;
+    }
+
+    // like Install, what's in the content box is rendered, saved or not
+    let mut template = match content_arc.lock() {
+        Ok(content) if content.active() => content.value().as_bytes().to_vec(),
+        Ok(_) => std::mem::take(&mut value.content),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under content_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    drop(value);
+
+    let rendered = templates::render_with_vault(&vault_db_arc, &ecies_arc, &vault_arc, &template);
+    template.zeroize();
+    let rendered = match rendered {
+        Ok(rendered) => rendered,
+        Err(TemplateErr::NotText(_)) => {
+            status_label.set_label("The template is not text, it can't be rendered!");
+            status_label.show();
+            return;
+        }
+        Err(TemplateErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(_) => {
+            status_label.set_label("Failed to read the template variables, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    if rendered.unknown.is_empty() {
+        status_label.set_label("");
+    } else {
+        status_label.set_label(&format!(
+            "Template variables not set on this machine: {}, set them in Templates!",
+            rendered.unknown.join(", ")
+        ));
+        status_label.show();
+    }
+    report_window::show(
+        &format!("Rendered {current_selected_entry}"),
+        &rendered.text,
+    );
+}
+
+///
+/// Callback for the Templates/Set Variable menu item.
+/// Sets a template variable for this machine, NAME=value, an empty value removes it.
+///
+pub fn set_template_variable_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+
+    let input = match dialog::input_default(
+        "Set a template variable for this machine, as NAME=value, like email=me@example.com.\nUse it in a template as {{ NAME }}, leave the value empty to remove it.",
+        "",
+    ) {
+        Some(input) => input,
+        None => return,
+    };
+    let (name, mut value) = match input.split_once('=') {
+        Some((name, value)) => (name.trim().to_owned(), value.trim().to_owned()),
+        None => {
+            status_label.set_label("Write the variable as NAME=value!");
+            status_label.show();
+            return;
+        }
+    };
+    if !path_vars::is_variable_name(&name) {
+        status_label
+            .set_label("Variable names are letters, digits and _, not starting with a digit!");
+        status_label.show();
+        return;
+    }
+
+    let removed = value.is_empty();
+    let saved = match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => {
+            match templates::get_variables(&vault_db, &mut ecies, &vault) {
+                Ok(mut variables) => {
+                    if removed {
+                        variables.remove(&name);
+                    } else {
+                        variables.insert(name.to_owned(), std::mem::take(&mut value));
+                    }
+                    let saved = templates::set_variables(&vault_db, &mut ecies, &vault, &variables);
+                    for value in variables.values_mut() {
+                        value.zeroize();
+                    }
+                    saved
+                }
+                Err(err) => Err(err),
+            }
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    if saved.is_err() {
+        status_label.set_label("Failed to save the variable, try again or restart!");
+        status_label.show();
+        return;
+    }
+
+    if removed {
+        status_label.set_label(&format!(
+            "Removed the template variable {name} from this machine!"
+        ));
+    } else {
+        status_label.set_label(&format!(
+            "Set the template variable {name} on this machine!"
+        ));
+    }
+    status_label.show();
+}
+
+///
+/// Callback for the Templates/List Variables menu item.
+/// Shows the builtin and set template variables with their value on this machine.
+///
+pub fn list_template_variables_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let custom = match (vault_db_arc.lock(), ecies_arc.lock(), vault_arc.lock()) {
+        (Ok(vault_db), Ok(mut ecies), Ok(vault)) => {
+            templates::get_variables(&vault_db, &mut ecies, &vault)
+        }
+        _ => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc, ecies_arc or vault_arc ARC!");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut custom = match custom {
+        Ok(custom) => custom,
+        Err(_) => {
+            status_label.set_label("Failed to read the template variables, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let builtin = templates::builtin_variables();
+    let mut report = String::from("Builtin variables, set on every machine:\n");
+    for (name, description) in BUILTIN_TEMPLATE_VARIABLES {
+        let value = builtin.get(name).map(String::as_str).unwrap_or_default();
+        report += &format!("  {{{{ {name} }}}} = {value}\n      {description}\n");
+    }
+    report += "\nVariables set on this machine, kept encrypted in the vault:\n";
+    if custom.is_empty() {
+        report += "  none, add them with Templates/Set Variable\n";
+    }
+    for (name, value) in &custom {
+        report += &format!("  {{{{ {name} }}}} = {value}\n");
+    }
+    report += "\nA variable set here wins over a builtin one with the same name, write {{{{ for a literal {{.\n";
+    for value in custom.values_mut() {
+        value.zeroize();
+    }
+
+    report_window::show("Template Variables", &report);
+    report.zeroize();
+}
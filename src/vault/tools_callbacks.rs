@@ -0,0 +1,764 @@
+use super::credentials::CredentialsErr;
+use super::integrity::{RecoverErr, VerifyErr};
+use crate::hasher::blake3;
+use crate::utils::key_slots::{self, KeySlotErr, KeySlotKind};
+use crate::utils::login_guard::{self, LockoutPolicy};
+use crate::utils::{self, db, Vault};
+use crate::ECIES;
+use fltk::{prelude::*, *};
+use sled::Db;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
+
+/// The label of the menu item toggling the compression setting.
+pub const COMPRESS_CONTENTS_ITEM: &str = "Compress Contents";
+
+///
+/// Callback for the Verify Vault menu item.
+/// Walks every entry in the vault, displays a report with everything
+/// that can't be read and offers to export what's still recoverable
+/// into a fresh vault.
+///
+pub fn verify_vault_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+    vault_arc: Arc<Mutex<Vault>>,
+    ecies_arc: Arc<Mutex<ECIES>>,
+    is_windows: bool,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut ecies = match ecies_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let report = match super::integrity::verify_vault(&vault, &vault_db, &mut ecies, is_windows) {
+        Ok(report) => report,
+        Err(VerifyErr::DbInaccesible(_)) => {
+            status_label.set_label("Failed to read the vault db, try again or restart!");
+            status_label.show();
+            return;
+        }
+        Err(VerifyErr::FailedToAccessVaultsDb(_)) => {
+            status_label.set_label("Failed to access the vaults db, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let vault_name = vault.vault_name.to_owned();
+    let vault_has_keyfile = vault.has_keyfile;
+
+    // drop arc ref
+    drop(vault_db);
+    drop(vault);
+    drop(vault_arc);
+    drop(ecies);
+    drop(ecies_arc);
+
+    super::report_window::show("Vault Integrity Report", &report.to_text());
+
+    if report.is_healthy() {
+        status_label.set_label("The vault has been verified, no problems found!");
+        status_label.show();
+        return;
+    }
+
+    status_label.set_label("The vault has problems, check the integrity report!");
+    status_label.show();
+
+    if report.healthy_entries.is_empty() {
+        return;
+    }
+
+    // offer to export what's still recoverable
+    match dialog::choice2_default(
+        "Do you want to export the recoverable entries into a fresh vault?",
+        "No",
+        "Export",
+        "",
+    ) {
+        Some(1) => (),
+        _ => return,
+    };
+
+    let new_vault_name =
+        match dialog::input_default("Name for the new vault:", &(vault_name + "-recovered")) {
+            Some(name) => name,
+            None => return,
+        };
+    let password = match dialog::password_default("Password for the new vault:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    let password_again = match dialog::password_default("Password for the new vault, again:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    if password != password_again {
+        status_label.set_label("Passwords don't match, the recoverable entries weren't exported!");
+        status_label.show();
+        return;
+    }
+
+    // a vault that needs a keyfile is exported into one that needs a keyfile too
+    let keyfile_hash = if vault_has_keyfile {
+        dialog::message_default(
+            "This vault needs a keyfile, select the keyfile for the new vault (it can be the same one).",
+        );
+        match choose_and_read_keyfile(&mut status_label) {
+            Some(hash) => Some(hash),
+            None => return,
+        }
+    } else {
+        None
+    };
+
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    match super::integrity::export_recoverable(
+        &report,
+        &vault_db,
+        &new_vault_name,
+        &password,
+        keyfile_hash.as_ref(),
+        is_windows,
+    ) {
+        Ok(exported) => {
+            status_label.set_label(&format!(
+                "Exported {exported} entries into the vault {new_vault_name}!"
+            ));
+            status_label.show();
+        }
+        Err(RecoverErr::FailedToCreateVault(_)) => {
+            status_label.set_label("Failed to create the new vault, maybe the name is taken!");
+            status_label.show();
+        }
+        Err(RecoverErr::FailedToStoreEntry(_)) => {
+            status_label.set_label("Failed to store some of the entries in the new vault!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Compress Contents menu item.
+/// Stores the new state of the toggle as the vault setting,
+/// only entries saved from now on are affected.
+///
+pub fn compress_contents_callback(
+    vault_menu: &mut menu::MenuButton,
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_db_arc: Arc<Mutex<Db>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault_db = match vault_db_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // fltk already flipped the toggle by the time we get here
+    let mut item = match vault_menu.find_item(COMPRESS_CONTENTS_ITEM) {
+        Some(item) => item,
+        None => return,
+    };
+    let enabled = item.value();
+
+    match super::settings::set_compression_enabled(&vault_db, enabled) {
+        Ok(_) => {
+            if enabled {
+                status_label
+                    .set_label("Entries will be compressed from the next time they're saved!");
+            } else {
+                status_label.set_label("Entries will no longer be compressed when saved!");
+            }
+            status_label.show();
+        }
+        Err(_) => {
+            // put the toggle back as it was
+            if enabled {
+                item.clear();
+            } else {
+                item.set();
+            }
+            status_label.set_label("Failed to store the setting, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Change Password menu item.
+/// Asks for the current credentials and the new password, the keyfile
+/// of a vault that has one is kept.
+///
+pub fn change_password_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let current_password = match dialog::password_default("Current password:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    let keyfile_hash = if vault.has_keyfile {
+        dialog::message_default(
+            "Select the keyfile of this vault, it will still be needed with the new password.",
+        );
+        match choose_and_read_keyfile(&mut status_label) {
+            Some(hash) => Some(hash),
+            None => return,
+        }
+    } else {
+        None
+    };
+    let new_password = match dialog::password_default("New password:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    let new_password_again = match dialog::password_default("New password, again:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    if new_password != new_password_again {
+        status_label.set_label("Passwords don't match, the password wasn't changed!");
+        status_label.show();
+        return;
+    }
+
+    match super::credentials::change_password(
+        &vault,
+        &current_password,
+        &new_password,
+        keyfile_hash.as_ref(),
+    ) {
+        Ok(_) => {
+            status_label.set_label("The password has been changed!");
+            status_label.show();
+        }
+        Err(CredentialsErr::WrongCredentials(_)) => {
+            status_label
+                .set_label("The current credentials are wrong, the password wasn't changed!");
+            status_label.show();
+        }
+        Err(CredentialsErr::FailedToAccessVaultsDb(_)) => {
+            status_label.set_label("Failed to access the vaults db, try again or restart!");
+            status_label.show();
+        }
+        Err(CredentialsErr::FailedToStoreKey(_)) => {
+            status_label
+                .set_label("Failed to store the key with the new password, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Key Slots/List Slots menu item.
+/// Shows every key slot that can unlock the vault.
+///
+pub fn list_key_slots_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let estashdb = match open_estashdb(&mut status_label) {
+        Some(db) => db,
+        None => return,
+    };
+    let vault_name_hashed = blake3::hash_str(&vault.vault_name);
+
+    let slots = match key_slots::list_slots(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        vault.has_keyfile,
+    ) {
+        Ok(slots) => slots,
+        Err(_) => {
+            status_label.set_label("Failed to read the key slots, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let mut text = format!("Key slots of the vault {}:\n\n", vault.vault_name);
+    for slot in &slots {
+        text += &(slot.describe() + "\n");
+    }
+    text += "\nEvery password slot is tried in turn at login, and each one takes as long as a whole login, so a wrong password takes longer with every password slot added. Recovery code slots are only tried when what was typed is shaped like a recovery code.\n";
+
+    super::report_window::show("Key Slots", &text);
+}
+
+///
+/// Callback for the Key Slots/Add Password menu item.
+/// Adds a slot opened by a new password, and the keyfile if the vault has one.
+///
+pub fn add_password_slot_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let estashdb = match open_estashdb(&mut status_label) {
+        Some(db) => db,
+        None => return,
+    };
+    let vault_name_hashed = blake3::hash_str(&vault.vault_name);
+
+    let label = match dialog::input_default("Label for the new slot:", "Password") {
+        Some(label) => label,
+        None => return,
+    };
+    let password = match dialog::password_default("Password for the new slot:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    let password_again = match dialog::password_default("Password for the new slot, again:", "") {
+        Some(pass) => Zeroizing::new(pass),
+        None => return,
+    };
+    if password != password_again {
+        status_label.set_label("Passwords don't match, the slot wasn't added!");
+        status_label.show();
+        return;
+    }
+    let keyfile_hash = if vault.has_keyfile {
+        dialog::message_default(
+            "This vault needs a keyfile, select the keyfile for the new slot (it can be the same one).",
+        );
+        match choose_and_read_keyfile(&mut status_label) {
+            Some(hash) => Some(hash),
+            None => return,
+        }
+    } else {
+        None
+    };
+
+    match key_slots::add_slot(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        &label,
+        KeySlotKind::Password,
+        &password,
+        keyfile_hash.as_ref(),
+        &vault.priv_key,
+    ) {
+        Ok(id) => {
+            status_label.set_label(&format!(
+                "Added the key slot {id}, logins with a password not in the first slot take a little longer!"
+            ));
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to add the key slot, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Key Slots/Add Recovery Code menu item.
+/// Generates a new recovery code, stores a slot for it and shows it once.
+///
+pub fn add_recovery_code_slot_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let estashdb = match open_estashdb(&mut status_label) {
+        Some(db) => db,
+        None => return,
+    };
+    let vault_name_hashed = blake3::hash_str(&vault.vault_name);
+
+    let recovery_code = key_slots::generate_recovery_code();
+
+    match key_slots::add_slot(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        "Recovery code",
+        KeySlotKind::RecoveryCode,
+        &recovery_code,
+        None,
+        &vault.priv_key,
+    ) {
+        Ok(id) => {
+            status_label.set_label(&format!("Added the key slot {id}!"));
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to add the key slot, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    // drop arc ref
+    drop(vault);
+    drop(vault_arc);
+
+    super::report_window::show(
+        "Recovery Code",
+        &format!(
+            "Recovery code for the vault, it can be used in place of the password (and keyfile).\nWrite it down, it's shown only once:\n\n{recovery_code}\n"
+        ),
+    );
+}
+
+///
+/// Callback for the Key Slots/Revoke Slot menu item.
+/// Removes the slot with the given id, the last slot of the vault can't be revoked.
+///
+pub fn revoke_key_slot_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let estashdb = match open_estashdb(&mut status_label) {
+        Some(db) => db,
+        None => return,
+    };
+    let vault_name_hashed = blake3::hash_str(&vault.vault_name);
+
+    let slot_id = match dialog::input_default("Id of the slot to revoke (see List Slots):", "") {
+        Some(id) => match id.trim().parse::<u64>() {
+            Ok(id) => id,
+            Err(_) => {
+                status_label.set_label("The slot id has to be a number!");
+                status_label.show();
+                return;
+            }
+        },
+        None => return,
+    };
+    match dialog::choice2_default(
+        &format!("Revoke the key slot {slot_id}? It won't unlock the vault anymore."),
+        "No",
+        "Revoke",
+        "",
+    ) {
+        Some(1) => (),
+        _ => return,
+    };
+
+    match key_slots::revoke_slot(
+        &estashdb.vault_priv_key_db,
+        &vault_name_hashed,
+        vault.has_keyfile,
+        slot_id,
+    ) {
+        Ok(_) => {
+            status_label.set_label(&format!("Revoked the key slot {slot_id}!"));
+            status_label.show();
+        }
+        Err(KeySlotErr::NotFound(_)) => {
+            status_label.set_label("There is no key slot with that id!");
+            status_label.show();
+        }
+        Err(KeySlotErr::LastSlot(_)) => {
+            status_label.set_label("The last key slot of the vault can't be revoked!");
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to revoke the key slot, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Callback for the Login Protection menu item.
+/// Sets what happens after too many failed logins in a row, on top of
+/// the waits between attempts: nothing more, requiring a recovery code or erasing the vault.
+///
+pub fn login_protection_callback(
+    status_label_arc: Arc<Mutex<frame::Frame>>,
+    vault_arc: Arc<Mutex<Vault>>,
+) {
+    // get references from arcs
+    let mut status_label = match status_label_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            return;
+        }
+    };
+    let vault = match vault_arc.lock() {
+        Ok(object) => object,
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let estashdb = match open_estashdb(&mut status_label) {
+        Some(db) => db,
+        None => return,
+    };
+    let vault_name_hashed = blake3::hash_str(&vault.vault_name);
+
+    let attempts = match login_guard::get_attempts(&estashdb.vault_db, &vault_name_hashed) {
+        Ok(attempts) => attempts,
+        Err(_) => {
+            status_label.set_label("Failed to read the login protection, try again or restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let current = match attempts.policy {
+        LockoutPolicy::DelayOnly => {
+            "Now failed logins only make you wait between attempts.".to_string()
+        }
+        policy => format!(
+            "Now after {} failed logins in a row: {}.",
+            attempts.policy_after,
+            policy.describe()
+        ),
+    };
+    let policy = match dialog::choice2_default(
+        &format!("{current}\nWhat should happen after too many failed logins in a row?"),
+        "Only wait",
+        "Require recovery code",
+        "Erase vault",
+    ) {
+        Some(0) => LockoutPolicy::DelayOnly,
+        Some(1) => LockoutPolicy::RequireRecovery,
+        Some(2) => LockoutPolicy::SelfDestruct,
+        _ => return,
+    };
+
+    let mut policy_after = login_guard::DEFAULT_POLICY_AFTER;
+    if policy != LockoutPolicy::DelayOnly {
+        let default_after = if attempts.policy_after > 0 {
+            attempts.policy_after
+        } else {
+            login_guard::DEFAULT_POLICY_AFTER
+        };
+        policy_after = match dialog::input_default(
+            "Failed logins in a row before it happens:",
+            &default_after.to_string(),
+        ) {
+            Some(after) => match after.trim().parse::<u32>() {
+                Ok(after) if after > 0 => after,
+                _ => {
+                    status_label.set_label("The number of failed logins has to be at least 1!");
+                    status_label.show();
+                    return;
+                }
+            },
+            None => return,
+        };
+    }
+
+    match policy {
+        LockoutPolicy::RequireRecovery => {
+            // without a recovery code slot the vault couldn't be unlocked anymore
+            let has_recovery_code = match key_slots::list_slots(
+                &estashdb.vault_priv_key_db,
+                &vault_name_hashed,
+                vault.has_keyfile,
+            ) {
+                Ok(slots) => slots
+                    .iter()
+                    .any(|slot| slot.kind == KeySlotKind::RecoveryCode),
+                Err(_) => false,
+            };
+            if !has_recovery_code {
+                status_label.set_label("Add a recovery code first, see Key Slots!");
+                status_label.show();
+                return;
+            }
+        }
+        LockoutPolicy::SelfDestruct => {
+            match dialog::choice2_default(
+                &format!("Erase the vault for good after {policy_after} failed logins in a row? Anyone who knows its name can make that happen."),
+                "No",
+                "Erase",
+                "",
+            ) {
+                Some(1) => (),
+                _ => return,
+            };
+        }
+        LockoutPolicy::DelayOnly => (),
+    };
+
+    match login_guard::set_policy(&estashdb.vault_db, &vault_name_hashed, policy, policy_after) {
+        Ok(_) => {
+            status_label.set_label(&format!(
+                "Too many failed logins will {}!",
+                policy.describe()
+            ));
+            status_label.show();
+        }
+        Err(_) => {
+            status_label.set_label("Failed to set the login protection, try again or restart!");
+            status_label.show();
+        }
+    };
+}
+
+///
+/// Loads the db holding the vaults and their keys.
+///
+fn open_estashdb(status_label: &mut frame::Frame) -> Option<db::EstashDb> {
+    match db::EstashDb::new() {
+        Ok(db) => Some(db),
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the db containing the list with vaults!\n{err}"
+            );
+            status_label.set_label("Failed to access the vaults db, try again or restart!");
+            status_label.show();
+            None
+        }
+    }
+}
+
+///
+/// Asks for a keyfile and reads it, returning the hash of its content.
+///
+fn choose_and_read_keyfile(status_label: &mut frame::Frame) -> Option<[u8; 32]> {
+    let keyfile_path = utils::keyfile::choose_keyfile()?;
+
+    match utils::keyfile::read_keyfile(&keyfile_path) {
+        Some(hash) => Some(hash),
+        None => {
+            status_label.set_label("Failed to read the keyfile!");
+            status_label.show();
+            None
+        }
+    }
+}
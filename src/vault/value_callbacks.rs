@@ -1,15 +1,23 @@
+use super::audit::{self, AuditAction};
+use super::content_stream::{self, ContentStream};
 use super::core::UpdateContentErr;
 use super::core::{VaultValue, VaultValueErr};
+use super::hooks_callbacks;
+use super::install_targets;
+use super::installs::{self, InstallContent, InstallMode, InstallsErr};
+use super::installs_callbacks;
+use super::path_vars;
+use super::templates::{self, TemplateErr};
 use crate::utils;
 use crate::utils::Vault;
 use crate::ECIES;
 use fltk::{prelude::*, *};
 use sled;
 use sled::Db;
-use std::collections::HashMap;
 use std::str;
 use std::sync::{Arc, Mutex};
 use std::{fs, process};
+use zeroize::Zeroize;
 
 ///
 /// Callback function for when you hit the save button on an entry
@@ -23,7 +31,6 @@ pub fn save_button_callback(
     current_selected_entry_arc_clone: Arc<Mutex<String>>,
     vault_db_arc_clone: Arc<Mutex<Db>>,
     vault_arc_clone: Arc<Mutex<Vault>>,
-    db_entries_dict_arc_clone: Arc<Mutex<HashMap<String, Vec<u8>>>>,
     ecies_arc_clone: Arc<Mutex<ECIES>>,
 ) {
     // get references from arcs
@@ -65,10 +72,20 @@ pub fn save_button_callback(
     // empty error label
     status_label.set_label("");
 
-    // check if the given path is valid
+    // check if the given path is valid, the variables in it are expanded first and a path
+    // using variables only set on other machines can't be checked here
     let install_path_value: String;
     if install_path.active() {
-        if !utils::is_path_os_valid(&install_path.value()) {
+        let expanded_path = match vault_db_arc_clone.lock() {
+            Ok(vault_db) => path_vars::expand_with_settings(&vault_db, &install_path.value()),
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                status_label.set_label("There was a Poison Error, try again, or try to restart!");
+                status_label.show();
+                return;
+            }
+        };
+        if expanded_path.unknown.is_empty() && !utils::is_path_os_valid(&expanded_path.path) {
             status_label.set_label("The given path is invalid on the current operating system!");
             status_label.show();
             return;
@@ -79,66 +96,79 @@ pub fn save_button_callback(
         install_path_value = String::new();
     }
 
+    // get the currently stored value, everything that isn't displayed is kept from it
+    let selected_item = match current_selected_entry_arc_clone.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let mut current_entry_value = match super::core::get_entry_value_plain(
+        vault_db_arc_clone.clone(),
+        ecies_arc_clone.clone(),
+        vault_arc_clone.clone(),
+        &selected_item,
+    ) {
+        Ok(val) => val,
+        Err(VaultValueErr::DbCorrupted(_)) => {
+            process::exit(100);
+        }
+        Err(VaultValueErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(VaultValueErr::DisplayNotInSync(_)) => {
+            status_label.set_label(
+                "What's on screen is not in sync with what's in memory, try again or restart!",
+            );
+            status_label.show();
+            return;
+        }
+        Err(VaultValueErr::MemoryNotInSync(_)) => {
+            status_label.set_label(
+                "What's in memory is not in sync with what's in storage, please restart!",
+            );
+            status_label.show();
+            return;
+        }
+    };
+
     // if the content widget isn't active it mean that it has either a file that is too big or a
     // file that isn't parsable to utf8 meaning we don't display it in the content box and keep it
     // in memory instead, so if that's the case we take the value from memory, if not we just take
     // them from the widgets
+    let old_content_stream: Option<ContentStream>;
     let entry_value: VaultValue;
     if content.active() {
+        old_content_stream = current_entry_value.content_stream.take();
         entry_value = VaultValue {
+            name: selected_item,
             install_path: install_path_value,
             content: content.value().as_bytes().to_vec(),
             notes: notes_value,
+            content_stream: None,
+            install_targets: std::mem::take(&mut current_entry_value.install_targets),
+            is_template: current_entry_value.is_template,
+            install_mode: current_entry_value.install_mode,
+            post_install: std::mem::take(&mut current_entry_value.post_install),
         };
     } else {
-        let selected_item = match current_selected_entry_arc_clone.lock() {
-            Ok(object) => object,
-            Err(err) => {
-                eprintln!("ERROR: Failed to get value under notes_arc ARC!\n{err}");
-                status_label.set_label("There was a Poison Error, try again, or try to restart!");
-                status_label.show();
-                return;
-            }
-        };
-
-        let current_entry_value = match super::core::get_entry_value_plain(
-            vault_db_arc_clone.clone(),
-            ecies_arc_clone.clone(),
-            vault_arc_clone.clone(),
-            &selected_item,
-            db_entries_dict_arc_clone.clone(),
-        ) {
-            Ok(val) => val,
-            Err(VaultValueErr::DbCorrupted(_)) => {
-                process::exit(100);
-            }
-            Err(VaultValueErr::PoisonErr(_)) => {
-                status_label.set_label("There was a Poison Error, try again, or try to restart!");
-                status_label.show();
-                return;
-            }
-            Err(VaultValueErr::DisplayNotInSync(_)) => {
-                status_label.set_label(
-                    "What's on screen is not in sync with what's in memory, try again or restart!",
-                );
-                status_label.show();
-                return;
-            }
-            Err(VaultValueErr::MemoryNotInSync(_)) => {
-                status_label.set_label(
-                    "What's in memory is not in sync with what's in storage, please restart!",
-                );
-                status_label.show();
-                return;
-            }
-        };
-
-        drop(selected_item);
-
+        old_content_stream = None;
         entry_value = VaultValue {
+            name: selected_item,
             install_path: install_path_value,
-            content: current_entry_value.content,
+            content: std::mem::take(&mut current_entry_value.content),
             notes: notes_value,
+            content_stream: current_entry_value.content_stream.take(),
+            install_targets: std::mem::take(&mut current_entry_value.install_targets),
+            is_template: current_entry_value.is_template,
+            install_mode: current_entry_value.install_mode,
+            post_install: std::mem::take(&mut current_entry_value.post_install),
         };
     }
 
@@ -148,128 +178,45 @@ pub fn save_button_callback(
     drop(content);
     drop(content_arc);
     drop(notes_arc);
+    drop(current_selected_entry_arc_clone);
 
-    // shouldn't error, hopefully
-    let entry_value_string = match serde_json::to_string(&entry_value) {
-        Ok(s) => s,
-        Err(err) => {
-            eprintln!("ERROR: Somehow converting the struct to json error'd out, shouldn't have, here is the error,\n{err}");
-            status_label.set_label(
-                "There was an error converting your input to json, restart or try again!",
-            );
-            status_label.show();
-            return;
-        }
-    };
-
-    // get value under ecies_arc arc
-    let mut ecies = match ecies_arc_clone.lock() {
-        Ok(object) => object,
-        Err(err) => {
-            eprintln!("ERROR: Failed to get value under ecies_arc ARC!\n{err}");
-            status_label.set_label("There was a Poison Error, try again, or try to restart!");
-            status_label.show();
-            return;
-        }
-    };
-
-    // get value under arc
-    let vault = match vault_arc_clone.lock() {
-        Ok(object) => object,
-        Err(err) => {
-            eprintln!("ERROR: Failed to get value under vault_arc_clone ARC!\n{err}");
-            status_label.set_label("There was a Poison Error, try again, or try to restart!");
-            status_label.show();
-            return;
-        }
-    };
-
-    // there should be no way for this to error out since once the vault is loaded it means the
-    // keys work
-    let entry_value_encrypted = match ecies.encrypt_bytes_array(
-        &entry_value_string.as_bytes(),
-        &vault.priv_key,
-        &vault.pub_key,
+    match super::core::set_entry_value(
+        vault_db_arc_clone.clone(),
+        ecies_arc_clone.clone(),
+        vault_arc_clone.clone(),
+        &entry_value,
     ) {
-        Ok(cipher) => cipher,
-        Err(err) => {
-            eprintln!("ERROR: Failed to encrypt the data input by user, there should be no way for this to error out since once the vault is loaded it means the keys work, anyways here is the error,\n{err}");
-            status_label.set_label(
-                "There was an error encrypting the data you input, try again or restart!",
-            );
-            status_label.show();
-            return;
-        }
-    };
-
-    // drop arc ref
-    drop(vault);
-    drop(vault_arc_clone);
-    drop(ecies);
-    drop(ecies_arc_clone);
-
-    // get value under arc
-    let db_entries_dict = match db_entries_dict_arc_clone.lock() {
-        Ok(object) => object,
-        Err(err) => {
-            eprintln!("ERROR: Failed to get value under db_entries_dict_arc ARC!\n{err}");
-            status_label.set_label("There was a Poison Error, try again, or try to restart!");
-            status_label.show();
-            return;
-        }
-    };
-
-    // get value under arc
-    let selected_item = match current_selected_entry_arc_clone.lock() {
-        Ok(object) => object.to_owned(),
-        Err(err) => {
-            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+        Ok(_) => (),
+        Err(UpdateContentErr::PoisonErr(_)) => {
             status_label.set_label("There was a Poison Error, try again, or try to restart!");
             status_label.show();
             return;
         }
-    };
-
-    let selected_item_encrypted = match db_entries_dict.get(&selected_item) {
-        Some(cipher) => cipher.to_owned(),
-        None => {
-            eprintln!("ERROR: The Values In Memory are not in sync with the ones on screen!");
+        Err(UpdateContentErr::UnknownError(_)) => {
             status_label.set_label(
-                "What's in memory is not in sync with what's in storage, please restart!",
+                "There was an error encrypting the data you input, try again or restart!",
             );
             status_label.show();
             return;
         }
-    };
-
-    // drop arc
-    drop(selected_item);
-    drop(current_selected_entry_arc_clone);
-    drop(db_entries_dict);
-    drop(db_entries_dict_arc_clone);
-
-    // get value under arc
-    let vault_db = match vault_db_arc_clone.lock() {
-        Ok(object) => object,
-        Err(err) => {
-            eprintln!("ERROR: Failedt to get value under vault_db_arc ARC!\n{err}");
-            status_label.set_label("There was a Poison Error, try again, or try to restart!");
-            status_label.show();
-            return;
-        }
-    };
-
-    // drop arc ref
-    drop(status_label);
-    drop(status_label_arc);
-
-    match vault_db.insert(selected_item_encrypted, entry_value_encrypted) {
-        Ok(_) => (),
-        Err(err) => {
-            eprintln!("ERROR: There was an error storing the input value in the db, for some reason the db in not accesible, please report this error on github if you are able to replicate it!\n{err}");
+        Err(_) => {
+            eprintln!("ERROR: There was an error storing the input value in the db, for some reason the db in not accesible, please report this error on github if you are able to replicate it!");
             process::exit(100);
         }
     };
+    audit::record(
+        &vault_db_arc_clone,
+        &ecies_arc_clone,
+        &vault_arc_clone,
+        AuditAction::Modified,
+        &entry_value.name,
+        "",
+    );
+
+    // the content has been replaced by the one in the content box, so the old chunks go
+    if let Some(old_content_stream) = old_content_stream {
+        super::core::remove_old_stream(vault_db_arc_clone, &old_content_stream);
+    }
 }
 
 ///
@@ -278,8 +225,9 @@ pub fn save_button_callback(
 ///
 pub fn delete_button_callback(
     vault_db_arc_clone: Arc<Mutex<Db>>,
+    vault_arc_clone: Arc<Mutex<Vault>>,
+    ecies_arc_clone: Arc<Mutex<ECIES>>,
     current_selected_entry_arc_clone: Arc<Mutex<String>>,
-    db_entries_dict_arc_clone: Arc<Mutex<HashMap<String, Vec<u8>>>>,
     entries_arc_clone: Arc<Mutex<tree::Tree>>,
 ) {
     let mut entries = match entries_arc_clone.lock() {
@@ -296,10 +244,23 @@ pub fn delete_button_callback(
             return;
         }
     };
-    let mut db_entries_dict = match db_entries_dict_arc_clone.lock() {
-        Ok(object) => object,
+
+    // get the stored value first so the chunks of a big content can be removed too, an entry
+    // with an unreadable value can still be deleted though
+    let content_stream = match super::core::get_entry_value_plain(
+        vault_db_arc_clone.clone(),
+        ecies_arc_clone.clone(),
+        vault_arc_clone.clone(),
+        &current_selected_entry,
+    ) {
+        Ok(mut value) => value.content_stream.take(),
+        Err(_) => None,
+    };
+
+    let current_selected_entry_key = match vault_arc_clone.lock() {
+        Ok(vault) => super::core::entry_key(&vault, &current_selected_entry),
         Err(err) => {
-            eprintln!("ERROR: Failed to get value under db_entries_dict_arc ARC!\n{err}");
+            eprintln!("ERROR: Failed to get value under vault_arc ARC!\n{err}");
             return;
         }
     };
@@ -311,26 +272,31 @@ pub fn delete_button_callback(
         }
     };
 
-    let current_selected_entry_encrypted = match db_entries_dict
-        .get(current_selected_entry.as_str())
-    {
-        Some(cipher) => cipher,
-        None => {
-            eprintln!("ERROR: What's on screen is not in sync with what's in memory for some reason, either try again or restart, if this error persists please report on github");
+    match super::core::remove_entry(&vault_db, &current_selected_entry_key) {
+        Ok(_) => (),
+        Err(_) => {
+            eprintln!("ERROR: What's in memory is not in sync with what's in storage, either try again or restart, if this error persisits please report on github!");
             return;
         }
     };
 
-    match vault_db.remove(current_selected_entry_encrypted) {
-        Ok(_) => (),
-        Err(err) => {
-            eprintln!("ERROR: What's in memory is not in sync with what's in storage, either try again or restart, if this error persisits please report on github!\n{err}");
-            return;
+    if let Some(content_stream) = content_stream {
+        if content_stream::remove_stream(&vault_db, &content_stream).is_err() {
+            eprintln!("ERROR: Failed to remove the content chunks of the deleted entry, they will stay unused in the db!");
         }
-    };
+    }
 
     // drop arc ref
     drop(vault_db);
+
+    audit::record(
+        &vault_db_arc_clone,
+        &ecies_arc_clone,
+        &vault_arc_clone,
+        AuditAction::Deleted,
+        &current_selected_entry,
+        "",
+    );
     drop(vault_db_arc_clone);
 
     let entries_items = match entries.get_items() {
@@ -351,7 +317,6 @@ pub fn delete_button_callback(
         };
 
         if current_item_label == current_selected_entry.as_str() {
-            db_entries_dict.remove(&current_item_label);
             match entries.remove(&item) {
                 Ok(_) => (),
                 Err(err) => {
@@ -367,15 +332,6 @@ pub fn delete_button_callback(
     drop(current_selected_entry);
     drop(current_selected_entry_arc_clone);
 
-    entries.clear();
-
-    for entrie in db_entries_dict.keys() {
-        entries.add(entrie);
-    }
-
-    // drop arc ref
-    drop(db_entries_dict);
-
     entries.redraw();
 
     entries.do_callback();
@@ -383,8 +339,9 @@ pub fn delete_button_callback(
 
 ///
 /// Callback function for when you hit the install button on an entry.
-/// Takes the install path from the current input (not what's saved)
-/// and the content and writes it to that file, creating any needed folders.
+/// Takes the install target of this machine, or else the install path from the
+/// current input (not what's saved), and writes the content to that file,
+/// creating any needed folders.
 ///
 pub fn install_button_callback(
     install_path_arc: Arc<Mutex<input::Input>>,
@@ -393,9 +350,7 @@ pub fn install_button_callback(
     vault_db_arc_clone: Arc<Mutex<Db>>,
     ecies_arc_clone: Arc<Mutex<ECIES>>,
     current_selected_entry_arc_clone: Arc<Mutex<String>>,
-    db_entries_dict_arc_clone: Arc<Mutex<HashMap<String, Vec<u8>>>>,
     status_label_arc: Arc<Mutex<frame::Frame>>,
-    is_windows: bool,
 ) {
     // get references behind arc
     let mut status_label = match status_label_arc.lock() {
@@ -424,67 +379,156 @@ pub fn install_button_callback(
         }
     };
 
-    if !install_path.active() {
-        status_label.set_label("There is no install path active for this entry!");
+    // the stored value holds the install targets, and the content when it's not displayed
+    let selected_item = match current_selected_entry_arc_clone.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    let mut current_entry_value = match super::core::get_entry_value_plain(
+        vault_db_arc_clone.clone(),
+        ecies_arc_clone.clone(),
+        vault_arc_clone.clone(),
+        &selected_item,
+    ) {
+        Ok(val) => val,
+        Err(VaultValueErr::DbCorrupted(_)) => {
+            process::exit(100);
+        }
+        Err(VaultValueErr::PoisonErr(_)) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+        Err(VaultValueErr::DisplayNotInSync(_)) => {
+            status_label.set_label(
+                "What's on screen is not in sync with what's in memory, try again or restart!",
+            );
+            status_label.show();
+            return;
+        }
+        Err(VaultValueErr::MemoryNotInSync(_)) => {
+            status_label.set_label(
+                "What's in memory is not in sync with what's in storage, please restart!",
+            );
+            status_label.show();
+            return;
+        }
+    };
+
+    // a target for this machine wins over the install path in the input
+    let default_path = if install_path.active() {
+        install_path.value()
+    } else {
+        String::new()
+    };
+    let hostname = utils::hostname();
+    let resolved = match install_targets::resolve(
+        &default_path,
+        &current_entry_value.install_targets,
+        std::env::consts::OS,
+        &hostname,
+    ) {
+        Some(resolved) => resolved,
+        None if current_entry_value.install_targets.is_empty() => {
+            status_label.set_label("There is no install path active for this entry!");
+            status_label.show();
+            return;
+        }
+        None => {
+            status_label.set_label(&format!(
+                "No install target applies to this machine ({}, {hostname}), see Install Targets!",
+                std::env::consts::OS
+            ));
+            status_label.show();
+            return;
+        }
+    };
+
+    // the variables in the path are expanded to their value on this machine
+    let expanded_path = match vault_db_arc_clone.lock() {
+        Ok(vault_db) => path_vars::expand_with_settings(&vault_db, &resolved.path),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    if !expanded_path.unknown.is_empty() {
+        status_label.set_label(&format!(
+            "${} not set on this machine, set it in Path Variables!",
+            expanded_path.unknown.join(", $")
+        ));
         status_label.show();
         return;
     }
-
-    let install_path_value = install_path.value().to_owned();
-    let content_value: Vec<u8>;
+    let install_path_value = expanded_path.path;
 
     // if the content widget isn't active it mean that it has either a file that is too big or a
     // file that isn't parsable to utf8 meaning we don't display it in the content box and keep it
     // in memory instead, so if that's the case we take the value from memory, if not we just take
     // them from the widgets
+    let mut content_value: Vec<u8>;
+    let mut content_stream_value: Option<ContentStream> = None;
     if content.active() {
         content_value = content.value().to_owned().as_bytes().to_vec();
     } else {
-        let selected_item = match current_selected_entry_arc_clone.lock() {
-            Ok(object) => object,
-            Err(err) => {
-                eprintln!("ERROR: Failed to get value under notes_arc ARC!\n{err}");
-                status_label.set_label("There was a Poison Error, try again, or try to restart!");
+        content_value = std::mem::take(&mut current_entry_value.content);
+        content_stream_value = current_entry_value.content_stream.take();
+    }
+    let is_template = current_entry_value.is_template;
+    let install_mode = current_entry_value.install_mode;
+    let post_install = std::mem::take(&mut current_entry_value.post_install);
+    drop(current_entry_value);
+
+    // templates are rendered with the variables of this machine before anything is written
+    if is_template {
+        if content_stream_value.is_some() {
+            status_label
+                .set_label("The template is too big to be rendered, keep templates under 1 MiB!");
+            status_label.show();
+            return;
+        }
+        match templates::render_with_vault(
+            &vault_db_arc_clone,
+            &ecies_arc_clone,
+            &vault_arc_clone,
+            &content_value,
+        ) {
+            Ok(rendered) if rendered.unknown.is_empty() => {
+                content_value.zeroize();
+                content_value = rendered.text.as_bytes().to_vec();
+            }
+            Ok(rendered) => {
+                status_label.set_label(&format!(
+                    "Template variables not set on this machine: {}, set them in Templates!",
+                    rendered.unknown.join(", ")
+                ));
                 status_label.show();
                 return;
             }
-        };
-
-        let current_entry_value = match super::core::get_entry_value_plain(
-            vault_db_arc_clone.clone(),
-            ecies_arc_clone.clone(),
-            vault_arc_clone.clone(),
-            &selected_item,
-            db_entries_dict_arc_clone.clone(),
-        ) {
-            Ok(val) => val,
-            Err(VaultValueErr::DbCorrupted(_)) => {
-                process::exit(100);
-            }
-            Err(VaultValueErr::PoisonErr(_)) => {
-                status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            Err(TemplateErr::NotText(_)) => {
+                status_label.set_label("The template is not text, it can't be rendered!");
                 status_label.show();
                 return;
             }
-            Err(VaultValueErr::DisplayNotInSync(_)) => {
-                status_label.set_label(
-                    "What's on screen is not in sync with what's in memory, try again or restart!",
-                );
+            Err(TemplateErr::PoisonErr(_)) => {
+                status_label.set_label("There was a Poison Error, try again, or try to restart!");
                 status_label.show();
                 return;
             }
-            Err(VaultValueErr::MemoryNotInSync(_)) => {
-                status_label.set_label(
-                    "What's in memory is not in sync with what's in storage, please restart!",
-                );
+            Err(_) => {
+                status_label
+                    .set_label("Failed to read the template variables, try again or restart!");
                 status_label.show();
                 return;
             }
         };
-
-        drop(selected_item);
-
-        content_value = current_entry_value.content;
     }
 
     // drop arc ref
@@ -495,54 +539,77 @@ pub fn install_button_callback(
 
     // check if install path is valid
     if !utils::is_path_os_valid(&install_path_value) {
-        status_label.set_label("The given path is invalid on the current operating system!");
+        status_label.set_label(&format!(
+            "The path of {} is invalid on the current operating system!",
+            resolved.description()
+        ));
         status_label.show();
         return;
     }
 
-    // create the folder or make sure there is one
-    if is_windows {
-        let mut path_folder_vec: Vec<&str> = install_path_value.split("\\").collect();
-        path_folder_vec.pop();
-        let path_folder = path_folder_vec.join("\\");
-
-        match fs::create_dir_all(path_folder) {
-            Ok(_) => (),
-            Err(_) => {
-                status_label
-                    .set_label("There was an error creating/finding the dir where to install!");
-                status_label.show();
-                return;
-            }
-        };
-    } else {
-        let mut path_folder_vec: Vec<&str> = install_path_value.split("/").collect();
-        path_folder_vec.pop();
-        let path_folder = path_folder_vec.join("/");
-
-        match fs::create_dir_all(path_folder) {
-            Ok(_) => (),
-            Err(_) => {
-                status_label
-                    .set_label("There was an error creating/finding the dir where to install!");
-                status_label.show();
-                return;
-            }
-        };
-    }
+    let installed_status = match (install_mode, &resolved.machine) {
+        (InstallMode::Symlink, Some(_)) => format!(
+            "Successfully linked {install_path_value} to the managed copy, {}!",
+            resolved.description()
+        ),
+        (InstallMode::Symlink, None) => {
+            "Successfully linked the file to the managed copy of the content!".to_string()
+        }
+        (InstallMode::Copy, Some(_)) => format!(
+            "Successfully written the content to {install_path_value}, {}!",
+            resolved.description()
+        ),
+        (InstallMode::Copy, None) => "Successfully written the content to the file!".to_string(),
+    };
 
-    // try and write to that file
-    match fs::write(install_path_value, content_value) {
-        Ok(_) => {
-            status_label.set_label("Successfully written the content to the file!");
-            status_label.show();
-            return;
+    let to_install = match &content_stream_value {
+        Some(content_stream_value) => InstallContent::Stream(content_stream_value),
+        None => InstallContent::Bytes(&content_value),
+    };
+    let installed = installs::install_content(
+        &vault_db_arc_clone,
+        &ecies_arc_clone,
+        &vault_arc_clone,
+        &selected_item,
+        &install_path_value,
+        install_mode,
+        to_install,
+    );
+    content_value.zeroize();
+    let installed_status = match installed {
+        Ok(_) => installed_status,
+        Err(InstallsErr::NotRecorded(_)) => {
+            format!("{installed_status} It couldn't be added to the installed files though.")
         }
-        Err(_) => {
-            status_label.set_label("There was an error writing the content to the file!");
-            status_label.show();
+        Err(err) => {
+            installs_callbacks::show_install_err(&mut status_label, err);
             return;
         }
+    };
+    status_label.set_label(&installed_status);
+    status_label.show();
+
+    audit::record(
+        &vault_db_arc_clone,
+        &ecies_arc_clone,
+        &vault_arc_clone,
+        AuditAction::Installed,
+        &selected_item,
+        &install_path_value,
+    );
+
+    // the hook runs once the file is in place
+    if !post_install.is_empty() {
+        drop(status_label);
+        hooks_callbacks::run_post_install_hook(
+            &status_label_arc,
+            &installed_status,
+            &vault_db_arc_clone,
+            &vault_arc_clone,
+            &selected_item,
+            &post_install,
+            &install_path_value,
+        );
     }
 }
 
@@ -563,7 +630,6 @@ pub fn select_file_button(
     vault_db_arc: Arc<Mutex<Db>>,
     ecies_arc: Arc<Mutex<ECIES>>,
     vault_arc: Arc<Mutex<Vault>>,
-    db_entries_dict_arc: Arc<Mutex<HashMap<String, Vec<u8>>>>,
 ) {
     let mut file_dialog = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
     file_dialog.show();
@@ -577,6 +643,85 @@ pub fn select_file_button(
         }
     };
 
+    // get the size of the file first, big files are never read as a whole
+    let file_size = match fs::metadata(file_dialog.filename()) {
+        Ok(metadata) => metadata.len(),
+        Err(err) => {
+            eprintln!(
+                "ERROR: There was an error reading the contents of the selected file!\n{err}"
+            );
+            status_label.set_label("There was an error reading the contents of the file!");
+            status_label.show();
+            return;
+        }
+    };
+
+    let selected_item = match current_selected_entry_arc.lock() {
+        Ok(object) => object.to_owned(),
+        Err(err) => {
+            eprintln!("ERROR: Failed to get value under current_selected_entry_arc ARC!\n{err}");
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+            return;
+        }
+    };
+    // recorded only once the content of the file is in the entry
+    let file_path = file_dialog.filename().to_string_lossy().to_string();
+
+    if file_size > content_stream::STREAM_THRESHOLD {
+        // encrypt the file chunk by chunk straight into the db
+        let content_stream_value = match vault_db_arc.lock() {
+            Ok(vault_db) => match content_stream::import_file(&vault_db, &file_path) {
+                Ok(content_stream_value) => content_stream_value,
+                Err(_) => {
+                    status_label.set_label("There was an error reading the contents of the file!");
+                    status_label.show();
+                    return;
+                }
+            },
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under vault_db_arc ARC!\n{err}");
+                status_label.set_label("There was a Poison Error, try again, or try to restart!");
+                status_label.show();
+                return;
+            }
+        };
+
+        match content_arc.lock() {
+            Ok(mut content) => {
+                content.set_value("File content is too big to be displayed!\nContent has been saved to the database automatically!");
+                content.deactivate();
+            }
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under content_arc ARC!\n{err}");
+            }
+        };
+
+        match super::core::update_content_in_entry(
+            current_selected_entry_arc,
+            vault_db_arc.clone(),
+            ecies_arc.clone(),
+            vault_arc.clone(),
+            Vec::new(),
+            Some(content_stream_value.to_owned()),
+        ) {
+            Ok(_) => audit::record(
+                &vault_db_arc,
+                &ecies_arc,
+                &vault_arc,
+                AuditAction::FileSelected,
+                &selected_item,
+                &file_path,
+            ),
+            Err(err) => {
+                // nothing points to the new chunks, so don't keep them around
+                super::core::remove_old_stream(vault_db_arc, &content_stream_value);
+                show_update_content_err(&mut status_label, err);
+            }
+        };
+        return;
+    }
+
     // get the content inside the file
     let file_content = match fs::read(file_dialog.filename()) {
         Ok(string) => string,
@@ -613,38 +758,33 @@ pub fn select_file_button(
             vault_db_arc.clone(),
             ecies_arc.clone(),
             vault_arc.clone(),
-            db_entries_dict_arc.clone(),
             file_content,
+            None,
         ) {
-            Ok(_) => return,
-            Err(UpdateContentErr::PoisonErr(_)) => {
-                status_label.set_label("There was a Poison Error, try again, or try to restart!");
-                status_label.show();
-                return;
-            }
-            Err(UpdateContentErr::DisplayNotInSync(_)) => {
-                status_label.set_label(
-                    "What's on screen is not in sync with what's in memory, try again or restart!",
-                );
-                status_label.show();
-                return;
-            }
-            Err(UpdateContentErr::MemoryNotInSync(_)) => {
-                status_label.set_label(
-                    "What's in memory is not in sync with what's in storage, please restart!",
-                );
-                status_label.show();
-                return;
-            }
-            Err(UpdateContentErr::UnknownError(_)) => {
-                process::exit(100);
-            }
+            Ok(_) => audit::record(
+                &vault_db_arc,
+                &ecies_arc,
+                &vault_arc,
+                AuditAction::FileSelected,
+                &selected_item,
+                &file_path,
+            ),
+            Err(err) => show_update_content_err(&mut status_label, err),
         };
     } else {
         match str::from_utf8(&file_content) {
             Ok(string) => {
                 content.set_value(string);
                 content.activate();
+                // the text only reaches the entry when it's saved, but it came from the file
+                audit::record(
+                    &vault_db_arc,
+                    &ecies_arc,
+                    &vault_arc,
+                    AuditAction::FileSelected,
+                    &selected_item,
+                    &file_path,
+                );
             }
             Err(_) => {
                 content.set_value("Content is not in utf8 so it can't be displayed!\nThe content has been saved to database automatically!");
@@ -655,35 +795,48 @@ pub fn select_file_button(
                     vault_db_arc.clone(),
                     ecies_arc.clone(),
                     vault_arc.clone(),
-                    db_entries_dict_arc.clone(),
                     file_content,
+                    None,
                 ) {
-                    Ok(_) => return,
-                    Err(UpdateContentErr::PoisonErr(_)) => {
-                        status_label
-                            .set_label("There was a Poison Error, try again, or try to restart!");
-                        status_label.show();
-                        return;
-                    }
-                    Err(UpdateContentErr::DisplayNotInSync(_)) => {
-                        status_label.set_label(
-                            "What's on screen is not in sync with what's in memory, try again or restart!",
-                        );
-                        status_label.show();
-                        return;
-                    }
-                    Err(UpdateContentErr::MemoryNotInSync(_)) => {
-                        status_label.set_label(
-                            "What's in memory is not in sync with what's in storage, please restart!",
-                        );
-                        status_label.show();
-                        return;
-                    }
-                    Err(UpdateContentErr::UnknownError(_)) => {
-                        process::exit(100);
-                    }
+                    Ok(_) => audit::record(
+                        &vault_db_arc,
+                        &ecies_arc,
+                        &vault_arc,
+                        AuditAction::FileSelected,
+                        &selected_item,
+                        &file_path,
+                    ),
+                    Err(err) => show_update_content_err(&mut status_label, err),
                 };
             }
         }
     }
 }
+
+///
+/// Displays the right message in the status label for an error
+/// returned when updating the content of an entry.
+///
+fn show_update_content_err(status_label: &mut frame::Frame, err: UpdateContentErr) {
+    match err {
+        UpdateContentErr::PoisonErr(_) => {
+            status_label.set_label("There was a Poison Error, try again, or try to restart!");
+            status_label.show();
+        }
+        UpdateContentErr::DisplayNotInSync(_) => {
+            status_label.set_label(
+                "What's on screen is not in sync with what's in memory, try again or restart!",
+            );
+            status_label.show();
+        }
+        UpdateContentErr::MemoryNotInSync(_) => {
+            status_label.set_label(
+                "What's in memory is not in sync with what's in storage, please restart!",
+            );
+            status_label.show();
+        }
+        UpdateContentErr::UnknownError(_) => {
+            process::exit(100);
+        }
+    };
+}
@@ -10,6 +10,7 @@ use std::sync::{Arc, Mutex};
 pub fn create(
     is_windows: bool,
     vault: Vault,
+    failed_logins: u32,
     login_wind: &mut DoubleWindow,
 ) -> fltk::window::DoubleWindow {
     //
@@ -21,7 +22,7 @@ pub fn create(
     wind.set_xclass("estash");
 
     // entries coloumn
-    let mut entries = tree::Tree::default().with_size(200, 475);
+    let mut entries = tree::Tree::default().with_size(200, 450);
     entries.set_color(Color::from_rgb(14, 14, 14));
     entries.set_selection_color(Color::from_rgb(140, 140, 140).darker());
     entries.set_item_label_fgcolor(Color::from_rgb(140, 140, 140));
@@ -45,6 +46,16 @@ pub fn create(
     entrie_add_button.set_label_color(Color::from_rgb(140, 140, 140));
     let entrie_add_button_arc = Arc::new(Mutex::new(entrie_add_button.clone()));
 
+    // vault tools menu
+    let mut vault_menu = menu::MenuButton::default()
+        .with_size(200, 25)
+        .below_of(&entrie_add_input, 0)
+        .with_label("Vault");
+    vault_menu.set_color(Color::from_rgb(43, 43, 43));
+    vault_menu.set_label_color(Color::from_rgb(140, 140, 140));
+    vault_menu.set_text_color(Color::from_rgb(140, 140, 140));
+    let vault_menu_arc = Arc::new(Mutex::new(vault_menu.clone()));
+
     // entry name
     let mut entrie_name = fltk::frame::Frame::default()
         .with_size(750, 35)
@@ -218,11 +229,25 @@ pub fn create(
     let vault_db = super::core::load_vault(is_windows, &vault);
 
     // load current entries in db and display them
-    // + save them in a dict where the key is the unecrypted value and the value is the one
-    // encrypted
+    super::core::load_entries(&vault, vault_db.clone(), ecies.clone(), &mut entries);
+
+    // every callback shares the one copy of the vault keys
+    let is_team = vault.is_team;
+    let vault_arc = Arc::new(Mutex::new(vault));
 
-    let db_entries_dict =
-        super::core::load_entries(&vault, vault_db.clone(), ecies.clone(), &mut entries);
+    let opened_detail = if failed_logins > 0 {
+        format!("after {failed_logins} failed logins")
+    } else {
+        String::new()
+    };
+    super::audit::record(
+        &vault_db,
+        &ecies,
+        &vault_arc,
+        super::audit::AuditAction::Opened,
+        "",
+        &opened_detail,
+    );
 
     //
     //  Window callbacks
@@ -245,6 +270,7 @@ pub fn create(
     let delete_button_arc_clone = delete_button_arc.clone();
     let install_button_arc_clone = install_button_arc.clone();
     let enable_install_path_arc_clone = enable_install_path_arc.clone();
+    let vault_menu_arc_clone = vault_menu_arc.clone();
     // wind resize callback
     wind.resize_callback(move |_, _, _, w, h| {
         super::dry_callbacks::wind_resize_callback(
@@ -252,6 +278,7 @@ pub fn create(
             h,
             entrie_add_input_arc_clone.clone(),
             entrie_add_button_arc.clone(),
+            vault_menu_arc_clone.clone(),
             entrie_name_arc_clone.clone(),
             install_path_label_arc_clone.clone(),
             enable_install_path_arc_clone.clone(),
@@ -272,8 +299,7 @@ pub fn create(
 
     // clone the needed arc references
     let entrie_name_arc_clone = entrie_name_arc.clone();
-    let vault_arc_clone = Arc::new(Mutex::new(vault.clone()));
-    let db_entries_dict_arc_clone = db_entries_dict.clone();
+    let vault_arc_clone = vault_arc.clone();
     let current_selected_entry_arc_clone = current_selected_entry.clone();
     let ecies_arc_clone = ecies.clone();
     let notes_label_arc_clone = notes_label_arc.clone();
@@ -311,18 +337,21 @@ pub fn create(
             install_button_arc_clone.clone(),
             status_label_arc_clone.clone(),
             current_selected_entry_arc_clone.clone(),
-            db_entries_dict_arc_clone.clone(),
             vault_db_arc_clone.clone(),
             ecies_arc_clone.clone(),
             vault_arc_clone.clone(),
         );
+        super::path_vars_callbacks::update_path_preview(
+            install_path_label_arc_clone.clone(),
+            install_path_arc_clone.clone(),
+            vault_db_arc_clone.clone(),
+        );
     });
 
     // clone the needed arc references
     let entrie_add_input_arc_clone = entrie_add_input_arc.clone();
-    let vault_arc_clone = Arc::new(Mutex::new(vault.clone()));
+    let vault_arc_clone = vault_arc.clone();
     let ecies_arc_clone = ecies.clone();
-    let db_entries_dict_arc_clone = db_entries_dict.clone();
     let vault_db_arc_clone = vault_db.clone();
     let entries_arc_clone = entries_arc.clone();
     // set entrie add button callback
@@ -332,15 +361,13 @@ pub fn create(
             vault_db_arc_clone.clone(),
             vault_arc_clone.clone(),
             ecies_arc_clone.clone(),
-            db_entries_dict_arc_clone.clone(),
             entries_arc_clone.clone(),
         );
     });
 
     // clone the needed arc references
-    let vault_arc_clone = Arc::new(Mutex::new(vault.clone()));
+    let vault_arc_clone = vault_arc.clone();
     let current_selected_entry_arc_clone = current_selected_entry.clone();
-    let db_entries_dict_arc_clone = db_entries_dict.clone();
     let ecies_arc_clone = ecies.clone();
     let vault_db_arc_clone = vault_db.clone();
     let notes_arc_clone = notes_arc.clone();
@@ -357,22 +384,23 @@ pub fn create(
             current_selected_entry_arc_clone.clone(),
             vault_db_arc_clone.clone(),
             vault_arc_clone.clone(),
-            db_entries_dict_arc_clone.clone(),
             ecies_arc_clone.clone(),
         );
     });
 
     // clone the needed arc references
     let current_selected_entry_arc_clone = current_selected_entry.clone();
-    let db_entries_dict_arc_clone = db_entries_dict.clone();
     let entries_arc_clone = entries_arc.clone();
     let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
     // set delete button callback
     delete_button.set_callback(move |_| {
         super::value_callbacks::delete_button_callback(
             vault_db_arc_clone.clone(),
+            vault_arc_clone.clone(),
+            ecies_arc_clone.clone(),
             current_selected_entry_arc_clone.clone(),
-            db_entries_dict_arc_clone.clone(),
             entries_arc_clone.clone(),
         );
     });
@@ -381,8 +409,7 @@ pub fn create(
     let install_path_arc_clone = install_path_arc.clone();
     let content_arc_clone = content_arc.clone();
     let status_label_arc_clone = status_label_arc.clone();
-    let vault_arc_clone = Arc::new(Mutex::new(vault.clone()));
-    let db_entries_dict_arc_clone = db_entries_dict.clone();
+    let vault_arc_clone = vault_arc.clone();
     let ecies_arc_clone = ecies.clone();
     let current_selected_entry_arc_clone = current_selected_entry.clone();
     let vault_db_arc_clone = vault_db.clone();
@@ -395,20 +422,34 @@ pub fn create(
             vault_db_arc_clone.clone(),
             ecies_arc_clone.clone(),
             current_selected_entry_arc_clone.clone(),
-            db_entries_dict_arc_clone.clone(),
             status_label_arc_clone.clone(),
-            is_windows,
         );
     });
 
     // clone the needed arc references
     let install_path_arc_clone = install_path_arc.clone();
     let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
     // set install path check button callback
     install_path_check_button.set_callback(move |_| {
         super::dry_callbacks::install_path_check_button_callback(
             status_label_arc_clone.clone(),
             install_path_arc_clone.clone(),
+            vault_db_arc_clone.clone(),
+        );
+    });
+
+    // clone the needed arc references
+    let install_path_label_arc_clone = install_path_label_arc.clone();
+    let install_path_arc_clone = install_path_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    // preview where the install path resolves to while it's typed
+    install_path.set_trigger(enums::CallbackTrigger::Changed);
+    install_path.set_callback(move |_| {
+        super::path_vars_callbacks::update_path_preview(
+            install_path_label_arc_clone.clone(),
+            install_path_arc_clone.clone(),
+            vault_db_arc_clone.clone(),
         );
     });
 
@@ -416,10 +457,9 @@ pub fn create(
     let content_arc_clone = content_arc.clone();
     let status_label_arc_clone = status_label_arc.clone();
     let current_selected_entry_arc_clone = current_selected_entry.clone();
-    let vault_arc_clone = Arc::new(Mutex::new(vault.clone()));
+    let vault_arc_clone = vault_arc.clone();
     let vault_db_arc_clone = vault_db.clone();
     let ecies_arc_clone = ecies.clone();
-    let db_entries_dict_arc_clone = db_entries_dict.clone();
     // set select file button callback
     select_file_button.set_callback(move |_| {
         super::value_callbacks::select_file_button(
@@ -429,7 +469,6 @@ pub fn create(
             vault_db_arc_clone.clone(),
             ecies_arc_clone.clone(),
             vault_arc_clone.clone(),
-            db_entries_dict_arc_clone.clone(),
         );
     });
 
@@ -445,14 +484,1007 @@ pub fn create(
     });
 
     // clone the needed arc references
+    let install_path_label_arc_clone = install_path_label_arc.clone();
     let install_path_arc_clone = install_path_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
     // set enable/disable install path button
     enable_install_path.set_callback(move |b| {
         super::dry_callbacks::enable_install_path_button_callback(
             b,
             install_path_arc_clone.clone(),
         );
+        super::path_vars_callbacks::update_path_preview(
+            install_path_label_arc_clone.clone(),
+            install_path_arc_clone.clone(),
+            vault_db_arc_clone.clone(),
+        );
     });
 
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add verify vault menu item
+    vault_menu.add(
+        "Verify Vault",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::tools_callbacks::verify_vault_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+                is_windows,
+            );
+        },
+    );
+
+    // team vaults have no password, their members open them with their own
+    if !is_team {
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        // add change password menu item
+        vault_menu.add(
+            "Change Password",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::tools_callbacks::change_password_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        // add list slots menu item
+        vault_menu.add(
+            "Key Slots/List Slots",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::tools_callbacks::list_key_slots_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        // add add password slot menu item
+        vault_menu.add(
+            "Key Slots/Add Password",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::tools_callbacks::add_password_slot_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        // add add recovery code slot menu item
+        vault_menu.add(
+            "Key Slots/Add Recovery Code",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::tools_callbacks::add_recovery_code_slot_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        // add revoke slot menu item
+        vault_menu.add(
+            "Key Slots/Revoke Slot",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::tools_callbacks::revoke_key_slot_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        // add login protection menu item
+        vault_menu.add(
+            "Login Protection",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::tools_callbacks::login_protection_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                );
+            },
+        );
+    }
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_arc_clone = vault_arc.clone();
+    // add export public key menu item
+    vault_menu.add(
+        "Keyring/Export Public Key",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::keyring_callbacks::export_public_key_callback(
+                status_label_arc_clone.clone(),
+                vault_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import contact menu item
+    vault_menu.add(
+        "Keyring/Import Contact",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::keyring_callbacks::import_contact_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add list contacts menu item
+    vault_menu.add(
+        "Keyring/List Contacts",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::keyring_callbacks::list_contacts_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add remove contact menu item
+    vault_menu.add(
+        "Keyring/Remove Contact",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::keyring_callbacks::remove_contact_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add share entry menu item
+    vault_menu.add(
+        "Share/Share Entry",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::share_callbacks::share_entry_callback(
+                status_label_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import shared entry menu item
+    vault_menu.add(
+        "Share/Import Shared Entry",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::share_callbacks::import_shared_entry_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import keepass menu item
+    vault_menu.add(
+        "Import/KeePass (KDBX)",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::import_callbacks::import_keepass_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import bitwarden menu item
+    vault_menu.add(
+        "Import/Bitwarden JSON",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::import_callbacks::import_bitwarden_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import pass store menu item
+    vault_menu.add(
+        "Import/pass Store",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::import_callbacks::import_pass_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import plaintext export menu item
+    vault_menu.add(
+        "Import/EStash Plaintext Export",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::import_callbacks::import_plain_export_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import dotfiles from directory menu item
+    vault_menu.add(
+        "Import/Dotfiles from Directory",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::import_callbacks::import_dotfiles_dir_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add import dotfiles from paths menu item
+    vault_menu.add(
+        "Import/Dotfiles from Paths",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::import_callbacks::import_dotfiles_paths_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add export plaintext json menu item
+    vault_menu.add(
+        "Export/Plaintext JSON",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::export_callbacks::export_plain_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+                super::export::ExportFormat::Json,
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add export plaintext csv menu item
+    vault_menu.add(
+        "Export/Plaintext CSV",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::export_callbacks::export_plain_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+                super::export::ExportFormat::Csv,
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let content_label_arc_clone = content_label_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add toggle template menu item
+    vault_menu.add(
+        "Templates/Toggle Template",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::templates_callbacks::toggle_template_callback(
+                status_label_arc_clone.clone(),
+                content_label_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let content_arc_clone = content_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add preview rendered template menu item
+    vault_menu.add(
+        "Templates/Preview Rendered",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::templates_callbacks::preview_template_callback(
+                status_label_arc_clone.clone(),
+                content_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add set template variable menu item
+    vault_menu.add(
+        "Templates/Set Variable",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::templates_callbacks::set_template_variable_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add list template variables menu item
+    vault_menu.add(
+        "Templates/List Variables",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::templates_callbacks::list_template_variables_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add edit install targets menu item
+    vault_menu.add(
+        "Install Targets/Edit Targets",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::install_targets_callbacks::edit_install_targets_callback(
+                status_label_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add show install targets menu item
+    vault_menu.add(
+        "Install Targets/Show Targets",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::install_targets_callbacks::show_install_targets_callback(
+                status_label_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add toggle symlink mode menu item
+    vault_menu.add(
+        "Installed Files/Toggle Symlink Mode",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::installs_callbacks::toggle_symlink_mode_callback(
+                status_label_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add installed files status menu item
+    vault_menu.add(
+        "Installed Files/Status",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::installs_callbacks::installed_files_status_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let content_arc_clone = content_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add check drift menu item
+    vault_menu.add(
+        "Installed Files/Check Drift",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::drift_callbacks::check_drift_callback(
+                status_label_arc_clone.clone(),
+                content_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add uninstall entry menu item
+    vault_menu.add(
+        "Installed Files/Uninstall Entry",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::installs_callbacks::uninstall_entry_callback(
+                status_label_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add uninstall all menu item
+    vault_menu.add(
+        "Installed Files/Uninstall All",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::installs_callbacks::uninstall_all_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let current_selected_entry_arc_clone = current_selected_entry.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add edit post-install hook menu item
+    vault_menu.add(
+        "Post-Install Hook/Edit Hook",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::hooks_callbacks::edit_hook_callback(
+                status_label_arc_clone.clone(),
+                current_selected_entry_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let install_path_label_arc_clone = install_path_label_arc.clone();
+    let install_path_arc_clone = install_path_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    // add set path variable menu item
+    vault_menu.add(
+        "Path Variables/Set Variable",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::path_vars_callbacks::set_path_variable_callback(
+                status_label_arc_clone.clone(),
+                install_path_label_arc_clone.clone(),
+                install_path_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    // add list path variables menu item
+    vault_menu.add(
+        "Path Variables/List Variables",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::path_vars_callbacks::list_path_variables_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add view audit log menu item
+    vault_menu.add(
+        "Audit Log/View Log",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::audit_callbacks::view_audit_log_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add export audit log menu item
+    vault_menu.add(
+        "Audit Log/Export to JSON Lines",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::audit_callbacks::export_audit_log_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add save to directory menu item
+    vault_menu.add(
+        "Directory Snapshot/Save Snapshot",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::dir_store_callbacks::save_to_directory_callback(
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add load from directory menu item
+    vault_menu.add(
+        "Directory Snapshot/Load Snapshot",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::dir_store_callbacks::load_from_directory_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add sync with directory menu item
+    vault_menu.add(
+        "Sync/Sync with Directory",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::sync_callbacks::sync_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let entries_arc_clone = entries_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    let vault_arc_clone = vault_arc.clone();
+    let ecies_arc_clone = ecies.clone();
+    // add resolve conflicts menu item
+    vault_menu.add(
+        "Sync/Resolve Conflicts",
+        enums::Shortcut::None,
+        menu::MenuFlag::Normal,
+        move |_| {
+            super::sync_callbacks::resolve_conflicts_callback(
+                status_label_arc_clone.clone(),
+                entries_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+                vault_arc_clone.clone(),
+                ecies_arc_clone.clone(),
+            );
+        },
+    );
+
+    if is_team {
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_db_arc_clone = vault_db.clone();
+        let vault_arc_clone = vault_arc.clone();
+        let ecies_arc_clone = ecies.clone();
+        // add list members menu item
+        vault_menu.add(
+            "Team/List Members",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::team_callbacks::list_members_callback(
+                    status_label_arc_clone.clone(),
+                    vault_db_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                    ecies_arc_clone.clone(),
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_db_arc_clone = vault_db.clone();
+        let vault_arc_clone = vault_arc.clone();
+        let ecies_arc_clone = ecies.clone();
+        // add add member menu item
+        vault_menu.add(
+            "Team/Add Member",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::team_callbacks::add_member_callback(
+                    status_label_arc_clone.clone(),
+                    vault_db_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                    ecies_arc_clone.clone(),
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_db_arc_clone = vault_db.clone();
+        let vault_arc_clone = vault_arc.clone();
+        let ecies_arc_clone = ecies.clone();
+        let mut wind_clone = wind.clone();
+        // add remove member menu item
+        vault_menu.add(
+            "Team/Remove Member",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::team_callbacks::remove_member_callback(
+                    status_label_arc_clone.clone(),
+                    vault_db_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                    ecies_arc_clone.clone(),
+                    &mut wind_clone,
+                    is_windows,
+                );
+            },
+        );
+    } else {
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        let ecies_arc_clone = ecies.clone();
+        // add create team vault menu item
+        vault_menu.add(
+            "Team/Create Team Vault",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::team_callbacks::create_team_vault_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                    ecies_arc_clone.clone(),
+                    is_windows,
+                );
+            },
+        );
+
+        // clone the needed arc references
+        let status_label_arc_clone = status_label_arc.clone();
+        let vault_arc_clone = vault_arc.clone();
+        let ecies_arc_clone = ecies.clone();
+        let mut wind_clone = wind.clone();
+        // add open team vault menu item
+        vault_menu.add(
+            "Team/Open Team Vault",
+            enums::Shortcut::None,
+            menu::MenuFlag::Normal,
+            move |_| {
+                super::team_callbacks::open_team_vault_callback(
+                    status_label_arc_clone.clone(),
+                    vault_arc_clone.clone(),
+                    ecies_arc_clone.clone(),
+                    &mut wind_clone,
+                    is_windows,
+                );
+            },
+        );
+    }
+
+    // clone the needed arc references
+    let status_label_arc_clone = status_label_arc.clone();
+    let vault_db_arc_clone = vault_db.clone();
+    // add compress contents toggle menu item
+    vault_menu.add(
+        super::tools_callbacks::COMPRESS_CONTENTS_ITEM,
+        enums::Shortcut::None,
+        menu::MenuFlag::Toggle,
+        move |m| {
+            super::tools_callbacks::compress_contents_callback(
+                m,
+                status_label_arc_clone.clone(),
+                vault_db_arc_clone.clone(),
+            );
+        },
+    );
+    // show the current state of the setting
+    let compression_enabled = match vault_db.lock() {
+        Ok(vault_db) => super::settings::is_compression_enabled(&vault_db),
+        Err(_) => false,
+    };
+    if compression_enabled {
+        if let Some(mut item) = vault_menu.find_item(super::tools_callbacks::COMPRESS_CONTENTS_ITEM)
+        {
+            item.set();
+        }
+    }
+
+    // let the user know someone tried to get in since the last unlock
+    if failed_logins > 0 {
+        match status_label_arc.lock() {
+            Ok(mut status_label) => {
+                status_label.set_label(&format!(
+                    "There were {failed_logins} failed logins since the last time this vault was unlocked!"
+                ));
+                status_label.show();
+            }
+            Err(err) => {
+                eprintln!("ERROR: Failed to get value under status_label_arc ARC!\n{err}");
+            }
+        };
+    }
+
     wind
 }